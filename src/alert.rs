@@ -0,0 +1,120 @@
+use crate::duration;
+use crate::predicate::Predicate;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A rolling condition like `count(level=="error") > 10 per 1m`.
+#[derive(Clone, Debug)]
+pub struct Alert {
+    expression: String,
+    predicate: Option<Predicate>,
+    operator: String,
+    threshold: usize,
+    window: Duration,
+    matches: VecDeque<Instant>,
+    firing: bool,
+}
+
+impl Alert {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "expected `count(PREDICATE) > N per DURATION` in `{}`",
+                expression
+            )
+        };
+        let rest = expression
+            .trim()
+            .strip_prefix("count(")
+            .ok_or_else(invalid)?;
+        let close = rest.rfind(')').ok_or_else(invalid)?;
+        let predicate = match rest[..close].trim() {
+            "" | "*" => None,
+            predicate => Some(Predicate::parse(predicate)?),
+        };
+        let (condition, window) = rest[close + 1..].split_once(" per ").ok_or_else(invalid)?;
+        let condition = condition.trim();
+        let split = condition
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let operator = condition[..split].trim();
+        if !matches!(operator, ">" | ">=" | "<" | "<=" | "==") {
+            return Err(invalid());
+        }
+        Ok(Alert {
+            expression: expression.trim().to_string(),
+            predicate,
+            operator: operator.to_string(),
+            threshold: condition[split..].trim().parse().map_err(|_| invalid())?,
+            window: duration::parse(window.trim())?,
+            matches: VecDeque::new(),
+            firing: false,
+        })
+    }
+
+    /// Counts the record, returns a message when the condition starts to hold.
+    pub fn observe(&mut self, object: &Map<String, Value>, now: Instant) -> Option<String> {
+        if self
+            .predicate
+            .as_ref()
+            .is_none_or(|predicate| predicate.matches(object))
+        {
+            self.matches.push_back(now);
+        }
+        while let Some(oldest) = self.matches.front() {
+            if now.duration_since(*oldest) <= self.window {
+                break;
+            }
+            self.matches.pop_front();
+        }
+        let count = self.matches.len();
+        let holds = match self.operator.as_str() {
+            ">" => count > self.threshold,
+            ">=" => count >= self.threshold,
+            "<" => count < self.threshold,
+            "<=" => count <= self.threshold,
+            _ => count == self.threshold,
+        };
+        let starts = holds && !self.firing;
+        self.firing = holds;
+        starts.then(|| format!("ALERT {} (count {})", self.expression, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_observe() {
+        let mut alert = Alert::parse(r#"count(level=="error") > 2 per 1m"#).unwrap();
+        let error = json!({"level": "error"});
+        let info = json!({"level": "info"});
+        let start = Instant::now();
+        let mut observe = |record: &Value, seconds| {
+            alert.observe(
+                record.as_object().unwrap(),
+                start + Duration::from_secs(seconds),
+            )
+        };
+        assert_eq!(observe(&error, 0), None);
+        assert_eq!(observe(&error, 1), None);
+        assert_eq!(observe(&info, 2), None);
+        assert_eq!(
+            observe(&error, 3).as_deref(),
+            Some(r#"ALERT count(level=="error") > 2 per 1m (count 3)"#)
+        );
+        assert_eq!(observe(&error, 4), None);
+        assert_eq!(observe(&info, 70), None);
+        assert_eq!(observe(&error, 71), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(Alert::parse("count() >= 100 per 10s").is_ok());
+        assert!(Alert::parse("count(level=error) > ten per 1m").is_err());
+        assert!(Alert::parse("sum(ms) > 10 per 1m").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use serde_json::{Map, Value};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Zeroes the host bits of an IP address field, keeping a /24 IPv4 or /48 IPv6 network.
+pub struct IpAnonymizer {
+    field: String,
+}
+
+impl IpAnonymizer {
+    pub fn new(field: &str) -> Self {
+        IpAnonymizer {
+            field: field.to_string(),
+        }
+    }
+
+    pub fn anonymize(&self, object: &mut Map<String, Value>) -> bool {
+        let value = match object.get_mut(&self.field) {
+            Some(Value::String(value)) => value,
+            _ => return false,
+        };
+        match anonymize(value) {
+            Some(anonymized) => {
+                *value = anonymized;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn anonymize(address: &str) -> Option<String> {
+    if let Ok(address) = address.parse::<IpAddr>() {
+        return Some(mask(address).to_string());
+    }
+    let address = address.parse::<SocketAddr>().ok()?;
+    Some(SocketAddr::new(mask(address.ip()), address.port()).to_string())
+}
+
+fn mask(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, c, _] = address.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(address) => {
+            let [a, b, c, ..] = address.segments();
+            IpAddr::V6(Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymize() {
+        for (input, output) in [
+            ("192.168.1.42", Some("192.168.1.0")),
+            ("192.168.1.42:8080", Some("192.168.1.0:8080")),
+            (
+                "2001:db8:85a3:8d3:1319:8a2e:370:7348",
+                Some("2001:db8:85a3::"),
+            ),
+            ("[2001:db8:85a3::1]:443", Some("[2001:db8:85a3::]:443")),
+            ("localhost", None),
+        ] {
+            assert_eq!(anonymize(input).as_deref(), output);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::binary::{float, invalid, read_bytes, read_first, read_uint, read_varint, zigzag};
+use crate::format::binary_value;
+use flate2::read::DeflateDecoder;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{self, Read};
+
+const MAGIC: &[u8] = b"Obj\x01";
+
+#[derive(Clone, Debug)]
+enum Schema {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Record(Vec<(String, Schema)>),
+    Enum(Vec<String>),
+    Array(Box<Schema>),
+    Map(Box<Schema>),
+    Union(Vec<Schema>),
+    Fixed(usize),
+    /// Reference to a named record, enum or fixed type.
+    Named(String),
+}
+
+/// Reads the records of an Avro object container file, using the schema embedded in its header.
+pub struct AvroReader<R> {
+    reader: R,
+    schema: Schema,
+    names: HashMap<String, Schema>,
+    deflate: bool,
+    sync: Vec<u8>,
+    block: io::Cursor<Vec<u8>>,
+    remaining: u64,
+}
+
+impl<R: Read> AvroReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        if read_bytes(&mut reader, MAGIC.len())? != MAGIC {
+            return Err(invalid("input isn't an Avro object container file"));
+        }
+        let mut metadata = HashMap::new();
+        read_blocks(&mut reader, |reader| {
+            let key = read_string(reader)?;
+            let length = read_long(reader)? as usize;
+            metadata.insert(key, read_bytes(reader, length)?);
+            Ok(())
+        })?;
+        let deflate = match metadata.get("avro.codec").map(Vec::as_slice) {
+            None | Some(b"null") => false,
+            Some(b"deflate") => true,
+            Some(codec) => {
+                let codec = String::from_utf8_lossy(codec);
+                let message = format!("unsupported Avro codec {}", codec);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        };
+        let schema = metadata
+            .get("avro.schema")
+            .and_then(|schema| serde_json::from_slice(schema).ok())
+            .ok_or_else(|| invalid("Avro file has no valid schema"))?;
+        let mut names = HashMap::new();
+        let schema = parse_schema(&schema, &mut names)?;
+        let sync = read_bytes(&mut reader, 16)?;
+        Ok(AvroReader {
+            reader,
+            schema,
+            names,
+            deflate,
+            sync,
+            block: io::Cursor::new(Vec::new()),
+            remaining: 0,
+        })
+    }
+
+    pub fn next_record(&mut self) -> io::Result<Option<Value>> {
+        while self.remaining == 0 {
+            let first = match read_first(&mut self.reader)? {
+                Some(first) => first,
+                None => return Ok(None),
+            };
+            let count = read_long(&mut (&[first][..]).chain(&mut self.reader))?;
+            let size = read_long(&mut self.reader)? as usize;
+            let data = read_bytes(&mut self.reader, size)?;
+            if read_bytes(&mut self.reader, 16)? != self.sync {
+                return Err(invalid("Avro block isn't followed by the sync marker"));
+            }
+            let data = match self.deflate {
+                true => {
+                    let mut inflated = Vec::new();
+                    DeflateDecoder::new(&data[..]).read_to_end(&mut inflated)?;
+                    inflated
+                }
+                false => data,
+            };
+            self.block = io::Cursor::new(data);
+            self.remaining = count as u64;
+        }
+        self.remaining -= 1;
+        let schema = self.schema.clone();
+        self.read_datum(&schema).map(Some)
+    }
+
+    fn read_datum(&mut self, schema: &Schema) -> io::Result<Value> {
+        let reader = &mut self.block;
+        Ok(match schema {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Bool(read_uint(reader, 1)? != 0),
+            Schema::Int | Schema::Long => Value::from(read_long(reader)?),
+            Schema::Float => {
+                let bytes = read_bytes(reader, 4)?;
+                float(f32::from_le_bytes(bytes[..].try_into().unwrap()) as f64)
+            }
+            Schema::Double => {
+                let bytes = read_bytes(reader, 8)?;
+                float(f64::from_le_bytes(bytes[..].try_into().unwrap()))
+            }
+            Schema::Bytes => {
+                let length = read_long(reader)? as usize;
+                binary_value(&read_bytes(reader, length)?)
+            }
+            Schema::String => Value::String(read_string(reader)?),
+            Schema::Fixed(size) => binary_value(&read_bytes(reader, *size)?),
+            Schema::Enum(symbols) => {
+                let index = read_long(reader)? as usize;
+                let symbol = symbols
+                    .get(index)
+                    .ok_or_else(|| invalid("invalid enum index"))?;
+                Value::String(symbol.clone())
+            }
+            Schema::Union(branches) => {
+                let index = read_long(reader)? as usize;
+                let branch = branches
+                    .get(index)
+                    .ok_or_else(|| invalid("invalid union index"))?;
+                self.read_datum(branch)?
+            }
+            Schema::Record(fields) => {
+                let mut record = Map::new();
+                for (name, schema) in fields {
+                    record.insert(name.clone(), self.read_datum(schema)?);
+                }
+                Value::Object(record)
+            }
+            Schema::Array(items) => {
+                let mut array = Vec::new();
+                while let Some(count) = self.read_block_count()? {
+                    for _ in 0..count {
+                        array.push(self.read_datum(items)?);
+                    }
+                }
+                Value::Array(array)
+            }
+            Schema::Map(values) => {
+                let mut map = Map::new();
+                while let Some(count) = self.read_block_count()? {
+                    for _ in 0..count {
+                        let key = read_string(&mut self.block)?;
+                        map.insert(key, self.read_datum(values)?);
+                    }
+                }
+                Value::Object(map)
+            }
+            Schema::Named(name) => {
+                let schema = lookup(&self.names, name)?.clone();
+                self.read_datum(&schema)?
+            }
+        })
+    }
+
+    /// Returns the number of items in the next block of an array or map, `None` after the last one.
+    fn read_block_count(&mut self) -> io::Result<Option<u64>> {
+        match read_long(&mut self.block)? {
+            0 => Ok(None),
+            count if count < 0 => {
+                read_long(&mut self.block)?;
+                Ok(Some(count.unsigned_abs()))
+            }
+            count => Ok(Some(count as u64)),
+        }
+    }
+}
+
+fn read_long<R: Read>(reader: &mut R) -> io::Result<i64> {
+    read_varint(reader).map(zigzag)
+}
+
+fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let length = read_long(reader)? as usize;
+    String::from_utf8(read_bytes(reader, length)?)
+        .map_err(|_| invalid("Avro string isn't valid UTF-8"))
+}
+
+/// Reads the blocks of a map in the file header.
+fn read_blocks<R, F>(reader: &mut R, mut read_item: F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(&mut R) -> io::Result<()>,
+{
+    loop {
+        let count = match read_long(reader)? {
+            0 => return Ok(()),
+            count if count < 0 => {
+                read_long(reader)?;
+                count.unsigned_abs()
+            }
+            count => count as u64,
+        };
+        for _ in 0..count {
+            read_item(reader)?;
+        }
+    }
+}
+
+fn lookup<'a>(names: &'a HashMap<String, Schema>, name: &str) -> io::Result<&'a Schema> {
+    let short = name.rsplit('.').next().unwrap_or(name);
+    names.get(name).or_else(|| names.get(short)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown Avro type {}", name),
+        )
+    })
+}
+
+/// Parses a schema, registering named types under their full and short names.
+fn parse_schema(schema: &Value, names: &mut HashMap<String, Schema>) -> io::Result<Schema> {
+    let object = match schema {
+        Value::String(name) => {
+            return Ok(match name.as_str() {
+                "null" => Schema::Null,
+                "boolean" => Schema::Boolean,
+                "int" => Schema::Int,
+                "long" => Schema::Long,
+                "float" => Schema::Float,
+                "double" => Schema::Double,
+                "bytes" => Schema::Bytes,
+                "string" => Schema::String,
+                name => Schema::Named(name.to_string()),
+            })
+        }
+        Value::Array(branches) => {
+            return branches
+                .iter()
+                .map(|branch| parse_schema(branch, names))
+                .collect::<io::Result<_>>()
+                .map(Schema::Union)
+        }
+        Value::Object(object) => object,
+        _ => return Err(invalid("invalid Avro schema")),
+    };
+    let kind = object.get("type").unwrap_or(&Value::Null);
+    let parsed = match kind.as_str() {
+        Some("record") | Some("error") => {
+            let fields = object.get("fields").and_then(Value::as_array);
+            let fields = fields.ok_or_else(|| invalid("Avro record has no fields"))?;
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    let name = field.get("name").and_then(Value::as_str);
+                    let name = name.ok_or_else(|| invalid("Avro field has no name"))?;
+                    let schema = field.get("type").unwrap_or(&Value::Null);
+                    Ok((name.to_string(), parse_schema(schema, names)?))
+                })
+                .collect::<io::Result<_>>()?;
+            Schema::Record(fields)
+        }
+        Some("enum") => Schema::Enum(
+            object
+                .get("symbols")
+                .and_then(Value::as_array)
+                .map(|symbols| {
+                    symbols
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        ),
+        Some("fixed") => {
+            let size = object.get("size").and_then(Value::as_u64);
+            Schema::Fixed(size.ok_or_else(|| invalid("Avro fixed type has no size"))? as usize)
+        }
+        Some("array") => {
+            let items = object.get("items").unwrap_or(&Value::Null);
+            Schema::Array(Box::new(parse_schema(items, names)?))
+        }
+        Some("map") => {
+            let values = object.get("values").unwrap_or(&Value::Null);
+            Schema::Map(Box::new(parse_schema(values, names)?))
+        }
+        _ => return parse_schema(kind, names),
+    };
+    if let Some(name) = object.get("name").and_then(Value::as_str) {
+        let full_name = match object.get("namespace").and_then(Value::as_str) {
+            Some(namespace) if !name.contains('.') => format!("{}.{}", namespace, name),
+            _ => name.to_string(),
+        };
+        let short = full_name.rsplit('.').next().unwrap_or(name).to_string();
+        names.insert(short, parsed.clone());
+        names.insert(full_name, parsed.clone());
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn long(value: i64) -> Vec<u8> {
+        let mut value = ((value << 1) ^ (value >> 63)) as u64;
+        let mut bytes = Vec::new();
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+        bytes
+    }
+
+    fn container(schema: &str, codec: &str, count: i64, block: &[u8]) -> Vec<u8> {
+        let mut file = MAGIC.to_vec();
+        file.extend(long(2));
+        for &(key, value) in &[("avro.schema", schema), ("avro.codec", codec)] {
+            file.extend(long(key.len() as i64));
+            file.extend(key.as_bytes());
+            file.extend(long(value.len() as i64));
+            file.extend(value.as_bytes());
+        }
+        file.extend(long(0));
+        file.extend([7; 16]);
+        file.extend(long(count));
+        file.extend(long(block.len() as i64));
+        file.extend(block);
+        file.extend([7; 16]);
+        file
+    }
+
+    #[test]
+    fn test_records() {
+        let schema = r#"{"type": "record", "name": "app.Log", "fields": [
+            {"name": "msg", "type": "string"},
+            {"name": "code", "type": ["null", "long"]},
+            {"name": "level", "type": {"type": "enum", "name": "Level", "symbols": ["INFO", "WARN"]}},
+            {"name": "tags", "type": {"type": "map", "values": "Level"}}
+        ]}"#;
+        let block = b"\x04hi\x02\x54\x02\x02\x02a\x00\x00\x06bye\x00\x00\x00";
+        let file = container(schema, "null", 2, block);
+        let mut reader = AvroReader::new(&file[..]).unwrap();
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(json!({"msg": "hi", "code": 42, "level": "WARN", "tags": {"a": "INFO"}}))
+        );
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some(json!({"msg": "bye", "code": null, "level": "INFO", "tags": {}}))
+        );
+        assert_eq!(reader.next_record().unwrap(), None);
+    }
+
+    #[test]
+    fn test_header() {
+        assert!(AvroReader::new(&b"PAR1"[..]).is_err());
+        let file = container(r#""string""#, "snappy", 0, b"");
+        assert!(AvroReader::new(&file[..]).is_err());
+        let mut file = container(r#""string""#, "null", 1, b"\x02a");
+        file.pop();
+        assert!(AvroReader::new(&file[..]).unwrap().next_record().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::Framing;
+use serde_json::{Number, Value};
+use std::io::{self, Read};
+
+/// Nesting of arrays and maps decoded at most, like the recursion limit of serde_json, so a
+/// deeply nested value can't overflow the stack.
+pub const MAX_DEPTH: usize = 128;
+
+/// Reads the first byte of a value, or `None` if the input ended before it.
+pub fn read_first<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Reads exactly `length` bytes without trusting the length for the allocation.
+pub fn read_bytes<R: Read>(reader: &mut R, length: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// Reads a big-endian unsigned integer of `size` bytes.
+pub fn read_uint<R: Read>(reader: &mut R, size: usize) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes[8 - size..])?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+/// Reads a little-endian base 128 varint, as used by protobuf and Avro.
+pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = read_uint(reader, 1)?;
+        value |= (byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid("varint is longer than 64 bits"))
+}
+
+/// Reads the next record prefixed by its length, or `None` if the input ended before it.
+pub fn read_frame<R: Read>(reader: &mut R, framing: Framing) -> io::Result<Option<Vec<u8>>> {
+    let first = match read_first(reader)? {
+        Some(first) => first,
+        None => return Ok(None),
+    };
+    let first = [first];
+    let length = {
+        let mut prefix = (&first[..]).chain(&mut *reader);
+        match framing {
+            Framing::Varint => read_varint(&mut prefix)?,
+            Framing::U32be => read_uint(&mut prefix, 4)?,
+            Framing::U32le => (read_uint(&mut prefix, 4)? as u32).swap_bytes().into(),
+        }
+    };
+    read_bytes(reader, length as usize).map(Some)
+}
+
+/// Decodes a zigzag encoded signed integer.
+pub fn zigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Converts a float to a JSON number, or null if it isn't finite.
+pub fn float(number: f64) -> Value {
+    Number::from_f64(number).map_or(Value::Null, Value::Number)
+}
+
+/// Returns the nesting left inside an array or map of a value allowed `depth` levels, or an error
+/// if it is nested deeper.
+pub fn nested(depth: usize) -> io::Result<usize> {
+    depth
+        .checked_sub(1)
+        .ok_or_else(|| invalid("value is nested too deeply"))
+}
+
+/// Returns an error for malformed input.
+pub fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        assert_eq!(read_varint(&mut &b"\x96\x01"[..]).unwrap(), 150);
+        assert!(read_varint(&mut &b"\x80"[..]).is_err());
+        assert_eq!(zigzag(3), -2);
+        assert_eq!(zigzag(4), 2);
+    }
+
+    #[test]
+    fn test_read_frame() {
+        let mut varint = &b"\x02ab\x00\x01"[..];
+        assert_eq!(
+            read_frame(&mut varint, Framing::Varint).unwrap().unwrap(),
+            b"ab"
+        );
+        assert_eq!(
+            read_frame(&mut varint, Framing::Varint).unwrap().unwrap(),
+            b""
+        );
+        assert!(read_frame(&mut varint, Framing::Varint).is_err());
+        let mut u32be = &b"\x00\x00\x00\x01a"[..];
+        assert_eq!(
+            read_frame(&mut u32be, Framing::U32be).unwrap().unwrap(),
+            b"a"
+        );
+        assert_eq!(read_frame(&mut u32be, Framing::U32be).unwrap(), None);
+        let mut u32le = &b"\x01\x00\x00\x00a"[..];
+        assert_eq!(
+            read_frame(&mut u32le, Framing::U32le).unwrap().unwrap(),
+            b"a"
+        );
+    }
+}
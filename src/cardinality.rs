@@ -0,0 +1,151 @@
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+
+/// Number of hash bits selecting a register, for a standard error of about 0.8%.
+const PRECISION: u32 = 14;
+
+/// Estimates the number of distinct values of fields in constant memory
+#[derive(Args, Debug)]
+pub struct CardinalityOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+    /// Fields to count the distinct values of, like `user_id,request_id`
+    #[clap(
+        long,
+        value_name = "FIELD,...",
+        required = true,
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true)
+    )]
+    field: Vec<String>,
+}
+
+pub fn run(opt: &CardinalityOpt) -> io::Result<()> {
+    let mut fields = FieldSketches {
+        sketches: opt
+            .field
+            .iter()
+            .map(|field| (field.clone(), 0, HyperLogLog::default()))
+            .collect(),
+    };
+    if opt.files.is_empty() {
+        fields.add_lines(io::stdin().lock())?;
+    }
+    for path in &opt.files {
+        fields.add_lines(BufReader::new(File::open(path)?))?;
+    }
+    let lines = fields.sketches.iter().map(|(field, records, sketch)| {
+        Ok(json!({"field": field, "records": records, "distinct": sketch.estimate()}).to_string())
+    });
+    crate::print_lines(lines)
+}
+
+/// Counts the records having each field with a sketch of its values.
+struct FieldSketches {
+    sketches: Vec<(String, u64, HyperLogLog)>,
+}
+
+impl FieldSketches {
+    fn add_lines<R: BufRead>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? != 0 {
+            if let Ok(Value::Object(object)) = serde_json::from_slice(&line) {
+                for (field, records, sketch) in &mut self.sketches {
+                    if let Some(value) = object.get(field.as_str()) {
+                        *records += 1;
+                        sketch.add(value);
+                    }
+                }
+            }
+            line.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Counts distinct values approximately, keeping the longest run of leading zeros per register.
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog {
+            registers: vec![0; 1 << PRECISION],
+        }
+    }
+}
+
+impl HyperLogLog {
+    fn add(&mut self, value: &Value) {
+        let mut hasher = DefaultHasher::new();
+        match value {
+            Value::String(string) => string.hash(&mut hasher),
+            value => value.to_string().hash(&mut hasher),
+        }
+        let hash = hasher.finish();
+        let index = (hash >> (64 - PRECISION)) as usize;
+        let rank = ((hash << PRECISION) | 1 << (PRECISION - 1)).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|rank| 2f64.powi(-(*rank as i32)))
+            .sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|rank| **rank == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // Linear counting is more accurate while many registers are still empty.
+            return (m * (m / zeros as f64).ln()).round() as u64;
+        }
+        estimate.round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn estimate(values: impl Iterator<Item = Value>) -> u64 {
+        let mut sketch = HyperLogLog::default();
+        for value in values {
+            sketch.add(&value);
+        }
+        sketch.estimate()
+    }
+
+    #[test]
+    fn test_estimate() {
+        assert_eq!(estimate(std::iter::empty()), 0);
+        assert_eq!(estimate((0..10).map(|_| Value::from("a"))), 1);
+        assert_eq!(estimate((0..100).map(|n| Value::from(n % 10))), 10);
+        for count in [1000, 100_000] {
+            let estimate = estimate((0..count).map(|n| Value::from(format!("user{}", n))));
+            let error = (estimate as f64 - count as f64).abs() / count as f64;
+            assert!(error < 0.03, "{} estimated as {}", count, estimate);
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let opt = crate::Opt::parse_from(["ndjson", "cardinality", "--field", "a,b", "app.log"]);
+        match opt.command {
+            Some(crate::Command::Cardinality(opt)) => {
+                assert_eq!(opt.field, ["a", "b"]);
+                assert_eq!(opt.files, [PathBuf::from("app.log")]);
+            }
+            command => panic!("{:?}", command),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::binary::{float, invalid, nested, read_bytes, read_first, read_uint};
+use crate::format::binary_value;
+use serde_json::{Map, Value};
+use std::io::{self, Read};
+
+/// Reads the next CBOR value with arrays, maps and tags nested at most `depth` levels, or `None`
+/// if the input ended before it.
+///
+/// Byte strings become arrays of bytes and tags are dropped, keeping the tagged value.
+pub fn read_value<R: Read>(reader: &mut R, depth: usize) -> io::Result<Option<Value>> {
+    match read_first(reader)? {
+        Some(initial) => read_item(reader, initial, depth)?
+            .map(Some)
+            .ok_or_else(|| invalid("unexpected CBOR break")),
+        None => Ok(None),
+    }
+}
+
+fn read_next<R: Read>(reader: &mut R, depth: usize) -> io::Result<Option<Value>> {
+    let initial = read_uint(reader, 1)? as u8;
+    read_item(reader, initial, depth)
+}
+
+fn read_required<R: Read>(reader: &mut R, depth: usize) -> io::Result<Value> {
+    read_next(reader, depth)?.ok_or_else(|| invalid("unexpected CBOR break"))
+}
+
+/// Reads the item starting with the initial byte, returns `None` for the break ending indefinite items.
+fn read_item<R: Read>(reader: &mut R, initial: u8, depth: usize) -> io::Result<Option<Value>> {
+    let (major, info) = (initial >> 5, initial & 0x1f);
+    if major == 7 {
+        return read_simple(reader, info);
+    }
+    let argument = match info {
+        0..=23 => Some(info as u64),
+        24..=27 => Some(read_uint(reader, 1 << (info - 24))?),
+        31 if (2..=5).contains(&major) => None,
+        _ => return Err(invalid("invalid CBOR additional information")),
+    };
+    let depth = match major {
+        4..=6 => nested(depth)?,
+        _ => depth,
+    };
+    Ok(Some(match (major, argument) {
+        (0, Some(number)) => Value::from(number),
+        (1, Some(number)) if number <= i64::MAX as u64 => Value::from(-1 - number as i64),
+        (1, Some(number)) => float(-1.0 - number as f64),
+        (2, Some(length)) => binary_value(&read_bytes(reader, length as usize)?),
+        (2, None) => binary_value(&read_chunks(reader, major)?),
+        (3, Some(length)) => string(read_bytes(reader, length as usize)?)?,
+        (3, None) => string(read_chunks(reader, major)?)?,
+        (4, Some(length)) => Value::Array(
+            (0..length)
+                .map(|_| read_required(reader, depth))
+                .collect::<io::Result<_>>()?,
+        ),
+        (4, None) => {
+            let mut array = Vec::new();
+            while let Some(value) = read_next(reader, depth)? {
+                array.push(value);
+            }
+            Value::Array(array)
+        }
+        (5, Some(length)) => {
+            let mut map = Map::new();
+            for _ in 0..length {
+                let key = read_required(reader, depth)?;
+                map.insert(key_string(key), read_required(reader, depth)?);
+            }
+            Value::Object(map)
+        }
+        (5, None) => {
+            let mut map = Map::new();
+            while let Some(key) = read_next(reader, depth)? {
+                map.insert(key_string(key), read_required(reader, depth)?);
+            }
+            Value::Object(map)
+        }
+        _ => read_required(reader, depth)?,
+    }))
+}
+
+fn read_simple<R: Read>(reader: &mut R, info: u8) -> io::Result<Option<Value>> {
+    Ok(Some(match info {
+        20 => Value::Bool(false),
+        21 => Value::Bool(true),
+        24 => {
+            read_uint(reader, 1)?;
+            Value::Null
+        }
+        25 => float(half_to_f64(read_uint(reader, 2)? as u16)),
+        26 => float(f32::from_bits(read_uint(reader, 4)? as u32) as f64),
+        27 => float(f64::from_bits(read_uint(reader, 8)?)),
+        28..=30 => return Err(invalid("invalid CBOR additional information")),
+        31 => return Ok(None),
+        _ => Value::Null,
+    }))
+}
+
+/// Concatenates the definite chunks of an indefinite byte or text string.
+fn read_chunks<R: Read>(reader: &mut R, major: u8) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        let initial = read_uint(reader, 1)? as u8;
+        if initial == 0xff {
+            return Ok(bytes);
+        }
+        let length = match (initial >> 5, initial & 0x1f) {
+            (chunk, info @ 0..=23) if chunk == major => info as u64,
+            (chunk, info @ 24..=27) if chunk == major => read_uint(reader, 1 << (info - 24))?,
+            _ => return Err(invalid("invalid chunk in indefinite CBOR string")),
+        };
+        bytes.extend(read_bytes(reader, length as usize)?);
+    }
+}
+
+fn string(bytes: Vec<u8>) -> io::Result<Value> {
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| invalid("CBOR text string isn't valid UTF-8"))
+}
+
+fn key_string(key: Value) -> String {
+    match key {
+        Value::String(key) => key,
+        key => key.to_string(),
+    }
+}
+
+fn half_to_f64(half: u16) -> f64 {
+    let exponent = (half >> 10) & 0x1f;
+    let mantissa = (half & 0x3ff) as f64;
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decode(bytes: &[u8]) -> Value {
+        read_value(&mut &bytes[..], 64).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_read_value() {
+        assert_eq!(
+            decode(b"\xa3\x63msg\x62hi\x65count\x19\x01\x00\x01\x83\xf6\xf5\x20"),
+            json!({"msg": "hi", "count": 256, "1": [null, true, -1]})
+        );
+        assert_eq!(decode(b"\xf9\x3e\x00"), json!(1.5));
+        assert_eq!(decode(b"\xfb\x3f\xf8\x00\x00\x00\x00\x00\x00"), json!(1.5));
+        assert_eq!(decode(b"\x42\x01\x02"), json!({"$binary": "0102"}));
+        assert_eq!(decode(b"\xc1\x1a\x65\x53\xf1\x00"), json!(1700000000));
+        assert!(read_value(&mut &b""[..], 64).unwrap().is_none());
+        assert!(read_value(&mut &b"\xff"[..], 64).is_err());
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(
+            read_value(&mut &b"\x81\x81\x01"[..], 2).unwrap(),
+            Some(json!([[1]]))
+        );
+        assert!(read_value(&mut &b"\x81\x81\x81\x01"[..], 2).is_err());
+        assert!(read_value(&mut &b"\xc1\xc1\xc1\x01"[..], 2).is_err());
+        let deep = [&[0x9f; 1 << 18][..], &[0xff; 1 << 18]].concat();
+        assert!(read_value(&mut &deep[..], 64).is_err());
+    }
+
+    #[test]
+    fn test_indefinite() {
+        assert_eq!(
+            decode(b"\xbf\x61a\x9f\x01\x02\xff\x7f\x62ab\x61c\xff\x5f\x41\x01\xff\xff"),
+            json!({"a": [1, 2], "abc": {"$binary": "01"}})
+        );
+    }
+}
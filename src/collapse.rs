@@ -0,0 +1,93 @@
+use crate::input::trim_newline;
+use crate::pipeline::Processed;
+use serde_json::Value;
+
+/// Collapses runs of lines repeating the line before them, comparing records without some of
+/// their fields like the time.
+pub struct Collapser {
+    ignored: Vec<String>,
+    /// The last line written, as its value without the ignored fields if it is JSON.
+    previous: Option<Result<Value, Vec<u8>>>,
+    repeats: u64,
+    collapsed: u64,
+}
+
+impl Collapser {
+    pub fn new(ignored: Vec<String>) -> Self {
+        Collapser {
+            ignored,
+            previous: None,
+            repeats: 0,
+            collapsed: 0,
+        }
+    }
+
+    /// Returns how many times in a row the last line was written counting this line, if this
+    /// line repeats it. Dropped lines don't interrupt a run.
+    pub fn repeat(&mut self, line: &[u8], processed: &Processed) -> Option<u64> {
+        let current = match processed {
+            Processed::Dropped => return None,
+            Processed::Unchanged(Some(Value::Object(object)))
+            | Processed::Changed(Value::Object(object)) => Ok(Value::Object(
+                object
+                    .iter()
+                    .filter(|(key, _)| !self.ignored.contains(key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )),
+            Processed::Unchanged(Some(value)) | Processed::Changed(value) => Ok(value.clone()),
+            Processed::Unchanged(None) => Err(trim_newline(line).to_vec()),
+        };
+        if self.previous.as_ref() == Some(&current) {
+            self.repeats += 1;
+            self.collapsed += 1;
+            return Some(self.repeats + 1);
+        }
+        self.previous = Some(current);
+        self.repeats = 0;
+        None
+    }
+
+    pub fn collapsed(&self) -> u64 {
+        self.collapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repeats(collapser: &mut Collapser, lines: &[&str]) -> Vec<Option<u64>> {
+        lines
+            .iter()
+            .map(|line| {
+                let processed = match serde_json::from_str(line) {
+                    Ok(value) => Processed::Unchanged(Some(value)),
+                    Err(_) if *line == "dropped" => Processed::Dropped,
+                    Err(_) => Processed::Unchanged(None),
+                };
+                collapser.repeat(line.as_bytes(), &processed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut collapser = Collapser::new(vec!["ts".to_string()]);
+        let lines = [
+            r#"{"ts":1,"msg":"retry"}"#,
+            r#"{"ts":2,"msg":"retry"}"#,
+            "dropped",
+            r#"{"msg":"retry","ts":3}"#,
+            r#"{"ts":4,"msg":"done"}"#,
+            "text",
+            "text\n",
+            r#"{"ts":5,"msg":"retry"}"#,
+        ];
+        assert_eq!(
+            repeats(&mut collapser, &lines),
+            [None, Some(2), None, Some(3), None, None, Some(2), None]
+        );
+        assert_eq!(collapser.collapsed(), 3);
+    }
+}
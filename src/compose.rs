@@ -0,0 +1,70 @@
+use clap::Args;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Follows the logs of all or some Docker Compose services, tagged with the service name
+#[derive(Args, Debug)]
+pub struct ComposeOpt {
+    /// Compose files, like `docker compose -f`
+    #[clap(
+        short = 'f',
+        long = "file",
+        value_name = "FILE",
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    files: Vec<PathBuf>,
+    /// Number of lines to show from the end of the logs of each service
+    #[clap(long, value_name = "LINES", default_value = "all")]
+    tail: String,
+    /// Services to follow instead of all
+    services: Vec<String>,
+}
+
+pub fn run(opt: &ComposeOpt) -> io::Result<()> {
+    let mut command = Command::new("docker");
+    command.arg("compose");
+    for file in &opt.files {
+        command.arg("--file").arg(file);
+    }
+    command
+        .args(["logs", "--follow", "--no-color", "--tail", &opt.tail])
+        .args(&opt.services)
+        .stdout(Stdio::piped());
+    let mut child = command.spawn()?;
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let lines = stdout
+        .lines()
+        .map(|line| line.map(|line| split_service(&line)));
+    crate::print_tagged_lines("service", lines)?;
+    child.wait()?;
+    Ok(())
+}
+
+/// Splits a line like `web-1  | message` into the service and the message.
+fn split_service(line: &str) -> (String, String) {
+    match line.split_once(" | ") {
+        Some((service, message)) if !service.trim().is_empty() => {
+            (service.trim().to_string(), message.to_string())
+        }
+        _ => (String::new(), line.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_service() {
+        assert_eq!(
+            split_service(r#"web-1     | {"msg":"a | b"}"#),
+            ("web-1".to_string(), r#"{"msg":"a | b"}"#.to_string())
+        );
+        assert_eq!(
+            split_service("Attaching to web-1"),
+            (String::new(), "Attaching to web-1".to_string())
+        );
+    }
+}
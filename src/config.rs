@@ -0,0 +1,465 @@
+use crate::level::{self, Level};
+use crate::predicate::Predicate;
+use crate::theme::{self, Theme};
+use clap::{Args, Subcommand};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Checks the config file or prints its JSON Schema
+#[derive(Args, Debug)]
+pub struct ConfigOpt {
+    #[clap(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Reports every invalid entry of the config file with its line
+    Validate(ValidateOpt),
+    /// Prints the JSON Schema of the config file, for editors checking TOML against one
+    Schema,
+}
+
+#[derive(Args, Debug)]
+struct ValidateOpt {
+    /// Config file to check instead of the one ndjson reads
+    path: Option<PathBuf>,
+}
+
+pub fn run(opt: &ConfigOpt) -> io::Result<()> {
+    let validate = match &opt.command {
+        ConfigCommand::Schema => {
+            println!("{}", serde_json::to_string_pretty(&schema())?);
+            return Ok(());
+        }
+        ConfigCommand::Validate(validate) => validate,
+    };
+    let path = match validate.path.clone().or_else(path) {
+        Some(path) => path,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config file, HOME and NDJSON_CONFIG are unset",
+            ))
+        }
+    };
+    let errors = validate_text(&fs::read_to_string(&path)?);
+    for error in &errors {
+        eprintln!("{}: {}", path.display(), error);
+    }
+    match errors.is_empty() {
+        true => {
+            println!("{} is valid", path.display());
+            Ok(())
+        }
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is invalid", path.display()),
+        )),
+    }
+}
+
+/// Settings read from the config file, like the color theme.
+#[derive(Default, Debug)]
+pub struct Config {
+    pub theme: Theme,
+    /// Conditions changing the level of records, from `[[reclassify]]` tables like
+    /// `level = "debug"` and `where = "msg~context canceled"`, in their order.
+    pub reclassify: Vec<(Predicate, Level)>,
+}
+
+/// A `key = "value"` line of the config file.
+#[derive(PartialEq, Debug)]
+struct Entry {
+    line: usize,
+    section: String,
+    /// Line of the `[[section]]` header of the table holding the entry, in an array of tables.
+    table: Option<usize>,
+    key: String,
+    value: String,
+}
+
+/// Line and name of a `[[section]]` header, starting a table in an array of tables.
+type Header = (usize, String);
+
+/// Returns the path of the config file, `NDJSON_CONFIG` or `ndjson/config.toml` in the config
+/// directory of the user.
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NDJSON_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let directory = match env::var_os("XDG_CONFIG_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(directory.join("ndjson").join("config.toml"))
+}
+
+/// Reads the config file, the defaults if it doesn't exist.
+pub fn load() -> io::Result<Config> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(err) => return Err(err),
+    };
+    parse(&text).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    })
+}
+
+impl Config {
+    fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), String> {
+        match section {
+            "theme" => self.theme.set(key, value),
+            "levels" => self.theme.set_level(key, value),
+            "reclassify" => Err("expected [[reclassify]] tables".to_string()),
+            section => Err(format!("unknown section [{}]", section)),
+        }
+    }
+}
+
+fn parse(text: &str) -> Result<Config, String> {
+    let (config, errors) = read(text)?;
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(config),
+    }
+}
+
+/// Returns the errors of all invalid entries, or the first syntax error.
+fn validate_text(text: &str) -> Vec<String> {
+    match read(text) {
+        Ok((_, errors)) => errors,
+        Err(err) => vec![err],
+    }
+}
+
+/// Reads the config with the errors of its invalid entries, failing only on syntax errors.
+fn read(text: &str) -> Result<(Config, Vec<String>), String> {
+    let (entries, tables) = entries(text)?;
+    let mut config = Config::default();
+    let mut errors = Vec::new();
+    // The condition and level of each [[reclassify]] table by the line of its header.
+    let mut rules: HashMap<usize, (Option<Predicate>, Option<Level>)> = HashMap::new();
+    let mut invalid_rules = HashSet::new();
+    for entry in entries {
+        let set = match (entry.section.as_str(), entry.table) {
+            ("reclassify", Some(table)) => {
+                let rule = rules.entry(table).or_default();
+                let set = match entry.key.as_str() {
+                    "where" => {
+                        Predicate::parse(&entry.value).map(|condition| rule.0 = Some(condition))
+                    }
+                    "level" => level::parse_name(&entry.value).map(|level| rule.1 = Some(level)),
+                    key => Err(format!("unknown key `{}`, expected level or where", key)),
+                };
+                if set.is_err() {
+                    invalid_rules.insert(table);
+                }
+                set
+            }
+            // Reported once at the header.
+            (_, Some(_)) => continue,
+            (section, None) => config.set(section, &entry.key, &entry.value),
+        };
+        if let Err(err) = set {
+            errors.push((entry.line, err));
+        }
+    }
+    for (line, section) in tables {
+        if section != "reclassify" {
+            errors.push((line, format!("unknown array of tables [[{}]]", section)));
+            continue;
+        }
+        match rules.remove(&line) {
+            Some((Some(condition), Some(level))) => config.reclassify.push((condition, level)),
+            _ if invalid_rules.contains(&line) => {}
+            _ => errors.push((
+                line,
+                "[[reclassify]] needs a level and a where condition".to_string(),
+            )),
+        }
+    }
+    errors.sort_by_key(|(line, _)| *line);
+    let errors = errors
+        .into_iter()
+        .map(|(line, err)| format!("line {}: {}", line, err))
+        .collect();
+    Ok((config, errors))
+}
+
+/// Returns the JSON Schema of the config file read as TOML, built from the same names as the
+/// parser, so it can't get out of date.
+fn schema() -> Value {
+    let colors: Vec<&str> = theme::COLORS.iter().map(|(name, _)| *name).collect();
+    let color = format!(
+        "((dark-)?({})|[0-9]{{1,3}}|#[0-9a-fA-F]{{6}})",
+        colors.join("|")
+    );
+    let levels: Vec<&str> = theme::LEVELS.iter().map(|level| level.name()).collect();
+    let section = |keys: &[&str], value: Value, description: &str| {
+        let properties: Map<String, Value> = keys
+            .iter()
+            .map(|key| (key.to_string(), value.clone()))
+            .collect();
+        json!({
+            "type": "object",
+            "description": description,
+            "properties": properties,
+            "additionalProperties": false,
+        })
+    };
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "ndjson config",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "theme": section(
+                &theme::KINDS,
+                json!({"$ref": "#/$defs/style"}),
+                "Colors of token kinds",
+            ),
+            "levels": section(
+                &levels,
+                json!({"$ref": "#/$defs/style"}),
+                "Colors of level values",
+            ),
+            "reclassify": {
+                "type": "array",
+                "description": "Conditions changing the level of records, the first matching one applies",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "level": {"enum": levels, "description": "Level of the matching records"},
+                        "where": {
+                            "type": "string",
+                            "description": "Condition of the records getting the level, like `msg~context canceled`",
+                            "pattern": "(==|=~|!=|>=|<=|=|>|<|~)",
+                        },
+                    },
+                    "required": ["level", "where"],
+                    "additionalProperties": false,
+                },
+            },
+        },
+        "$defs": {
+            "style": {
+                "description": "A color like `blue`, `dark-blue`, `208` or `#ffaa00`, optionally with a background like `white on red`",
+                "type": ["string", "integer"],
+                "pattern": format!("^{0}( on {0})?$", color),
+            },
+        },
+    })
+}
+
+/// Parses the subset of TOML used by the config file, sections and arrays of tables of
+/// `key = "string"` entries, into the entries and the line and name of each `[[table]]` header.
+/// Keys and sections may not be repeated, except `[[table]]` headers each starting a new table.
+fn entries(text: &str) -> Result<(Vec<Entry>, Vec<Header>), String> {
+    let mut section = String::new();
+    let mut table = None;
+    let mut entries = Vec::new();
+    let mut tables = Vec::new();
+    // Whether each section seen is an array of tables.
+    let mut sections: HashMap<String, bool> = HashMap::new();
+    let mut keys = HashSet::new();
+    for (index, line) in text.lines().enumerate() {
+        let invalid = |message: &str| format!("line {}: {}", index + 1, message);
+        let comment = line
+            .match_indices('#')
+            .map(|(index, _)| index)
+            .find(|index| line[..*index].matches('"').count() % 2 == 0);
+        let line = line[..comment.unwrap_or(line.len())].trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let array = name.starts_with('[');
+            let name = match array {
+                true => name[1..]
+                    .strip_suffix("]]")
+                    .ok_or_else(|| invalid("expected `]]`"))?,
+                false => name
+                    .strip_suffix(']')
+                    .ok_or_else(|| invalid("expected `]`"))?,
+            };
+            section = name.trim().to_string();
+            match sections.insert(section.clone(), array) {
+                Some(true) if array => {}
+                Some(_) if array => {
+                    return Err(invalid(&format!("[{}] is already a table", section)))
+                }
+                Some(true) => {
+                    return Err(invalid(&format!(
+                        "[{}] is already an array of tables",
+                        section
+                    )))
+                }
+                Some(_) => return Err(invalid(&format!("duplicate section [{}]", section))),
+                None => {}
+            }
+            table = array.then_some(index + 1);
+            if array {
+                tables.push((index + 1, section.clone()));
+            }
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("expected `key = \"value\"`"))?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| value.parse::<u8>().is_ok().then_some(value))
+            .ok_or_else(|| invalid("expected a quoted string"))?;
+        let key = key.trim().trim_matches('"');
+        if !keys.insert((section.clone(), table, key.to_string())) {
+            return Err(invalid(&format!("duplicate key `{}`", key)));
+        }
+        entries.push(Entry {
+            line: index + 1,
+            section: section.clone(),
+            table,
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+    Ok((entries, tables))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn entry(line: usize, section: &str, table: Option<usize>, key: &str, value: &str) -> Entry {
+        Entry {
+            line,
+            section: section.to_string(),
+            table,
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_entries() {
+        let text = "# colors\n[theme]\nkey = \"blue\" # keys\nstring = 38\n\n[levels]\n\"warn\" = \"#ffaa00\"\n";
+        assert_eq!(
+            entries(text).unwrap(),
+            (
+                vec![
+                    entry(3, "theme", None, "key", "blue"),
+                    entry(4, "theme", None, "string", "38"),
+                    entry(7, "levels", None, "warn", "#ffaa00"),
+                ],
+                vec![]
+            )
+        );
+        let text = "[[rule]]\nlevel = \"debug\"\n[[rule]]\nlevel = \"info\"\n";
+        assert_eq!(
+            entries(text).unwrap(),
+            (
+                vec![
+                    entry(2, "rule", Some(1), "level", "debug"),
+                    entry(4, "rule", Some(3), "level", "info"),
+                ],
+                vec![(1, "rule".to_string()), (3, "rule".to_string())]
+            )
+        );
+        assert!(entries("[theme\n").is_err());
+        assert!(entries("[[rule]\n").is_err());
+        assert_eq!(
+            entries("[theme]\nkey blue").unwrap_err(),
+            "line 2: expected `key = \"value\"`"
+        );
+        assert!(entries("[theme]\nkey = blue").is_err());
+        assert_eq!(
+            entries("[theme]\nkey = \"blue\"\n\"key\" = \"red\"").unwrap_err(),
+            "line 3: duplicate key `key`"
+        );
+        assert_eq!(
+            entries("[[rule]]\nlevel = \"debug\"\nlevel = \"info\"").unwrap_err(),
+            "line 3: duplicate key `level`"
+        );
+        assert_eq!(
+            entries("[theme]\n[levels]\n[theme]").unwrap_err(),
+            "line 3: duplicate section [theme]"
+        );
+        assert!(entries("[rule]\n[[rule]]").is_err());
+        assert!(entries("[[rule]]\n[rule]").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(parse("[theme]\nkey = \"blue\"").is_ok());
+        assert!(parse("[colors]\nkey = \"blue\"").is_err());
+        assert!(parse("[theme]\nkeys = \"blue\"").is_err());
+        let config = parse(
+            "[[reclassify]]\nlevel = \"debug\"\nwhere = \"msg~context canceled\"\n\
+             [[reclassify]]\nwhere = \"msg~broken pipe\"\nlevel = \"debug\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.reclassify.len(), 2);
+        assert_eq!(config.reclassify[1].1, Level::Debug);
+        assert!(parse("[reclassify]\ndebug = \"msg~x\"").is_err());
+        assert!(parse("[[reclassify]]\nlevel = \"loud\"\nwhere = \"msg~x\"").is_err());
+        assert!(parse("[[reclassify]]\nlevel = \"debug\"\nwhere = \"msg\"").is_err());
+        assert!(parse("[[reclassify]]\nlevel = \"debug\"\nwhen = \"msg~x\"").is_err());
+        assert!(parse("[[colors]]\nkey = \"blue\"").is_err());
+        assert_eq!(
+            parse("[[reclassify]]\nlevel = \"debug\"\n").unwrap_err(),
+            "line 1: [[reclassify]] needs a level and a where condition"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let text =
+            "[theme]\nkey = \"blue\"\nkeys = \"blue\"\nstring = \"purple\"\n[colors]\na = \"b\"\n\
+             [[reclassify]]\nlevel = \"loud\"\nwhere = \"msg~x\"\n[[reclassify]]\nwhere = \"msg~y\"\n";
+        assert_eq!(
+            validate_text(text),
+            [
+                "line 3: unknown token kind `keys`, expected one of key, value, true, false, null, dim, string, secret, spotlight, alert, error, highlight",
+                "line 4: invalid color `purple`",
+                "line 6: unknown section [colors]",
+                "line 8: unknown level `loud`",
+                "line 10: [[reclassify]] needs a level and a where condition",
+            ]
+        );
+        assert!(validate_text("[theme]\nkey = \"blue\"\n").is_empty());
+        assert_eq!(validate_text("[theme\n"), ["line 1: expected `]`"]);
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = schema();
+        let pattern = Regex::new(schema["$defs"]["style"]["pattern"].as_str().unwrap()).unwrap();
+        for style in ["blue", "dark-white on 52", "#ffaa00", "208"] {
+            assert!(pattern.is_match(style), "{}", style);
+        }
+        assert!(!pattern.is_match("purple"));
+        assert!(schema["properties"]["theme"]["properties"]["key"].is_object());
+        assert!(schema["properties"]["levels"]["properties"]["warn"].is_object());
+        let rule = &schema["properties"]["reclassify"]["items"];
+        assert!(rule["properties"]["level"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("debug")));
+        assert_eq!(rule["required"], json!(["level", "where"]));
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+const CAPACITY: usize = 1 << 20;
+
+/// Remembers the hashes of the most recent records, forgetting the oldest ones when full.
+pub struct Deduplicator {
+    seen: HashSet<u64>,
+    order: VecDeque<u64>,
+    capacity: usize,
+    removed: u64,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Deduplicator::with_capacity(CAPACITY)
+    }
+}
+
+impl Deduplicator {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Deduplicator {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+            removed: 0,
+        }
+    }
+
+    pub fn is_duplicate(&mut self, record: &[u8]) -> bool {
+        let mut hasher = DefaultHasher::new();
+        record.hash(&mut hasher);
+        let hash = hasher.finish();
+        if !self.seen.insert(hash) {
+            self.removed += 1;
+            return true;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        false
+    }
+
+    pub fn removed(&self) -> u64 {
+        self.removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates() {
+        let mut dedup = Deduplicator::with_capacity(2);
+        assert!(!dedup.is_duplicate(b"a"));
+        assert!(!dedup.is_duplicate(b"b"));
+        assert!(dedup.is_duplicate(b"a"));
+        assert!(!dedup.is_duplicate(b"c"));
+        assert!(!dedup.is_duplicate(b"a"));
+        assert_eq!(dedup.removed(), 1);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::config;
+use clap::Args;
+use serde_json::{json, Value};
+use std::env;
+use std::io;
+
+/// Record rendered to compare the colors and layout between terminals.
+const SAMPLE: &str = r#"{"ts":"2023-01-01T12:00:00Z","level":"warn","msg":"sample","status":503,"ok":false,"user":null,"tags":["a","b"],"latency_ms":1234.5}"#;
+
+/// Prints the detected terminal capabilities and a sample record, to debug why the output differs
+/// between machines
+#[derive(Args, Debug)]
+pub struct DoctorOpt {}
+
+pub fn run(_opt: &DoctorOpt) -> io::Result<()> {
+    let mut checks = checks(
+        |name| env::var(name).ok(),
+        atty::is(atty::Stream::Stdout),
+        atty::is(atty::Stream::Stdin),
+    );
+    checks.push(config_check());
+    let lines = checks
+        .into_iter()
+        .map(|check| check.to_string())
+        .chain(Some(SAMPLE.to_string()));
+    crate::print_lines(lines.map(Ok))
+}
+
+/// Returns a record per capability with its value and the environment it was derived from.
+fn checks<F: Fn(&str) -> Option<String>>(var: F, stdout_tty: bool, stdin_tty: bool) -> Vec<Value> {
+    let vars = |names: &[&str]| vars(&var, names);
+    let term = var("TERM").unwrap_or_default();
+    let colorterm = var("COLORTERM").unwrap_or_default();
+    let colors = if !stdout_tty {
+        "none, stdout is not a terminal"
+    } else if term == "dumb" {
+        "none"
+    } else if colorterm == "truecolor" || colorterm == "24bit" {
+        "truecolor"
+    } else if term.contains("256color") {
+        "256"
+    } else if cfg!(windows) || !term.is_empty() {
+        "16"
+    } else {
+        "unknown"
+    };
+    let width = match var("COLUMNS").and_then(|columns| columns.parse::<usize>().ok()) {
+        Some(columns) => columns.to_string(),
+        None => "unknown, --panes assumes 120 unless --width is given".to_string(),
+    };
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    let hyperlinks = ["iTerm.app", "WezTerm", "vscode"].contains(&program.as_str())
+        || ["KITTY_WINDOW_ID", "WT_SESSION", "DOMTERM"]
+            .iter()
+            .any(|name| var(name).is_some())
+        || var("VTE_VERSION")
+            .and_then(|version| version.parse::<u32>().ok())
+            .is_some_and(|version| version >= 5000);
+    let terminal = |tty| match tty {
+        true => "terminal",
+        false => "pipe or file",
+    };
+    vec![
+        json!({"check": "platform", "value": env::consts::OS}),
+        json!({"check": "stdout", "value": terminal(stdout_tty)}),
+        json!({"check": "stdin", "value": terminal(stdin_tty)}),
+        json!({"check": "colors", "value": colors, "env": vars(&["TERM", "COLORTERM"])}),
+        json!({"check": "width", "value": width, "env": vars(&["COLUMNS"])}),
+        json!({
+            "check": "hyperlinks",
+            "value": if hyperlinks { "likely" } else { "unknown" },
+            "env": vars(&["TERM_PROGRAM", "VTE_VERSION", "KITTY_WINDOW_ID", "WT_SESSION", "DOMTERM"]),
+        }),
+    ]
+}
+
+/// Returns where the config file is looked for and whether it was read.
+fn config_check() -> Value {
+    let path = match config::path() {
+        Some(path) => path,
+        None => return json!({"check": "config", "value": "none, HOME is not set"}),
+    };
+    let value = match config::load() {
+        Ok(_) if path.exists() => "read".to_string(),
+        Ok(_) => "missing, using the defaults".to_string(),
+        Err(err) => format!("invalid, {}", err),
+    };
+    json!({
+        "check": "config",
+        "value": value,
+        "path": path.display().to_string(),
+        "env": vars(&|name| env::var(name).ok(), &["NDJSON_CONFIG", "XDG_CONFIG_HOME"]),
+    })
+}
+
+/// Returns the variables which are set as `NAME=value` separated by spaces.
+fn vars<F: Fn(&str) -> Option<String>>(var: &F, names: &[&str]) -> String {
+    names
+        .iter()
+        .filter_map(|name| Some(format!("{}={}", name, var(name)?)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(checks: &[Value], name: &str) -> Value {
+        checks
+            .iter()
+            .find(|check| check["check"] == name)
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_checks() {
+        let env = |name: &str| match name {
+            "TERM" => Some("xterm-256color".to_string()),
+            "COLUMNS" => Some("160".to_string()),
+            "VTE_VERSION" => Some("6003".to_string()),
+            _ => None,
+        };
+        let checks = checks(env, true, false);
+        assert_eq!(
+            check(&checks, "colors"),
+            json!({"check": "colors", "value": "256", "env": "TERM=xterm-256color"})
+        );
+        assert_eq!(check(&checks, "width")["value"], "160");
+        assert_eq!(check(&checks, "hyperlinks")["value"], "likely");
+        assert_eq!(check(&checks, "stdin")["value"], "pipe or file");
+        let checks = super::checks(|_| None, false, false);
+        assert_eq!(
+            check(&checks, "colors")["value"],
+            "none, stdout is not a terminal"
+        );
+        assert_eq!(check(&checks, "hyperlinks")["value"], "unknown");
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+/// Parses durations like `500ms`, `30s`, `1m`, `2h` or `1d`, seconds if the unit is missing.
+pub fn parse(string: &str) -> Result<Duration, String> {
+    let split = string
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(string.len());
+    let (number, unit) = string.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", string))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 60.0 * 60.0,
+        "d" => number * 60.0 * 60.0 * 24.0,
+        unit => return Err(format!("invalid duration unit `{}`", unit)),
+    };
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Formats a duration with its two largest units, like `1h 5m` or `30s`.
+pub fn format(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let units = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+    let parts: Vec<String> = units
+        .iter()
+        .scan(seconds, |rest, (size, unit)| {
+            let count = *rest / size;
+            *rest %= size;
+            Some((count, unit))
+        })
+        .skip_while(|(count, _)| *count == 0)
+        .take(2)
+        .filter(|(count, _)| *count != 0)
+        .map(|(count, unit)| format!("{}{}", count, unit))
+        .collect();
+    if parts.is_empty() {
+        format!("{}ms", duration.as_millis())
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse("60"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse("1w").is_err());
+        assert!(parse("m").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(Duration::from_millis(250)), "250ms");
+        assert_eq!(format(Duration::from_secs(90)), "1m 30s");
+        assert_eq!(format(Duration::from_secs(3600 + 59)), "1h");
+        assert_eq!(format(Duration::from_secs(2 * 86400 + 3 * 3600)), "2d 3h");
+    }
+}
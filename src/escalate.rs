@@ -0,0 +1,169 @@
+use crate::duration;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Observations after which values which stopped repeating are forgotten.
+const PRUNE_INTERVAL: u64 = 1024;
+
+/// How records occurring too often are shown.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Treatment {
+    /// Highlights the repeated value and appends how often it occurred.
+    Highlight,
+    /// Writes a banner when a value starts to repeat too often.
+    Banner,
+}
+
+/// A rule like `same msg 5 times in 1m → highlight`, escalating records whose field value repeats.
+#[derive(Clone, Debug)]
+pub struct Escalation {
+    field: String,
+    times: usize,
+    window: Duration,
+    treatment: Treatment,
+    seen: HashMap<String, VecDeque<Instant>>,
+    observed: u64,
+}
+
+/// A record whose value occurred at least as often as the rule allows.
+#[derive(PartialEq, Debug)]
+pub struct Escalated {
+    pub field: String,
+    pub treatment: Treatment,
+    pub count: usize,
+    /// Whether the value just reached the number of times.
+    pub starts: bool,
+}
+
+impl Escalation {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "expected `same FIELD N times in DURATION → highlight|banner` in `{}`",
+                rule
+            )
+        };
+        let (condition, treatment) = match rule.split_once('→').or_else(|| rule.split_once("->"))
+        {
+            Some((condition, treatment)) => (condition, treatment.trim()),
+            None => (rule, "highlight"),
+        };
+        let treatment = match treatment {
+            "highlight" => Treatment::Highlight,
+            "banner" => Treatment::Banner,
+            _ => return Err(invalid()),
+        };
+        let words: Vec<&str> = condition.split_whitespace().collect();
+        let (field, times, window) = match words[..] {
+            ["same", field, times, "times", "in", window] => (field, times, window),
+            _ => return Err(invalid()),
+        };
+        Ok(Escalation {
+            field: field.to_string(),
+            times: times
+                .parse()
+                .ok()
+                .filter(|times| *times > 1)
+                .ok_or_else(invalid)?,
+            window: duration::parse(window)?,
+            treatment,
+            seen: HashMap::new(),
+            observed: 0,
+        })
+    }
+
+    /// Counts the value of the record, returns whether it occurred too often within the window.
+    pub fn observe(&mut self, object: &Map<String, Value>, now: Instant) -> Option<Escalated> {
+        let value = match object.get(&self.field)? {
+            Value::String(string) => string.clone(),
+            value => value.to_string(),
+        };
+        self.observed += 1;
+        if self.observed.is_multiple_of(PRUNE_INTERVAL) {
+            let window = self.window;
+            self.seen.retain(|_, times| {
+                times
+                    .back()
+                    .is_some_and(|last| now.duration_since(*last) <= window)
+            });
+        }
+        let times = self.seen.entry(value).or_default();
+        times.push_back(now);
+        while let Some(oldest) = times.front() {
+            if now.duration_since(*oldest) <= self.window {
+                break;
+            }
+            times.pop_front();
+        }
+        let count = times.len();
+        (count >= self.times).then(|| Escalated {
+            field: self.field.clone(),
+            treatment: self.treatment,
+            count,
+            starts: count == self.times,
+        })
+    }
+
+    /// Returns the message of a banner for the escalated value.
+    pub fn describe(&self, escalated: &Escalated, value: &Value) -> String {
+        let value = match value {
+            Value::String(string) => string.clone(),
+            value => value.to_string(),
+        };
+        format!(
+            "{} {:?} repeated {} times in {}",
+            self.field,
+            value,
+            escalated.count,
+            duration::format(self.window)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let escalation = Escalation::parse("same msg 5 times in 1m → highlight").unwrap();
+        assert_eq!(escalation.field, "msg");
+        assert_eq!(escalation.times, 5);
+        assert_eq!(escalation.window, Duration::from_secs(60));
+        assert_eq!(
+            Escalation::parse("same msg 3 times in 10s -> banner")
+                .unwrap()
+                .treatment,
+            Treatment::Banner
+        );
+        assert!(Escalation::parse("same msg 3 times in 10s").is_ok());
+        assert!(Escalation::parse("same msg 1 times in 10s").is_err());
+        assert!(Escalation::parse("same msg 3 times in 10s → blink").is_err());
+        assert!(Escalation::parse("msg 3 times").is_err());
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut escalation = Escalation::parse("same msg 3 times in 1m").unwrap();
+        let retry = json!({"msg": "retrying"});
+        let other = json!({"msg": "done"});
+        let start = Instant::now();
+        let mut observe = |record: &Value, seconds| {
+            escalation
+                .observe(
+                    record.as_object().unwrap(),
+                    start + Duration::from_secs(seconds),
+                )
+                .map(|escalated| (escalated.count, escalated.starts))
+        };
+        assert_eq!(observe(&retry, 0), None);
+        assert_eq!(observe(&retry, 1), None);
+        assert_eq!(observe(&other, 2), None);
+        assert_eq!(observe(&retry, 3), Some((3, true)));
+        assert_eq!(observe(&retry, 4), Some((4, false)));
+        assert_eq!(observe(&json!({}), 5), None);
+        assert_eq!(observe(&retry, 90), None);
+    }
+}
@@ -0,0 +1,381 @@
+use crate::secrets::REDACTED;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// What happens to the fields matched by a rule, in the order in which it wins ties.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Action {
+    Include,
+    Dim,
+    Exclude,
+}
+
+/// A glob like `http.*` or a regex in slashes like `/^(trace|span)_id$/`, matched against a whole
+/// dotted key path. `*` also matches dots, `?` matches a single character.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    regex: Regex,
+    /// Number of literal characters, the more the more specific the pattern is.
+    specificity: usize,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err("empty field pattern".to_string());
+        }
+        let (regex, specificity) = match pattern
+            .strip_prefix('/')
+            .and_then(|regex| regex.strip_suffix('/'))
+        {
+            Some(regex) => (
+                regex.to_string(),
+                regex
+                    .chars()
+                    .filter(|c| !r"\.*+?()[]{}|^$".contains(*c))
+                    .count(),
+            ),
+            None => {
+                let mut regex = String::from("^");
+                for c in pattern.chars() {
+                    match c {
+                        '*' => regex.push_str(".*"),
+                        '?' => regex.push('.'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push('$');
+                (
+                    regex,
+                    pattern.chars().filter(|c| !"*?".contains(*c)).count(),
+                )
+            }
+        };
+        Ok(Pattern {
+            regex: Regex::new(&regex)
+                .map_err(|err| format!("invalid pattern `{}`: {}", pattern, err))?,
+            specificity,
+        })
+    }
+}
+
+/// Rules deciding how the fields of records are written, matched against dotted key paths like
+/// `http.headers.host`, where the elements of an array share its path.
+///
+/// A path gets the action of the most specific matching rule, the one with the most literal
+/// characters, and exclude wins ties over dim, dim over include. Paths without a matching rule get
+/// the action of their closest ancestor with one. The remaining paths are included, unless there
+/// are include rules. So `--include 'http.*' --exclude 'http.headers.*'` shows `http` except its
+/// headers, and `--include 'msg,http.*' --dim 'http.headers.*'` shows the headers dimmed.
+#[derive(Clone, Default, Debug)]
+pub struct FieldRules {
+    rules: Vec<(Action, Pattern)>,
+}
+
+impl FieldRules {
+    pub fn new(include: &[Pattern], exclude: &[Pattern], dim: &[Pattern]) -> Self {
+        let rules = [
+            (Action::Include, include),
+            (Action::Exclude, exclude),
+            (Action::Dim, dim),
+        ]
+        .iter()
+        .flat_map(|(action, patterns)| {
+            patterns
+                .iter()
+                .map(move |pattern| (*action, pattern.clone()))
+        })
+        .collect();
+        FieldRules { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether some rule changes which fields are written, rather than only how.
+    pub fn filters(&self) -> bool {
+        self.rules.iter().any(|(action, _)| *action != Action::Dim)
+    }
+
+    /// Returns the action for a key path, inherited from its ancestors if no rule matches it.
+    pub fn action(&self, path: &str) -> Action {
+        let mut path = path;
+        loop {
+            let matched = self
+                .rules
+                .iter()
+                .filter(|(_, pattern)| pattern.regex.is_match(path))
+                .max_by_key(|(action, pattern)| (pattern.specificity, *action));
+            if let Some((action, _)) = matched {
+                return *action;
+            }
+            match path.rfind('.') {
+                Some(end) => path = &path[..end],
+                None => break,
+            }
+        }
+        match self
+            .rules
+            .iter()
+            .any(|(action, _)| *action == Action::Include)
+        {
+            true => Action::Exclude,
+            false => Action::Include,
+        }
+    }
+
+    /// Returns the record without its excluded fields, and without objects and arrays which only
+    /// held excluded fields.
+    pub fn filter(&self, object: &Map<String, Value>) -> Map<String, Value> {
+        self.filter_object(object, "")
+    }
+
+    fn filter_object(&self, object: &Map<String, Value>, prefix: &str) -> Map<String, Value> {
+        object
+            .iter()
+            .filter_map(|(key, value)| {
+                let path = match prefix {
+                    "" => key.clone(),
+                    prefix => format!("{}.{}", prefix, key),
+                };
+                Some((key.clone(), self.filter_value(value, &path)?))
+            })
+            .collect()
+    }
+
+    fn filter_value(&self, value: &Value, path: &str) -> Option<Value> {
+        match value {
+            Value::Object(object) if !object.is_empty() => {
+                let object = self.filter_object(object, path);
+                (!object.is_empty()).then_some(Value::Object(object))
+            }
+            Value::Array(array) if !array.is_empty() => {
+                let array: Vec<Value> = array
+                    .iter()
+                    .filter_map(|value| self.filter_value(value, path))
+                    .collect();
+                (!array.is_empty()).then_some(Value::Array(array))
+            }
+            value => (self.action(path) != Action::Exclude).then(|| value.clone()),
+        }
+    }
+}
+
+/// Narrows records written as JSON or logfmt like the formatter narrows the records it writes, by
+/// the field rules, with the first and last keys moved and long strings cut off.
+#[derive(Clone, Default, Debug)]
+pub struct Shape {
+    pub rules: FieldRules,
+    pub first: Vec<String>,
+    pub last: Vec<String>,
+    pub max_string_length: Option<usize>,
+    /// Top-level keys whose strings aren't cut off.
+    pub expand: Vec<String>,
+}
+
+impl Shape {
+    /// Returns the record as it is written, or `None` if none of its fields are kept.
+    pub fn apply(&self, object: &Map<String, Value>) -> Option<Map<String, Value>> {
+        let kept = match self.rules.filters() {
+            true => self.rules.filter(object),
+            false => object.clone(),
+        };
+        if kept.is_empty() && !object.is_empty() {
+            return None;
+        }
+        let mut entries: Vec<(String, Value)> = kept.into_iter().collect();
+        entries.sort_by_key(|(key, _)| position(&self.first, &self.last, key));
+        for (key, value) in &mut entries {
+            match self.max_string_length {
+                Some(max) if !self.expand.contains(key) => truncate_strings(value, max),
+                _ => {}
+            }
+        }
+        Some(entries.into_iter().collect())
+    }
+}
+
+/// Returns the sort key of a top-level key, placing the first keys in their order before the
+/// others and the last keys after them.
+pub fn position(first: &[String], last: &[String], key: &str) -> (u8, usize) {
+    match first.iter().position(|first| first == key) {
+        Some(index) => (0, index),
+        None => match last.iter().position(|last| last == key) {
+            Some(index) => (2, index),
+            None => (1, 0),
+        },
+    }
+}
+
+/// Cuts off the strings of a value after `max` characters, ending them with an ellipsis.
+fn truncate_strings(value: &mut Value, max: usize) {
+    match value {
+        Value::String(string) => {
+            if let Some((end, _)) = string.char_indices().nth(max) {
+                string.truncate(end);
+                string.push('…');
+            }
+        }
+        Value::Array(array) => array
+            .iter_mut()
+            .for_each(|value| truncate_strings(value, max)),
+        Value::Object(object) => object
+            .values_mut()
+            .for_each(|value| truncate_strings(value, max)),
+        _ => {}
+    }
+}
+
+/// Replaces the values of fields whose key or key path matches a pattern by `[REDACTED]`, at any
+/// depth and case-insensitively for keys, so `authorization` also redacts `headers.Authorization`.
+/// Returns whether any value was replaced.
+pub fn redact(object: &mut Map<String, Value>, patterns: &[Pattern]) -> bool {
+    redact_object(object, "", patterns)
+}
+
+fn redact_object(object: &mut Map<String, Value>, prefix: &str, patterns: &[Pattern]) -> bool {
+    let mut redacted = false;
+    for (key, value) in object.iter_mut() {
+        let path = match prefix {
+            "" => key.clone(),
+            prefix => format!("{}.{}", prefix, key),
+        };
+        let lowercase = key.to_lowercase();
+        let matches = patterns.iter().any(|pattern| {
+            [key.as_str(), &lowercase, &path]
+                .iter()
+                .any(|text| pattern.regex.is_match(text))
+        });
+        if !matches {
+            redacted |= redact_value(value, &path, patterns);
+        } else if value.as_str() != Some(REDACTED) {
+            *value = Value::String(REDACTED.to_string());
+            redacted = true;
+        }
+    }
+    redacted
+}
+
+fn redact_value(value: &mut Value, path: &str, patterns: &[Pattern]) -> bool {
+    match value {
+        Value::Object(object) => redact_object(object, path, patterns),
+        Value::Array(array) => array.iter_mut().fold(false, |redacted, value| {
+            redact_value(value, path, patterns) | redacted
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(include: &[&str], exclude: &[&str], dim: &[&str]) -> FieldRules {
+        let patterns = |patterns: &[&str]| -> Vec<Pattern> {
+            patterns
+                .iter()
+                .map(|pattern| Pattern::parse(pattern).unwrap())
+                .collect()
+        };
+        FieldRules::new(&patterns(include), &patterns(exclude), &patterns(dim))
+    }
+
+    fn filter(rules: &FieldRules, record: Value) -> Value {
+        Value::Object(rules.filter(record.as_object().unwrap()))
+    }
+
+    #[test]
+    fn test_filter() {
+        let record = json!({
+            "msg": "a",
+            "http": {"status": 200, "headers": {"host": "x"}},
+            "spans": [{"id": 1, "name": "db"}],
+        });
+        assert_eq!(
+            filter(
+                &rules(&["http.*"], &["http.headers.*"], &[]),
+                record.clone()
+            ),
+            json!({"http": {"status": 200}})
+        );
+        assert_eq!(
+            filter(&rules(&["http.status"], &["http.*"], &[]), record.clone()),
+            json!({"http": {"status": 200}})
+        );
+        assert_eq!(
+            filter(&rules(&["msg", "spans.id"], &[], &[]), record.clone()),
+            json!({"msg": "a", "spans": [{"id": 1}]})
+        );
+        assert_eq!(
+            filter(&rules(&[], &["/^(msg|spans)$/"], &[]), record),
+            json!({"http": {"status": 200, "headers": {"host": "x"}}})
+        );
+    }
+
+    #[test]
+    fn test_shape() {
+        let shape = Shape {
+            rules: rules(&[], &["password", "http.headers"], &[]),
+            first: vec!["msg".to_string()],
+            last: vec!["stack".to_string()],
+            max_string_length: Some(3),
+            expand: vec!["stack".to_string()],
+        };
+        let record = json!({
+            "stack": "a\nb\nc",
+            "password": "hunter2",
+            "http": {"path": "/orders", "headers": {"host": "x"}},
+            "msg": "timeout",
+        });
+        assert_eq!(
+            Value::Object(shape.apply(record.as_object().unwrap()).unwrap()),
+            json!({"msg": "tim…", "http": {"path": "/or…"}, "stack": "a\nb\nc"})
+        );
+        assert_eq!(
+            shape.apply(json!({"password": "x"}).as_object().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let patterns: Vec<Pattern> = ["password", "authorization", "*_secret", "/^db\\.url$/"]
+            .iter()
+            .map(|pattern| Pattern::parse(pattern).unwrap())
+            .collect();
+        let mut record = json!({
+            "user": {"name": "a", "password": "hunter2"},
+            "headers": [{"Authorization": "Bearer x"}],
+            "client_secret": {"value": 1},
+            "db": {"url": "postgres://", "host": "h"},
+            "url": "/",
+        });
+        assert!(redact(record.as_object_mut().unwrap(), &patterns));
+        assert_eq!(
+            record,
+            json!({
+                "user": {"name": "a", "password": "[REDACTED]"},
+                "headers": [{"Authorization": "[REDACTED]"}],
+                "client_secret": "[REDACTED]",
+                "db": {"url": "[REDACTED]", "host": "h"},
+                "url": "/",
+            })
+        );
+        assert!(!redact(record.as_object_mut().unwrap(), &patterns));
+    }
+
+    #[test]
+    fn test_action() {
+        let rules = rules(&["level", "msg", "http.status"], &["level"], &["http.*"]);
+        assert_eq!(rules.action("msg"), Action::Include);
+        assert_eq!(rules.action("level"), Action::Exclude);
+        assert_eq!(rules.action("ts"), Action::Exclude);
+        assert_eq!(rules.action("http.headers.host"), Action::Dim);
+        assert_eq!(rules.action("http.status"), Action::Include);
+        assert!(Pattern::parse("/(/").is_err());
+        assert!(Pattern::parse("").is_err());
+    }
+}
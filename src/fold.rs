@@ -0,0 +1,145 @@
+use crate::predicate::Predicate;
+use crate::{duration, timestamp};
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+/// Outcome of passing a record through the folder.
+#[derive(PartialEq, Debug)]
+pub enum Folded {
+    /// The record isn't part of a transaction.
+    Outside(Map<String, Value>),
+    /// The record is part of an open transaction.
+    Hidden,
+    /// The transaction ended, replaced by its start marker with the count and duration.
+    Summary(Map<String, Value>),
+}
+
+/// Collapses the records from a start marker to an end marker into a single summary record.
+pub struct Folder {
+    start: Predicate,
+    end: Predicate,
+    open: Option<Transaction>,
+}
+
+struct Transaction {
+    marker: Map<String, Value>,
+    records: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Folder {
+    pub fn new(start: Predicate, end: Predicate) -> Self {
+        Folder {
+            start,
+            end,
+            open: None,
+        }
+    }
+
+    pub fn fold(&mut self, object: Map<String, Value>) -> Folded {
+        let time = timestamp::detect(&object);
+        let transaction = match &mut self.open {
+            Some(transaction) => transaction,
+            None if self.start.matches(&object) => {
+                self.open = Some(Transaction {
+                    marker: object,
+                    records: 1,
+                    first: time,
+                    last: time,
+                });
+                return Folded::Hidden;
+            }
+            None => return Folded::Outside(object),
+        };
+        transaction.records += 1;
+        transaction.first = transaction.first.or(time);
+        transaction.last = time.or(transaction.last);
+        if self.end.matches(&object) {
+            return Folded::Summary(self.open.take().unwrap().summary(false));
+        }
+        Folded::Hidden
+    }
+
+    /// Returns the summary of a transaction still open when the input ended.
+    pub fn finish(&mut self) -> Option<Map<String, Value>> {
+        self.open
+            .take()
+            .map(|transaction| transaction.summary(true))
+    }
+}
+
+impl Transaction {
+    fn summary(self, unfinished: bool) -> Map<String, Value> {
+        let mut summary = self.marker;
+        summary.insert("fold_records".to_string(), self.records.into());
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            if let Ok(elapsed) = (last - first).to_std() {
+                summary.insert(
+                    "fold_duration".to_string(),
+                    Value::String(duration::format(elapsed)),
+                );
+            }
+        }
+        if unfinished {
+            summary.insert("fold_unfinished".to_string(), Value::Bool(true));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn object(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn folder() -> Folder {
+        Folder::new(
+            Predicate::parse(r#"event=="request_start""#).unwrap(),
+            Predicate::parse(r#"event=="request_end""#).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_fold() {
+        let mut folder = folder();
+        let outside = object(json!({"event": "boot"}));
+        assert_eq!(folder.fold(outside.clone()), Folded::Outside(outside));
+        for record in [
+            json!({"event": "request_start", "id": 1, "ts": 1700000000}),
+            json!({"event": "query", "ts": 1700000001}),
+        ] {
+            assert_eq!(folder.fold(object(record)), Folded::Hidden);
+        }
+        assert_eq!(
+            folder.fold(object(json!({"event": "request_end", "ts": 1700000065}))),
+            Folded::Summary(object(json!({
+                "event": "request_start",
+                "id": 1,
+                "ts": 1700000000,
+                "fold_records": 3,
+                "fold_duration": "1m 5s"
+            })))
+        );
+        assert_eq!(folder.finish(), None);
+    }
+
+    #[test]
+    fn test_unfinished() {
+        let mut folder = folder();
+        folder.fold(object(json!({"event": "request_start"})));
+        folder.fold(object(json!({"event": "query"})));
+        assert_eq!(
+            folder.finish(),
+            Some(object(json!({
+                "event": "request_start",
+                "fold_records": 2,
+                "fold_unfinished": true
+            })))
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use crate::format::binary_value_bytes;
+use crate::{binary, logplex, msgpack, size, timestamp};
+use chrono::SecondsFormat;
+use clap::Args;
+use serde_json::{Map, Value};
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// Bytes of a forward message read at most, well above the chunks agents send by default, so a
+/// peer can't make a connection buffer without bound.
+const MAX_MESSAGE: u64 = 64 << 20;
+
+/// Receives records from Fluentd or Fluent Bit agents over the forward protocol, or from a Heroku
+/// log drain
+#[derive(Args, Debug)]
+pub struct ListenOpt {
+    /// Address to accept forward protocol connections on, like `:24224`
+    #[clap(long, value_name = "[HOST]:PORT", required_unless_present = "logplex")]
+    forward: Option<String>,
+    /// Address to accept the HTTP requests of a Heroku log drain on, like `:8080` behind an HTTPS tunnel
+    #[clap(long, value_name = "[HOST]:PORT", conflicts_with = "forward")]
+    logplex: Option<String>,
+}
+
+/// A tag and its records, with the chunk id to acknowledge if the sender asked for it.
+#[derive(PartialEq, Debug)]
+struct Message {
+    tag: String,
+    records: Vec<Map<String, Value>>,
+    chunk: Option<String>,
+}
+
+type Serve = fn(TcpStream, &Sender<(String, String)>) -> io::Result<()>;
+
+pub fn run(opt: &ListenOpt) -> io::Result<()> {
+    let (address, protocol, serve, field): (_, _, Serve, _) = match (&opt.forward, &opt.logplex) {
+        (_, Some(address)) => (address, "Logplex drains", logplex::serve, "dyno"),
+        (Some(address), None) => (address, "the forward protocol", serve, "tag"),
+        (None, None) => unreachable!("clap requires --forward or --logplex"),
+    };
+    let address = match address.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => address.clone(),
+    };
+    let listener = TcpListener::bind(address)?;
+    eprintln!(
+        "ndjson: listening for {} on {}",
+        protocol,
+        listener.local_addr()?
+    );
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("ndjson: {} connection failed: {}", protocol, err);
+                    continue;
+                }
+            };
+            let sender = sender.clone();
+            thread::spawn(move || {
+                if let Err(err) = serve(stream, &sender) {
+                    eprintln!("ndjson: {} connection failed: {}", protocol, err);
+                }
+            });
+        }
+    });
+    crate::print_tagged_lines(field, receiver.into_iter().map(Ok))
+}
+
+fn serve(stream: TcpStream, sender: &Sender<(String, String)>) -> io::Result<()> {
+    let mut reply = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    while let Some(message) = read_message(&mut reader, MAX_MESSAGE)? {
+        let message = decode(message)?;
+        for record in message.records {
+            let line = Value::Object(record).to_string();
+            if sender.send((message.tag.clone(), line)).is_err() {
+                return Ok(());
+            }
+        }
+        if let Some(chunk) = message.chunk {
+            let mut ack = vec![0x81];
+            msgpack::write_str(&mut ack, "ack");
+            msgpack::write_str(&mut ack, &chunk);
+            reply.write_all(&ack)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the next message of at most `limit` bytes, or `None` if the connection closed.
+fn read_message<R: Read>(reader: &mut R, limit: u64) -> io::Result<Option<Value>> {
+    let mut limited = reader.take(limit);
+    match msgpack::read_value(&mut limited, binary::MAX_DEPTH) {
+        Err(_) if limited.limit() == 0 => Err(binary::invalid(&format!(
+            "forward message over {}",
+            size::format(limit)
+        ))),
+        result => result,
+    }
+}
+
+/// Decodes the message, forward and packed forward modes of the protocol.
+fn decode(message: Value) -> io::Result<Message> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut items = match message {
+        Value::Array(items) if items.len() >= 2 => items.into_iter(),
+        _ => return Err(invalid("forward message isn't an array with a tag")),
+    };
+    let tag = match items.next() {
+        Some(Value::String(tag)) => tag,
+        _ => return Err(invalid("forward message tag isn't a string")),
+    };
+    let mut records = Vec::new();
+    let option = match items.next().unwrap() {
+        Value::Array(entries) if entries.iter().all(Value::is_array) => {
+            for entry in entries {
+                records.extend(decode_entry(entry));
+            }
+            items.next()
+        }
+        Value::String(packed) => {
+            decode_packed(packed.as_bytes(), &mut records)?;
+            items.next()
+        }
+        time => match binary_value_bytes(&time) {
+            Some(packed) => {
+                decode_packed(&packed, &mut records)?;
+                items.next()
+            }
+            None => {
+                let record = items.next().unwrap_or(Value::Null);
+                records.extend(decode_entry(Value::Array(vec![time, record])));
+                items.next()
+            }
+        },
+    };
+    let option = option.unwrap_or(Value::Null);
+    if option.get("compressed").and_then(Value::as_str) == Some("gzip") {
+        return Err(invalid("compressed forward messages aren't supported"));
+    }
+    Ok(Message {
+        tag,
+        records,
+        chunk: option
+            .get("chunk")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+fn decode_packed(mut bytes: &[u8], records: &mut Vec<Map<String, Value>>) -> io::Result<()> {
+    while let Some(entry) = msgpack::read_value(&mut bytes, binary::MAX_DEPTH)? {
+        records.extend(decode_entry(entry));
+    }
+    Ok(())
+}
+
+/// Returns the record of a `[time, record]` entry, with the time added unless it has one.
+fn decode_entry(entry: Value) -> Option<Map<String, Value>> {
+    let (time, record) = match entry {
+        Value::Array(mut entry) if entry.len() == 2 => {
+            let record = entry.pop()?;
+            (entry.pop()?, record)
+        }
+        _ => return None,
+    };
+    let mut record = match record {
+        Value::Object(record) => record,
+        _ => return None,
+    };
+    if timestamp::detect(&record).is_some() {
+        return Some(record);
+    }
+    let time = match (time.get("type"), time.get("data").and_then(Value::as_array)) {
+        (Some(kind), Some(data)) if kind == 0 => {
+            let bytes: Vec<u64> = data.iter().filter_map(Value::as_u64).collect();
+            let word = |bytes: &[u64]| bytes.iter().fold(0, |word, byte| word << 8 | byte);
+            match bytes.len() {
+                8 => Value::from(word(&bytes[..4]) as f64 + word(&bytes[4..]) as f64 / 1e9),
+                // A malformed EventTime is a missing time.
+                _ => Value::Null,
+            }
+        }
+        _ => time,
+    };
+    let mut timed = Map::new();
+    if let Some(time) = timestamp::parse(&time) {
+        let time = time.to_rfc3339_opts(SecondsFormat::Millis, true);
+        timed.insert("time".to_string(), Value::String(time));
+    }
+    timed.append(&mut record);
+    Some(timed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::binary_value;
+    use serde_json::json;
+
+    fn record(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_modes() {
+        let expected = Message {
+            tag: "app".to_string(),
+            records: vec![record(
+                json!({"time": "2023-11-14T22:13:20.000Z", "msg": "a"}),
+            )],
+            chunk: None,
+        };
+        let message = json!(["app", 1700000000, {"msg": "a"}]);
+        assert_eq!(decode(message).unwrap(), expected);
+        let message = json!(["app", [[1700000000, {"msg": "a"}]]]);
+        assert_eq!(decode(message).unwrap(), expected);
+        let packed = b"\x92\xce\x65\x53\xf1\x00\x81\xa3msg\xa1a";
+        let message = json!(["app", binary_value(packed), {"chunk": "c1"}]);
+        assert_eq!(
+            decode(message).unwrap(),
+            Message {
+                chunk: Some("c1".to_string()),
+                ..expected
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut input = &b"\x92\xa3app\x81\xa1a\x01\x92\xa3app\xa4long"[..];
+        assert_eq!(
+            read_message(&mut input, 10).unwrap(),
+            Some(json!(["app", {"a": 1}]))
+        );
+        let err = read_message(&mut input, 8).unwrap_err();
+        assert_eq!(err.to_string(), "forward message over 8 B");
+        let deep = [0x91; 1 << 16];
+        assert!(read_message(&mut &deep[..], MAX_MESSAGE).is_err());
+    }
+
+    #[test]
+    fn test_event_time() {
+        let time = json!({"type": 0, "data": [0x65, 0x53, 0xf1, 0x00, 0x1d, 0xcd, 0x65, 0x00]});
+        assert_eq!(
+            decode_entry(json!([time, {"msg": "a"}])),
+            Some(record(
+                json!({"time": "2023-11-14T22:13:20.500Z", "msg": "a"})
+            ))
+        );
+        for data in [
+            json!([0x65, 0x53, 0xf1]),
+            json!([0x65, "a", "b", "c", "d", "e", "f", "g"]),
+        ] {
+            let time = json!({"type": 0, "data": data});
+            assert_eq!(
+                decode_entry(json!([time, {"msg": "a"}])),
+                Some(record(json!({"msg": "a"})))
+            );
+        }
+        assert_eq!(
+            decode_entry(json!([1, {"ts": 5}])),
+            Some(record(json!({"ts": 5})))
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use maxminddb::Reader;
+use serde_json::{Map, Value};
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+
+const DEFAULT_DATABASES: [&str; 4] = [
+    "/usr/share/GeoIP/GeoLite2-City.mmdb",
+    "/usr/share/GeoIP/GeoLite2-ASN.mmdb",
+    "/var/lib/GeoIP/GeoLite2-City.mmdb",
+    "/var/lib/GeoIP/GeoLite2-ASN.mmdb",
+];
+
+pub struct GeoIp {
+    field: String,
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    /// Opens the databases for a `field[:mmdb_path]` spec, defaulting to the GeoLite2 databases.
+    pub fn open(spec: &str) -> io::Result<Self> {
+        let (field, paths) = match spec.split_once(':') {
+            Some((field, path)) => (field, vec![path]),
+            None => (
+                spec,
+                DEFAULT_DATABASES
+                    .iter()
+                    .copied()
+                    .filter(|path| Path::new(path).exists())
+                    .collect(),
+            ),
+        };
+        if paths.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no GeoLite2 database found, pass one with --geoip field:path",
+            ));
+        }
+        let readers = paths
+            .into_iter()
+            .map(|path| Reader::open_readfile(path).map_err(io::Error::other))
+            .collect::<io::Result<_>>()?;
+        Ok(GeoIp {
+            field: field.to_string(),
+            readers,
+        })
+    }
+
+    /// Inserts the location of the address after its field, returns whether anything was found.
+    pub fn annotate(&self, object: &mut Map<String, Value>) -> bool {
+        let address = match object.get(&self.field).and_then(Value::as_str) {
+            Some(address) => address,
+            None => return false,
+        };
+        let address = match address.parse::<IpAddr>() {
+            Ok(address) => address,
+            Err(_) => return false,
+        };
+        let mut derived = Vec::new();
+        for reader in &self.readers {
+            let record = reader
+                .lookup(address)
+                .and_then(|result| result.decode::<Value>());
+            if let Ok(Some(record)) = record {
+                derived.extend(location_fields(&self.field, &record));
+            }
+        }
+        let found = !derived.is_empty();
+        crate::pipeline::insert_after(object, &self.field, derived);
+        found
+    }
+}
+
+fn location_fields(field: &str, record: &Value) -> Vec<(String, Value)> {
+    let mut fields = Vec::new();
+    if let Some(country) = record.pointer("/country/iso_code") {
+        fields.push((format!("{}_country", field), country.clone()));
+    }
+    if let Some(city) = record.pointer("/city/names/en") {
+        fields.push((format!("{}_city", field), city.clone()));
+    }
+    if let Some(number) = record.get("autonomous_system_number") {
+        let asn = match record
+            .get("autonomous_system_organization")
+            .and_then(Value::as_str)
+        {
+            Some(organization) => format!("AS{} {}", number, organization),
+            None => format!("AS{}", number),
+        };
+        fields.push((format!("{}_asn", field), Value::String(asn)));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_location_fields() {
+        let city = json!({
+            "city": {"names": {"de": "Berlin", "en": "Berlin"}},
+            "country": {"iso_code": "DE", "names": {"en": "Germany"}}
+        });
+        assert_eq!(
+            location_fields("ip", &city),
+            [
+                ("ip_country".to_string(), json!("DE")),
+                ("ip_city".to_string(), json!("Berlin"))
+            ]
+        );
+        let asn = json!({
+            "autonomous_system_number": 3320,
+            "autonomous_system_organization": "Deutsche Telekom AG"
+        });
+        assert_eq!(
+            location_fields("ip", &asn),
+            [("ip_asn".to_string(), json!("AS3320 Deutsche Telekom AG"))]
+        );
+    }
+}
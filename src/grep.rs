@@ -0,0 +1,146 @@
+use crate::pipeline::Processed;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Line written between groups of matching lines and their context which aren't adjacent.
+pub const SEPARATOR: &[u8] = b"--\n";
+
+/// Keeps the lines matching a regex, with the lines before and after them like `grep -B -A`.
+pub struct Grep {
+    regex: Regex,
+    before: usize,
+    after: usize,
+    /// The last lines which didn't match, written before the next match.
+    recent: VecDeque<(Vec<u8>, Processed)>,
+    /// Lines still written after the last match.
+    remaining: usize,
+    /// Whether lines were written, and lines were left out since then.
+    written: bool,
+    skipped: bool,
+}
+
+impl Grep {
+    pub fn new(regex: Regex, before: usize, after: usize) -> Self {
+        Grep {
+            regex,
+            before,
+            after,
+            recent: VecDeque::with_capacity(before),
+            remaining: 0,
+            written: false,
+            skipped: false,
+        }
+    }
+
+    /// Returns the lines to write after reading a line, preceded by the separator if they don't
+    /// follow the lines written before. Dropped lines are neither matched nor context.
+    pub fn filter(&mut self, line: Vec<u8>, processed: Processed) -> Vec<(Vec<u8>, Processed)> {
+        if let Processed::Dropped = processed {
+            return Vec::new();
+        }
+        if !self.matches(&line, &processed) {
+            if self.remaining > 0 {
+                self.remaining -= 1;
+                return self.write(vec![(line, processed)]);
+            }
+            if self.recent.len() == self.before {
+                self.recent.pop_front();
+                self.skipped = true;
+            }
+            if self.before > 0 {
+                self.recent.push_back((line, processed));
+            }
+            return Vec::new();
+        }
+        self.remaining = self.after;
+        let mut lines: Vec<_> = self.recent.drain(..).collect();
+        lines.push((line, processed));
+        self.write(lines)
+    }
+
+    fn write(&mut self, mut lines: Vec<(Vec<u8>, Processed)>) -> Vec<(Vec<u8>, Processed)> {
+        if self.written && self.skipped {
+            lines.insert(0, (SEPARATOR.to_vec(), Processed::Unchanged(None)));
+        }
+        self.written = true;
+        self.skipped = false;
+        lines
+    }
+
+    /// Returns whether the raw line or a value of the record as it is written matches.
+    fn matches(&self, line: &[u8], processed: &Processed) -> bool {
+        if self.regex.is_match(&String::from_utf8_lossy(line)) {
+            return true;
+        }
+        match processed {
+            Processed::Unchanged(Some(value)) | Processed::Changed(value) => {
+                self.matches_value(value)
+            }
+            _ => false,
+        }
+    }
+
+    fn matches_value(&self, value: &Value) -> bool {
+        match value {
+            Value::String(string) => self.regex.is_match(string),
+            Value::Array(array) => array.iter().any(|value| self.matches_value(value)),
+            Value::Object(object) => object.values().any(|value| self.matches_value(value)),
+            value => self.regex.is_match(&value.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn grep(grep: &mut Grep, lines: &[&str]) -> Vec<String> {
+        let mut written = Vec::new();
+        for line in lines {
+            let processed = match serde_json::from_str(line) {
+                Ok(value) => Processed::Unchanged(Some(value)),
+                Err(_) if *line == "dropped" => Processed::Dropped,
+                Err(_) => Processed::Unchanged(None),
+            };
+            for (line, _) in grep.filter(line.as_bytes().to_vec(), processed) {
+                written.push(String::from_utf8(line).unwrap());
+            }
+        }
+        written
+    }
+
+    #[test]
+    fn test_context() {
+        let lines = [
+            "a", "b", "match 1", "c", "d", "e", "match 2", "f", "match 3", "g",
+        ];
+        let mut context = Grep::new(Regex::new("match").unwrap(), 1, 1);
+        assert_eq!(
+            grep(&mut context, &lines),
+            ["b", "match 1", "c", "--\n", "e", "match 2", "f", "match 3", "g"]
+        );
+        let mut only = Grep::new(Regex::new("match").unwrap(), 0, 0);
+        assert_eq!(
+            grep(&mut only, &lines),
+            ["match 1", "--\n", "match 2", "--\n", "match 3"]
+        );
+        let mut adjacent = Grep::new(Regex::new("match").unwrap(), 0, 0);
+        assert_eq!(
+            grep(&mut adjacent, &["match 1", "dropped", "match 2"]),
+            ["match 1", "match 2"]
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let grep = Grep::new(Regex::new("^caf\u{e9}$|^404$").unwrap(), 0, 0);
+        let escaped = br#"{"name":"caf\u00e9"}"#;
+        let value = serde_json::from_slice(escaped).unwrap();
+        assert!(grep.matches(escaped, &Processed::Unchanged(Some(value))));
+        assert!(grep.matches(b"", &Processed::Changed(json!({"a": [{"status": 404}]}))));
+        assert!(!grep.matches(b"404 not found", &Processed::Unchanged(None)));
+        assert!(!grep.matches(b"cafe", &Processed::Unchanged(None)));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::level::Level;
+use crate::limits::Limits;
+use crate::predicate::Predicate;
+use crate::timestamp;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::Args;
+use serde_json::{json, Value};
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, BufWriter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Format of the index, which is ignored by versions expecting another one.
+const VERSION: u64 = 2;
+const LEVELS: [Level; 6] = [
+    Level::Trace,
+    Level::Debug,
+    Level::Info,
+    Level::Warn,
+    Level::Error,
+    Level::Fatal,
+];
+
+/// Builds a sidecar index of line offsets, time ranges and levels per block of lines, with which
+/// --lines and --where conditions on the level skip blocks of the file while it is unchanged
+#[derive(Args, Debug)]
+pub struct IndexOpt {
+    file: PathBuf,
+    /// Number of lines per indexed block
+    #[clap(long, value_name = "LINES", default_value = "4096")]
+    block_lines: u64,
+}
+
+pub fn run(opt: &IndexOpt) -> io::Result<()> {
+    let file = File::open(&opt.file)?;
+    let metadata = file.metadata()?;
+    let blocks = build(BufReader::new(file), opt.block_lines.max(1))?;
+    let index = json!({
+        "version": VERSION,
+        "size": metadata.len(),
+        "modified": modified(&metadata)?,
+        "block_lines": opt.block_lines,
+        "blocks": blocks.iter().map(Block::to_json).collect::<Vec<_>>(),
+    });
+    let path = index_path(&opt.file);
+    serde_json::to_writer(BufWriter::new(File::create(&path)?), &index)?;
+    eprintln!(
+        "ndjson: indexed {} lines in {} blocks to {}",
+        blocks.iter().map(|block| block.lines).sum::<u64>(),
+        blocks.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Returns the path of the index next to the file, like `app.ndjson.idx`.
+pub fn index_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".idx");
+    PathBuf::from(path)
+}
+
+fn modified(metadata: &Metadata) -> io::Result<String> {
+    let modified: DateTime<Utc> = metadata.modified()?.into();
+    Ok(modified.to_rfc3339_opts(SecondsFormat::Millis, true))
+}
+
+/// The blocks of the index of a file.
+pub struct Index {
+    size: u64,
+    blocks: Vec<Block>,
+}
+
+impl Index {
+    /// Loads the index next to a file, if it has one of this version from when the file had its
+    /// current size and modification time.
+    pub fn load(file: &Path, metadata: &Metadata) -> Option<Index> {
+        let index: Value = serde_json::from_slice(&fs::read(index_path(file)).ok()?).ok()?;
+        if index["version"] != VERSION
+            || index["size"] != metadata.len()
+            || index["modified"] != modified(metadata).ok()?
+        {
+            return None;
+        }
+        Some(Index {
+            size: metadata.len(),
+            blocks: index["blocks"]
+                .as_array()?
+                .iter()
+                .map(Block::from_json)
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// Returns the offset and the zero-based number of the first line of the block holding a line.
+    pub fn find_line(&self, line: u64) -> Option<(u64, u64)> {
+        self.blocks
+            .iter()
+            .take_while(|block| block.line - 1 <= line)
+            .last()
+            .map(|block| (block.offset, block.line - 1))
+    }
+
+    /// Returns the byte ranges of the blocks in which no record can match all the conditions.
+    pub fn skippable(&self, conditions: &[Predicate]) -> Vec<Range<u64>> {
+        let mut ranges: Vec<Range<u64>> = Vec::new();
+        for (index, block) in self.blocks.iter().enumerate() {
+            if block.can_match(conditions) {
+                continue;
+            }
+            let end = self
+                .blocks
+                .get(index + 1)
+                .map_or(self.size, |next| next.offset);
+            match ranges.last_mut() {
+                Some(range) if range.end == block.offset => range.end = end,
+                _ => ranges.push(block.offset..end),
+            }
+        }
+        ranges
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct Block {
+    offset: u64,
+    line: u64,
+    lines: u64,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    /// Bit `n` is set if a record has the `n`th level from trace to fatal in its `level` field.
+    levels: u8,
+    /// Lines which can't be skipped by their level: text, JSON which isn't an object or exceeds
+    /// the default limits, and records with an unknown level.
+    plain: u64,
+}
+
+impl Block {
+    fn from_json(block: &Value) -> Option<Block> {
+        let time = |time: &Value| match time {
+            Value::Null => Some(None),
+            time => DateTime::parse_from_rfc3339(time.as_str()?)
+                .ok()
+                .map(|time| Some(time.with_timezone(&Utc))),
+        };
+        Some(Block {
+            offset: block["offset"].as_u64()?,
+            line: block["line"].as_u64().filter(|line| *line > 0)?,
+            lines: block["lines"].as_u64()?,
+            from: time(&block["from"])?,
+            to: time(&block["to"])?,
+            levels: block["levels"].as_u64()? as u8,
+            plain: block["plain"].as_u64()?,
+        })
+    }
+
+    /// Returns whether a record of the block may match all conditions, which is decided by the
+    /// levels of the records if a condition is on the level.
+    fn can_match(&self, conditions: &[Predicate]) -> bool {
+        let by_level = conditions
+            .iter()
+            .any(|condition| condition.matches_level(Level::Info).is_some());
+        if !by_level || self.plain > 0 {
+            return true;
+        }
+        LEVELS
+            .iter()
+            .filter(|level| self.levels & 1 << **level as u8 != 0)
+            .any(|level| {
+                conditions
+                    .iter()
+                    .all(|condition| condition.matches_level(*level) != Some(false))
+            })
+    }
+
+    fn to_json(&self) -> Value {
+        let time = |time: Option<DateTime<Utc>>| {
+            time.map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        };
+        json!({
+            "offset": self.offset,
+            "line": self.line,
+            "lines": self.lines,
+            "from": time(self.from),
+            "to": time(self.to),
+            "levels": self.levels,
+            "plain": self.plain,
+        })
+    }
+}
+
+fn build<R: BufRead>(mut reader: R, block_lines: u64) -> io::Result<Vec<Block>> {
+    let limits = Limits::default();
+    let mut blocks: Vec<Block> = Vec::new();
+    let (mut offset, mut line_number) = (0, 1);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)? as u64;
+        if read == 0 {
+            return Ok(blocks);
+        }
+        let block = match blocks.last_mut() {
+            Some(block) if block.lines < block_lines => block,
+            _ => {
+                blocks.push(Block {
+                    offset,
+                    line: line_number,
+                    lines: 0,
+                    from: None,
+                    to: None,
+                    levels: 0,
+                    plain: 0,
+                });
+                blocks.last_mut().unwrap()
+            }
+        };
+        block.lines += 1;
+        match serde_json::from_slice(&line) {
+            Ok(Value::Object(object)) if limits.check(&line).is_none() => {
+                if let Some(time) = timestamp::detect(&object) {
+                    block.from = Some(block.from.map_or(time, |from| from.min(time)));
+                    block.to = Some(block.to.map_or(time, |to| to.max(time)));
+                }
+                match object.get("level").map(Level::parse) {
+                    Some(Some(level)) => block.levels |= 1 << level as u8,
+                    Some(None) => block.plain += 1,
+                    None => {}
+                }
+            }
+            _ => block.plain += 1,
+        }
+        offset += read;
+        line_number += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_build() {
+        let input = "{\"ts\":20,\"level\":\"info\"}\n{\"ts\":10,\"level\":\"error\"}\ntext\n{}\n";
+        let blocks = build(input.as_bytes(), 2).unwrap();
+        let time = |seconds| Some(Utc.timestamp_opt(seconds, 0).unwrap());
+        assert_eq!(
+            blocks,
+            [
+                Block {
+                    offset: 0,
+                    line: 1,
+                    lines: 2,
+                    from: time(10),
+                    to: time(20),
+                    levels: 0b10100,
+                    plain: 0,
+                },
+                Block {
+                    offset: 51,
+                    line: 3,
+                    lines: 2,
+                    from: None,
+                    to: None,
+                    levels: 0,
+                    plain: 1,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skippable() {
+        let input = concat!(
+            "{\"level\":\"info\"}\n{\"level\":\"debug\"}\n",
+            "{\"level\":\"error\"}\n{}\n",
+            "{\"level\":\"info\"}\ntext\n",
+        );
+        let index = Index {
+            size: input.len() as u64,
+            blocks: build(input.as_bytes(), 2).unwrap(),
+        };
+        let skippable = |conditions: &[&str]| {
+            let conditions: Vec<_> = conditions
+                .iter()
+                .map(|condition| Predicate::parse(condition).unwrap())
+                .collect();
+            let ranges = index.skippable(&conditions).into_iter();
+            ranges
+                .map(|range| (range.start, range.end))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(skippable(&["level>=warn"]), [(0, 35)]);
+        assert_eq!(skippable(&["level<=info"]), [(35, 56)]);
+        assert_eq!(skippable(&["level>=warn", "level<=info"]), [(0, 56)]);
+        assert_eq!(skippable(&["level=fatal"]), [(0, 56)]);
+        assert!(skippable(&["status>=500"]).is_empty());
+        assert!(skippable(&["level!=info"]).is_empty());
+        assert_eq!(index.find_line(0), Some((0, 0)));
+        assert_eq!(index.find_line(3), Some((35, 2)));
+        assert_eq!(index.find_line(100), Some((56, 4)));
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("ndjson-index-{}", std::process::id()));
+        fs::write(&path, "{\"level\":\"info\"}\n{\"level\":\"error\"}\n").unwrap();
+        run(&IndexOpt {
+            file: path.clone(),
+            block_lines: 1,
+        })
+        .unwrap();
+        let index = Index::load(&path, &fs::metadata(&path).unwrap()).unwrap();
+        assert_eq!(index.blocks.len(), 2);
+        fs::write(&path, "{}\n").unwrap();
+        assert!(Index::load(&path, &fs::metadata(&path).unwrap()).is_none());
+        fs::remove_file(index_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(
+            index_path(Path::new("logs/app.ndjson")),
+            Path::new("logs/app.ndjson.idx")
+        );
+    }
+}
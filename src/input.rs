@@ -0,0 +1,726 @@
+use crate::avro::AvroReader;
+use crate::index::Index;
+use crate::predicate::Predicate;
+use crate::proto::ProtoDecoder;
+use crate::slice::{self, Slice, SlicedReader};
+use crate::{binary, cbor, msgpack, Framing, InputFormat};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+/// Number of lines read ahead of the processing.
+const BUFFER: usize = 1024;
+/// Time between checks of a followed file for changes.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
+pub enum Event {
+    /// A line including its terminator, which is missing only at the end of the input.
+    Line(io::Result<Vec<u8>>),
+    /// A line written to the control FIFO.
+    Annotation(String),
+    End,
+    Interrupted,
+}
+
+/// Reads the lines of the files one after another, or of stdin if there are none, as bytes on a
+/// separate thread, so Ctrl-C can be handled between lines. Blocks of lines which the index of a
+/// file shows to have no records matching the conditions are skipped.
+pub fn input_events(
+    files: Vec<PathBuf>,
+    follow: bool,
+    slice: Option<Slice>,
+    conditions: Vec<Predicate>,
+    decoding: Decoding,
+    explode: bool,
+    control: Option<PathBuf>,
+) -> io::Result<Receiver<Event>> {
+    let (sender, receiver) = mpsc::sync_channel(BUFFER);
+    let interrupt = sender.clone();
+    ctrlc::set_handler(move || {
+        let _ = interrupt.send(Event::Interrupted);
+    })
+    .map_err(io::Error::other)?;
+    if let Some(path) = control {
+        open_control(&path)?;
+        let sender = sender.clone();
+        thread::spawn(move || read_control(path, sender));
+    }
+    thread::spawn(move || {
+        let mut next_line = line_reader(files, follow, slice, conditions, decoding);
+        if explode {
+            next_line = explode_arrays(next_line);
+        }
+        loop {
+            let line = match next_line() {
+                Ok(None) => break,
+                Ok(Some(line)) => Ok(line),
+                Err(err) => Err(err),
+            };
+            let failed = line.is_err();
+            if sender.send(Event::Line(line)).is_err() || failed {
+                return;
+            }
+        }
+        let _ = sender.send(Event::End);
+    });
+    Ok(receiver)
+}
+
+/// Creates the control FIFO if it is missing, and makes sure an existing file is one.
+#[cfg(unix)]
+fn open_control(path: &Path) -> io::Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => Ok(()),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} isn't a FIFO", path.display()),
+        )),
+        Err(_) => match Command::new("mkfifo").arg(path).status()?.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!(
+                "couldn't create FIFO {}",
+                path.display()
+            ))),
+        },
+    }
+}
+
+#[cfg(not(unix))]
+fn open_control(_: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--control is only supported on Unix",
+    ))
+}
+
+/// Sends the lines written to the control FIFO, opening it again after each writer closed it.
+fn read_control(path: PathBuf, sender: SyncSender<Event>) {
+    loop {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                eprintln!("ndjson: can't read {}: {}", path.display(), err);
+                return;
+            }
+        };
+        for line in BufReader::new(file).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let message = line.trim();
+            if !message.is_empty() && sender.send(Event::Annotation(message.to_string())).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// How the records of the input are decoded into lines.
+pub struct Decoding {
+    pub format: InputFormat,
+    /// Prefix of each record with its length, instead of records being lines or following each
+    /// other.
+    pub framing: Option<Framing>,
+    pub proto: Option<ProtoDecoder>,
+    /// Nesting of arrays and maps decoded at most from MessagePack and CBOR.
+    pub depth: usize,
+}
+
+type LineReader = Box<dyn FnMut() -> io::Result<Option<Vec<u8>>>>;
+
+/// Returns a function reading the next line, with binary records decoded to JSON lines.
+fn line_reader(
+    files: Vec<PathBuf>,
+    follow: bool,
+    slice: Option<Slice>,
+    conditions: Vec<Predicate>,
+    decoding: Decoding,
+) -> LineReader {
+    let Decoding {
+        format,
+        framing,
+        proto,
+        depth,
+    } = decoding;
+    let lines = format == InputFormat::Json && framing.is_none();
+    let stdin = files.is_empty();
+    // Regular files are seeked to the start of a byte slice, or to the indexed block holding the
+    // first line of a line slice, other files are read up to it. The offset is in the unit of the
+    // slice.
+    let regular = !stdin && files.iter().all(|path| is_regular_file(path));
+    let (skip, offset) = match slice {
+        Some(Slice::Lines(start, _)) if regular => fs::metadata(&files[0])
+            .ok()
+            .and_then(|metadata| Index::load(&files[0], &metadata))
+            .and_then(|index| index.find_line(start))
+            .unwrap_or((0, 0)),
+        Some(slice) if regular => slice.seek_offset().map_or((0, 0), |skip| (skip, skip)),
+        _ => (0, 0),
+    };
+    // Skipping blocks would throw off the counting of a slice.
+    let conditions = match slice {
+        None if lines => conditions,
+        _ => Vec::new(),
+    };
+    let open = move || -> Box<dyn BufRead> {
+        match stdin {
+            true => Box::new(io::stdin().lock()),
+            false => {
+                let mut files = Files::new(files, lines, follow);
+                files.skip = skip;
+                files.conditions = conditions;
+                Box::new(files)
+            }
+        }
+    };
+    if let Some(framing) = framing {
+        let mut reader = open();
+        return Box::new(move || match binary::read_frame(&mut reader, framing)? {
+            Some(frame) => decode_frame(format, frame, depth).map(Some),
+            None => Ok(None),
+        });
+    }
+    let decode: fn(&mut Box<dyn BufRead>, usize) -> io::Result<Option<Value>> = match format {
+        InputFormat::Json => {
+            let (reader, offset): (Box<dyn BufRead>, u64) = match slice
+                .filter(|_| stdin)
+                .and_then(|slice| slice::open_stdin(Some(slice)))
+            {
+                Some((file, offset)) => (Box::new(BufReader::new(file)), offset),
+                None => (open(), offset),
+            };
+            let mut reader = SlicedReader::new(reader, slice, offset);
+            return Box::new(move || {
+                let mut line = Vec::new();
+                match reader.read_line(&mut line)? {
+                    0 => Ok(None),
+                    _ => Ok(Some(line)),
+                }
+            });
+        }
+        InputFormat::Msgpack => msgpack::read_value,
+        InputFormat::Cbor => cbor::read_value,
+        InputFormat::Avro => {
+            let (mut open, mut reader) = (Some(open), None);
+            return Box::new(move || {
+                if let Some(open) = open.take() {
+                    reader = Some(AvroReader::new(open())?);
+                }
+                Ok(reader.as_mut().unwrap().next_record()?.map(json_line))
+            });
+        }
+        InputFormat::Proto => {
+            let decoder = proto.expect("--input proto without a descriptor");
+            let mut reader = open();
+            return Box::new(move || Ok(decoder.read_value(&mut reader)?.map(json_line)));
+        }
+    };
+    let mut reader = open();
+    Box::new(move || Ok(decode(&mut reader, depth)?.map(json_line)))
+}
+
+/// Decodes a length-prefixed record into a line. JSON records are written compactly, so they
+/// don't span lines, and frames which aren't JSON are kept as they are.
+fn decode_frame(format: InputFormat, frame: Vec<u8>, depth: usize) -> io::Result<Vec<u8>> {
+    let value = match format {
+        InputFormat::Msgpack => msgpack::read_value(&mut &frame[..], depth)?,
+        InputFormat::Cbor => cbor::read_value(&mut &frame[..], depth)?,
+        _ => serde_json::from_slice(&frame).ok(),
+    };
+    Ok(match value {
+        Some(value) => json_line(value),
+        None => [&frame[..], b"\n"].concat(),
+    })
+}
+
+/// Returns a function reading the elements of JSON arrays as separate lines, for lines which are
+/// an array and for arrays spread over several lines, like a saved API response. Lines are joined
+/// only until the brackets are balanced or the text can't start an array anymore, so following a
+/// file isn't held up by a line like `[INFO] started`, and lines which don't end up being a valid
+/// array are read unchanged.
+fn explode_arrays(mut next_line: LineReader) -> LineReader {
+    let mut pending = VecDeque::new();
+    Box::new(move || loop {
+        if let Some(line) = pending.pop_front() {
+            return Ok(Some(line));
+        }
+        let mut text = match next_line()? {
+            Some(line) if line.trim_ascii_start().starts_with(b"[") => line,
+            line => return Ok(line),
+        };
+        let mut nesting = Nesting::default();
+        nesting.scan(&text);
+        let mut checked = 0;
+        while nesting.depth > 0 {
+            // Parsing again only once the text doubled keeps joining linear in its length.
+            if text.len() >= checked * 2 {
+                match serde_json::from_slice::<Value>(&text) {
+                    Err(err) if err.is_eof() => checked = text.len(),
+                    _ => break,
+                }
+            }
+            match next_line()? {
+                Some(line) => {
+                    nesting.scan(&line);
+                    text.extend(line);
+                }
+                None => break,
+            }
+        }
+        match serde_json::from_slice(&text) {
+            Ok(Value::Array(elements)) => pending.extend(elements.into_iter().map(json_line)),
+            _ => pending.extend(
+                text.split_inclusive(|byte| *byte == b'\n')
+                    .map(<[u8]>::to_vec),
+            ),
+        }
+    })
+}
+
+/// Tracks the brackets left open by text which may be JSON, ignoring those in strings.
+#[derive(Default)]
+struct Nesting {
+    depth: usize,
+    string: bool,
+    escaped: bool,
+}
+
+impl Nesting {
+    fn scan(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            match (self.string, byte) {
+                (true, _) if self.escaped => self.escaped = false,
+                (true, b'\\') => self.escaped = true,
+                (true, b'"') | (false, b'"') => self.string = !self.string,
+                (true, _) => {}
+                (false, b'[' | b'{') => self.depth += 1,
+                (false, b']' | b'}') => self.depth = self.depth.saturating_sub(1),
+                (false, _) => {}
+            }
+        }
+    }
+}
+
+/// Reads files one after another, ending the last line of a file if it lacks a terminator so it
+/// isn't joined with the first line of the next one. The last file can be followed like `tail -F`.
+struct Files {
+    paths: VecDeque<PathBuf>,
+    current: Option<(PathBuf, BufReader<File>)>,
+    /// Whether the last file is read again as it grows, reopened when it was replaced and read from
+    /// the start when it was truncated.
+    follow: bool,
+    /// Bytes read from the current file.
+    position: u64,
+    /// Whether a missing line terminator is added at the end of a file.
+    lines: bool,
+    /// Whether the last byte read from the current file was a line terminator.
+    terminated: bool,
+    /// Whether a line terminator is due before the next file.
+    newline: bool,
+    /// Bytes at the start of the input to skip by seeking, past whole files by their size.
+    skip: u64,
+    /// Conditions of records, with which blocks of files are skipped if their index shows that
+    /// none of their records match.
+    conditions: Vec<Predicate>,
+    /// Byte ranges of the current file to skip.
+    skipped: VecDeque<Range<u64>>,
+}
+
+impl Files {
+    fn new(paths: Vec<PathBuf>, lines: bool, follow: bool) -> Self {
+        Files {
+            paths: paths.into(),
+            current: None,
+            follow,
+            position: 0,
+            lines,
+            terminated: true,
+            newline: false,
+            skip: 0,
+            conditions: Vec::new(),
+            skipped: VecDeque::new(),
+        }
+    }
+
+    /// Waits for the followed file to change, reopening it if the path refers to another file now
+    /// and rewinding it if it became shorter than what was read. A replaced file is read to its
+    /// end first, for lines written to it just before it was rotated.
+    fn wait(&mut self) -> io::Result<()> {
+        thread::sleep(FOLLOW_INTERVAL);
+        let (path, reader) = match &mut self.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(()),
+        };
+        if !is_same_file(&metadata, &reader.get_ref().metadata()?) {
+            if !reader.fill_buf()?.is_empty() {
+                return Ok(());
+            }
+            *reader = BufReader::new(File::open(&path)?);
+            self.position = 0;
+            self.skipped.clear();
+        } else if metadata.len() < self.position {
+            reader.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+            self.skipped.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether the metadata is of the same file, by its inode on Unix and by its creation time
+/// elsewhere.
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[cfg(not(unix))]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.created().ok() == b.created().ok()
+}
+
+fn is_regular_file(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+}
+
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+impl Read for Files {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl BufRead for Files {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        loop {
+            if self.newline {
+                return Ok(b"\n");
+            }
+            match &mut self.current {
+                Some((_, reader)) => {
+                    let position = self.position;
+                    if let Some(range) = self.skipped.pop_front() {
+                        if range.start > position {
+                            self.skipped.push_front(range);
+                        } else {
+                            if range.end > position {
+                                self.position = reader.seek(SeekFrom::Start(range.end))?;
+                                self.terminated = true;
+                            }
+                            continue;
+                        }
+                    }
+                    match reader.fill_buf()?.is_empty() {
+                        true if self.follow && self.paths.is_empty() => self.wait()?,
+                        true => {
+                            self.current = None;
+                            self.newline = self.lines && !self.terminated;
+                        }
+                        false => break,
+                    }
+                }
+                None => {
+                    let path = match self.paths.pop_front() {
+                        Some(path) => path,
+                        None => return Ok(&[]),
+                    };
+                    let mut file = File::open(&path).map_err(|err| {
+                        io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+                    })?;
+                    self.position = 0;
+                    self.terminated = true;
+                    if self.skip > 0 {
+                        let len = file.metadata()?.len();
+                        let followed = self.follow && self.paths.is_empty();
+                        if self.skip >= len && !followed {
+                            // The terminator added after the file is part of the input too.
+                            self.skip -= len;
+                            if self.lines && len > 0 && !ends_with_newline(&mut file)? {
+                                match self.skip {
+                                    0 => self.newline = true,
+                                    _ => self.skip -= 1,
+                                }
+                            }
+                            continue;
+                        }
+                        self.position = file.seek(SeekFrom::Start(self.skip.min(len)))?;
+                        self.skip = 0;
+                    }
+                    self.skipped = match self.conditions.is_empty() {
+                        true => VecDeque::new(),
+                        false => Index::load(&path, &file.metadata()?)
+                            .map(|index| index.skippable(&self.conditions).into())
+                            .unwrap_or_default(),
+                    };
+                    self.current = Some((path, BufReader::new(file)));
+                }
+            }
+        }
+        // Stops at the next skipped range, for it to be seeked past.
+        let until = match self.skipped.front() {
+            Some(range) => (range.start - self.position).min(usize::MAX as u64) as usize,
+            None => usize::MAX,
+        };
+        let buffer = self.current.as_mut().unwrap().1.fill_buf()?;
+        Ok(&buffer[..buffer.len().min(until)])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if self.newline {
+            self.newline = amount == 0;
+            return;
+        }
+        if let Some((_, reader)) = &mut self.current {
+            if amount > 0 {
+                self.terminated = reader.buffer()[amount - 1] == b'\n';
+            }
+            self.position += amount as u64;
+            reader.consume(amount);
+        }
+    }
+}
+
+fn json_line(value: Value) -> Vec<u8> {
+    let mut line = value.to_string().into_bytes();
+    line.push(b'\n');
+    line
+}
+
+/// Returns the line without its `\n` or `\r\n` terminator.
+pub fn trim_newline(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn read_all(mut next_line: LineReader) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Some(line) = next_line().unwrap() {
+            lines.push(String::from_utf8(line).unwrap());
+        }
+        lines
+    }
+
+    #[test]
+    fn test_explode_arrays() {
+        let lines = |text: &'static str| -> LineReader {
+            let mut lines = text
+                .split_inclusive('\n')
+                .map(|line| line.as_bytes().to_vec());
+            Box::new(move || Ok(lines.next()))
+        };
+        assert_eq!(
+            read_all(explode_arrays(lines(
+                "{\"a\":0}\n[{\"a\":1}, {\"a\":2}]\n[]\n"
+            ))),
+            ["{\"a\":0}\n", "{\"a\":1}\n", "{\"a\":2}\n"]
+        );
+        assert_eq!(
+            read_all(explode_arrays(lines("[\n  {\"a\": 1},\n  {\"a\": 2}\n]\n"))),
+            ["{\"a\":1}\n", "{\"a\":2}\n"]
+        );
+        assert_eq!(
+            read_all(explode_arrays(lines("[INFO] started\nplain\n"))),
+            ["[INFO] started\n", "plain\n"]
+        );
+        assert_eq!(
+            read_all(explode_arrays(lines("[\"]\\\"\",\n{\"a\": \"[\"}]\n[1]\n"))),
+            ["\"]\\\"\"\n", "{\"a\":\"[\"}\n", "1\n"]
+        );
+    }
+
+    #[test]
+    fn test_explode_arrays_following() {
+        // Stands for a followed file without more lines yet, which must not be waited for.
+        let waiting = |text: &'static str| -> LineReader {
+            let mut lines = text
+                .split_inclusive('\n')
+                .map(|line| line.as_bytes().to_vec());
+            Box::new(move || match lines.next() {
+                Some(line) => Ok(Some(line)),
+                None => Err(io::ErrorKind::WouldBlock.into()),
+            })
+        };
+        let mut next_line = explode_arrays(waiting("[INFO started\n"));
+        assert_eq!(next_line().unwrap().unwrap(), b"[INFO started\n");
+        let mut next_line = explode_arrays(waiting("[INFO] started\n"));
+        assert_eq!(next_line().unwrap().unwrap(), b"[INFO] started\n");
+        let mut next_line = explode_arrays(waiting("[\n  {\"a\": 1}\n]\n"));
+        assert_eq!(next_line().unwrap().unwrap(), b"{\"a\":1}\n");
+        let mut next_line = explode_arrays(waiting("[\nplain\n"));
+        assert_eq!(next_line().unwrap().unwrap(), b"[\n");
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        let line = |format, frame: &[u8]| decode_frame(format, frame.to_vec(), 64).unwrap();
+        assert_eq!(line(InputFormat::Json, b"{\"a\": 1}"), b"{\"a\":1}\n");
+        assert_eq!(line(InputFormat::Json, b"text"), b"text\n");
+        assert_eq!(line(InputFormat::Msgpack, b"\x81\xa1a\x01"), b"{\"a\":1}\n");
+        assert_eq!(line(InputFormat::Cbor, b"\xa1\x61a\x01"), b"{\"a\":1}\n");
+        assert!(decode_frame(InputFormat::Msgpack, b"\x81".to_vec(), 64).is_err());
+    }
+
+    #[test]
+    fn test_files() {
+        let dir = std::env::temp_dir().join(format!("ndjson-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b, c) = (dir.join("a"), dir.join("b"), dir.join("c"));
+        fs::write(&a, "{\"n\":1}\n{\"n\":2}").unwrap();
+        fs::write(&b, "").unwrap();
+        fs::write(&c, "{\"n\":3}\n").unwrap();
+        let mut lines = String::new();
+        Files::new(vec![a.clone(), b.clone(), c.clone()], true, false)
+            .read_to_string(&mut lines)
+            .unwrap();
+        assert_eq!(lines, "{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n");
+        let mut bytes = Vec::new();
+        Files::new(vec![a.clone(), c.clone()], false, false)
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, b"{\"n\":1}\n{\"n\":2}{\"n\":3}\n");
+        let missing = Files::new(vec![dir.join("missing")], true, false)
+            .fill_buf()
+            .map(|_| ());
+        assert!(missing.unwrap_err().to_string().contains("missing"));
+        for skip in 0..=lines.len() {
+            let mut skipped = String::new();
+            let mut files = Files::new(vec![a.clone(), b.clone(), c.clone()], true, false);
+            files.skip = skip as u64;
+            files.read_to_string(&mut skipped).unwrap();
+            assert_eq!(skipped, lines[skip..], "skipping {} bytes", skip);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_indexed_files() {
+        let path = std::env::temp_dir().join(format!("ndjson-indexed-{}", std::process::id()));
+        let lines = [
+            r#"{"level":"info","n":1}"#,
+            r#"{"level":"error","n":2}"#,
+            r#"{"level":"info","n":3}"#,
+            r#"{"level":"info","n":4}"#,
+            "text",
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        let mut files = Files::new(vec![path.clone()], true, false);
+        files.conditions = vec![Predicate::parse("level>=error").unwrap()];
+        let mut unindexed = String::new();
+        files.read_to_string(&mut unindexed).unwrap();
+        assert_eq!(unindexed, lines.join("\n") + "\n");
+        let opt = crate::Opt::parse_from([
+            "ndjson".as_ref(),
+            "index".as_ref(),
+            path.as_os_str(),
+            "--block-lines=2".as_ref(),
+        ]);
+        if let Some(crate::Command::Index(opt)) = opt.command {
+            crate::index::run(&opt).unwrap();
+        }
+        let mut files = Files::new(vec![path.clone()], true, false);
+        files.conditions = vec![Predicate::parse("level>=error").unwrap()];
+        let mut indexed = String::new();
+        files.read_to_string(&mut indexed).unwrap();
+        assert_eq!(indexed, [lines[0], lines[1], lines[4]].join("\n") + "\n");
+        let mut reader = line_reader(
+            vec![path.clone()],
+            false,
+            Some(Slice::parse_lines("4..5").unwrap()),
+            Vec::new(),
+            Decoding {
+                format: InputFormat::Json,
+                framing: None,
+                proto: None,
+                depth: 64,
+            },
+        );
+        assert_eq!(
+            reader().unwrap().unwrap(),
+            format!("{}\n", lines[3]).as_bytes()
+        );
+        assert!(reader().unwrap().is_none());
+        fs::remove_file(crate::index::index_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_follow() {
+        let dir = std::env::temp_dir().join(format!("ndjson-follow-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (path, rotated) = (dir.join("app.log"), dir.join("app.log.1"));
+        let append = |path: &Path, text: &str| {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap();
+            io::Write::write_all(&mut file, text.as_bytes()).unwrap();
+        };
+        append(&path, "1\n");
+        let mut files = Files::new(vec![path.clone()], true, true);
+        let mut read_line = || {
+            let mut line = String::new();
+            files.read_line(&mut line).unwrap();
+            line
+        };
+        assert_eq!(read_line(), "1\n");
+        append(&path, "2\n");
+        assert_eq!(read_line(), "2\n");
+        // Lines written before the rotation are read from the old file first.
+        append(&path, "3\n");
+        fs::rename(&path, &rotated).unwrap();
+        append(&path, "4\n");
+        files.wait().unwrap();
+        let mut read_line = || {
+            let mut line = String::new();
+            files.read_line(&mut line).unwrap();
+            line
+        };
+        assert_eq!(read_line(), "3\n");
+        assert_eq!(read_line(), "4\n");
+        // A file truncated below what was read is read from its start.
+        append(&path, "5\n");
+        assert_eq!(read_line(), "5\n");
+        fs::write(&path, "6\n").unwrap();
+        assert_eq!(read_line(), "6\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trim_newline() {
+        assert_eq!(trim_newline(b"line\n"), b"line");
+        assert_eq!(trim_newline(b"line\r\n"), b"line");
+        assert_eq!(trim_newline(b"line"), b"line");
+        assert_eq!(trim_newline(b"\xff\n"), b"\xff");
+    }
+}
@@ -0,0 +1,121 @@
+use clap::{ArgEnum, Args};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+
+/// Merges records from two files sharing a key
+#[derive(Args, Debug)]
+pub struct JoinOpt {
+    /// Records to iterate in order
+    left: PathBuf,
+    /// Records to look up by key
+    right: PathBuf,
+    /// Key present in records of both files
+    #[clap(long, value_name = "KEY")]
+    on: String,
+    /// Whether left records without a match are kept
+    #[clap(long = "type", arg_enum, default_value = "inner")]
+    kind: JoinKind,
+}
+
+#[derive(ArgEnum, Copy, Clone, PartialEq, Debug)]
+pub enum JoinKind {
+    Left,
+    Inner,
+}
+
+pub fn run(opt: &JoinOpt) -> io::Result<()> {
+    let mut joiner = Joiner::new(&opt.on, opt.kind);
+    for line in BufReader::new(File::open(&opt.right)?).lines() {
+        joiner.add_right(&line?);
+    }
+    let left = BufReader::new(File::open(&opt.left)?);
+    let lines = left.lines().flat_map(|line| match line {
+        Ok(line) => joiner.join(&line).into_iter().map(Ok).collect(),
+        Err(err) => vec![Err(err)],
+    });
+    crate::print_lines(lines)
+}
+
+struct Joiner<'a> {
+    key: &'a str,
+    kind: JoinKind,
+    right: HashMap<String, Vec<Map<String, Value>>>,
+}
+
+impl<'a> Joiner<'a> {
+    fn new(key: &'a str, kind: JoinKind) -> Self {
+        Joiner {
+            key,
+            kind,
+            right: HashMap::new(),
+        }
+    }
+
+    fn add_right(&mut self, line: &str) {
+        if let Ok(Value::Object(object)) = serde_json::from_str(line) {
+            if let Some(key) = object.get(self.key).map(Value::to_string) {
+                self.right.entry(key).or_default().push(object);
+            }
+        }
+    }
+
+    /// Returns one merged line per matching right record, keys of the left record take precedence.
+    fn join(&self, line: &str) -> Vec<String> {
+        let left = match serde_json::from_str(line) {
+            Ok(Value::Object(object)) => object,
+            _ => return Vec::new(),
+        };
+        let matches = left
+            .get(self.key)
+            .and_then(|key| self.right.get(&key.to_string()));
+        match matches {
+            Some(matches) => matches
+                .iter()
+                .map(|right| {
+                    let mut merged = left.clone();
+                    for (key, value) in right {
+                        merged.entry(key).or_insert_with(|| value.clone());
+                    }
+                    Value::Object(merged).to_string()
+                })
+                .collect(),
+            None if self.kind == JoinKind::Left => vec![line.to_string()],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join(kind: JoinKind, left: &str) -> Vec<String> {
+        let mut joiner = Joiner::new("id", kind);
+        joiner.add_right(r#"{"id":1,"status":200,"path":"/"}"#);
+        joiner.add_right(r#"{"id":1,"status":500}"#);
+        joiner.add_right("text");
+        joiner.join(left)
+    }
+
+    #[test]
+    fn test_inner() {
+        assert_eq!(
+            join(JoinKind::Inner, r#"{"id":1,"path":"/a"}"#),
+            [
+                r#"{"id":1,"path":"/a","status":200}"#,
+                r#"{"id":1,"path":"/a","status":500}"#
+            ]
+        );
+        assert!(join(JoinKind::Inner, r#"{"id":2}"#).is_empty());
+        assert!(join(JoinKind::Inner, r#"{"id":"1"}"#).is_empty());
+    }
+
+    #[test]
+    fn test_left() {
+        assert_eq!(join(JoinKind::Left, r#"{"id":2}"#), [r#"{"id":2}"#]);
+        assert!(join(JoinKind::Left, "text").is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+use clap::Args;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Follows the logs of Kubernetes pods, tagged with the pod and container name
+#[derive(Args, Debug)]
+pub struct K8sOpt {
+    /// Pod or resource like `deployment/web`, instead of --selector
+    #[clap(required_unless_present = "selector")]
+    target: Option<String>,
+    /// Label selector of the pods, like `app=web`
+    #[clap(short = 'l', long, value_name = "SELECTOR")]
+    selector: Option<String>,
+    /// Kubernetes context to use instead of the current one
+    #[clap(long, value_name = "CONTEXT")]
+    context: Option<String>,
+    /// Kubeconfig file to use instead of $KUBECONFIG or ~/.kube/config
+    #[clap(long, value_name = "FILE")]
+    kubeconfig: Option<PathBuf>,
+    /// Namespace of the pods instead of the one of the context
+    #[clap(short = 'n', long, value_name = "NAMESPACE")]
+    namespace: Option<String>,
+    /// Container to show instead of the default one
+    #[clap(
+        short = 'c',
+        long,
+        value_name = "CONTAINER",
+        conflicts_with = "all-containers"
+    )]
+    container: Option<String>,
+    /// Shows the logs of all containers including init containers
+    #[clap(long)]
+    all_containers: bool,
+    /// Shows the logs of the previous instance of crashed containers instead of following
+    #[clap(short = 'p', long)]
+    previous: bool,
+    /// Number of lines to show from the end of the logs of each container
+    #[clap(long, value_name = "LINES", default_value = "100")]
+    tail: String,
+}
+
+pub fn run(opt: &K8sOpt) -> io::Result<()> {
+    let mut command = Command::new("kubectl");
+    command.args(["logs", "--prefix", "--tail", &opt.tail]);
+    if !opt.previous {
+        command.arg("--follow");
+    }
+    if let Some(kubeconfig) = &opt.kubeconfig {
+        command.arg("--kubeconfig").arg(kubeconfig);
+    }
+    if let Some(context) = &opt.context {
+        command.args(["--context", context]);
+    }
+    if let Some(namespace) = &opt.namespace {
+        command.args(["--namespace", namespace]);
+    }
+    if let Some(selector) = &opt.selector {
+        command.args(["--selector", selector]);
+    }
+    if let Some(container) = &opt.container {
+        command.args(["--container", container]);
+    }
+    if opt.all_containers {
+        command.arg("--all-containers");
+    }
+    if opt.previous {
+        command.arg("--previous");
+    }
+    command.args(&opt.target).stdout(Stdio::piped());
+    let mut child = command.spawn()?;
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let lines = stdout
+        .lines()
+        .map(|line| line.map(|line| split_prefix(&line)));
+    crate::print_tagged_lines("pod", lines)?;
+    child.wait()?;
+    Ok(())
+}
+
+/// Splits a line like `[pod/web-7d9f/app] message` into `web-7d9f/app` and the message.
+fn split_prefix(line: &str) -> (String, String) {
+    let prefixed = line
+        .strip_prefix('[')
+        .and_then(|line| line.split_once("] "));
+    match prefixed {
+        Some((source, message)) => (
+            source.strip_prefix("pod/").unwrap_or(source).to_string(),
+            message.to_string(),
+        ),
+        None => (String::new(), line.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_prefix() {
+        assert_eq!(
+            split_prefix(r#"[pod/web-7d9f/migrate] {"msg":"done"}"#),
+            (
+                "web-7d9f/migrate".to_string(),
+                r#"{"msg":"done"}"#.to_string()
+            )
+        );
+        assert_eq!(
+            split_prefix("[unprefixed"),
+            (String::new(), "[unprefixed".to_string())
+        );
+    }
+}
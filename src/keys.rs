@@ -0,0 +1,142 @@
+use clap::Args;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+
+/// Reports every key path with the types of its values, flagging keys which change type
+#[derive(Args, Debug)]
+pub struct KeysOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+}
+
+pub fn run(opt: &KeysOpt) -> io::Result<()> {
+    let mut keys = KeyTypes::default();
+    if opt.files.is_empty() {
+        for line in io::stdin().lock().lines() {
+            keys.add_line(&line?);
+        }
+    }
+    for path in &opt.files {
+        for line in BufReader::new(File::open(path)?).lines() {
+            keys.add_line(&line?);
+        }
+    }
+    for key in keys.mixed() {
+        eprintln!("ndjson: key {} changes type between records", key);
+    }
+    crate::print_lines(keys.report().into_iter().map(Ok))
+}
+
+/// Counts the value types of each key path, with `[]` for elements of arrays.
+#[derive(Default)]
+struct KeyTypes {
+    types: BTreeMap<String, BTreeMap<&'static str, u64>>,
+}
+
+impl KeyTypes {
+    fn add_line(&mut self, line: &str) {
+        if let Ok(Value::Object(object)) = serde_json::from_str(line) {
+            self.add_object("", &object);
+        }
+    }
+
+    fn add_object(&mut self, prefix: &str, object: &Map<String, Value>) {
+        for (key, value) in object {
+            self.add_value(format!("{}{}", prefix, key), value);
+        }
+    }
+
+    fn add_value(&mut self, path: String, value: &Value) {
+        match value {
+            Value::Object(object) => self.add_object(&format!("{}.", path), object),
+            Value::Array(array) => {
+                for value in array {
+                    self.add_value(format!("{}[]", path), value);
+                }
+            }
+            _ => {}
+        }
+        *self
+            .types
+            .entry(path)
+            .or_default()
+            .entry(type_name(value))
+            .or_default() += 1;
+    }
+
+    /// Returns the keys which have values of more than one type, ignoring null.
+    fn mixed(&self) -> Vec<&str> {
+        self.types
+            .iter()
+            .filter(|(_, types)| types.keys().filter(|name| **name != "null").count() > 1)
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    fn report(&self) -> Vec<String> {
+        let mixed = self.mixed();
+        self.types
+            .iter()
+            .map(|(key, types)| {
+                json!({
+                    "key": key,
+                    "count": types.values().sum::<u64>(),
+                    "types": types,
+                    "mixed": mixed.contains(&key.as_str()),
+                })
+                .to_string()
+            })
+            .collect()
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(lines: &[&str]) -> KeyTypes {
+        let mut keys = KeyTypes::default();
+        for line in lines {
+            keys.add_line(line);
+        }
+        keys
+    }
+
+    #[test]
+    fn test_report() {
+        let keys = keys(&[
+            r#"{"id":1,"user":{"name":"a"},"tags":["x"]}"#,
+            r#"{"id":"2","user":{"name":null}}"#,
+            "text",
+        ]);
+        assert_eq!(
+            keys.report(),
+            [
+                r#"{"key":"id","count":2,"types":{"number":1,"string":1},"mixed":true}"#,
+                r#"{"key":"tags","count":1,"types":{"array":1},"mixed":false}"#,
+                r#"{"key":"tags[]","count":1,"types":{"string":1},"mixed":false}"#,
+                r#"{"key":"user","count":2,"types":{"object":2},"mixed":false}"#,
+                r#"{"key":"user.name","count":2,"types":{"null":1,"string":1},"mixed":false}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mixed() {
+        assert_eq!(keys(&[r#"{"a":1,"b":[1,"1"]}"#]).mixed(), ["b[]"]);
+    }
+}
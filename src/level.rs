@@ -0,0 +1,226 @@
+use crate::predicate::Predicate;
+use serde_json::{Map, Value};
+
+const FIELDS: [&str; 4] = ["level", "severity", "lvl", "loglevel"];
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    /// Parses level names and the numeric levels of bunyan and pino.
+    pub fn parse(value: &Value) -> Option<Level> {
+        match value {
+            Value::String(name) => Level::from_name(name),
+            Value::Number(number) => match number.as_u64()? {
+                0..=10 => Some(Level::Trace),
+                11..=20 => Some(Level::Debug),
+                21..=30 => Some(Level::Info),
+                31..=40 => Some(Level::Warn),
+                41..=50 => Some(Level::Error),
+                _ => Some(Level::Fatal),
+            },
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Level> {
+        match name.to_lowercase().as_str() {
+            "trace" => Some(Level::Trace),
+            "debug" | "dbg" | "verbose" => Some(Level::Debug),
+            "info" | "information" | "notice" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" | "err" => Some(Level::Error),
+            "fatal" | "critical" | "crit" | "panic" | "alert" | "emergency" => Some(Level::Fatal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Fatal => "fatal",
+        }
+    }
+}
+
+/// Returns the level of the first known level field.
+pub fn detect(object: &Map<String, Value>) -> Option<Level> {
+    FIELDS
+        .iter()
+        .find_map(|field| object.get(*field))
+        .and_then(Level::parse)
+}
+
+/// Detects levels in configurable fields, with custom values like `E` mapped to levels.
+#[derive(Clone, Debug)]
+pub struct LevelFields {
+    fields: Vec<String>,
+    mappings: Vec<(String, Level)>,
+}
+
+impl Default for LevelFields {
+    fn default() -> Self {
+        LevelFields::new(Vec::new(), Vec::new())
+    }
+}
+
+impl LevelFields {
+    /// Creates a detector for the fields, the common level fields if none are given.
+    pub fn new(fields: Vec<String>, mappings: Vec<(String, Level)>) -> Self {
+        let fields = match fields.is_empty() {
+            true => FIELDS.iter().map(|field| field.to_string()).collect(),
+            false => fields,
+        };
+        LevelFields { fields, mappings }
+    }
+
+    /// Returns the level of a value if the key is a level field.
+    pub fn level(&self, key: &str, value: &Value) -> Option<Level> {
+        if !self.fields.iter().any(|field| field == key) {
+            return None;
+        }
+        let text = match value {
+            Value::String(string) => string.clone(),
+            value => value.to_string(),
+        };
+        let mapped = self
+            .mappings
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&text));
+        match mapped {
+            Some((_, level)) => Some(*level),
+            None => Level::parse(value),
+        }
+    }
+}
+
+/// Rules changing the level of the records matching a condition, like treating errors with
+/// `msg~"context canceled"` as debug, before they are filtered and colored.
+#[derive(Clone, Debug)]
+pub struct Reclassifier {
+    fields: Vec<String>,
+    rules: Vec<(Predicate, Level)>,
+}
+
+impl Reclassifier {
+    /// Creates rules rewriting the first of the level fields which a record has, the common level
+    /// fields if none are given.
+    pub fn new(fields: Vec<String>, rules: Vec<(Predicate, Level)>) -> Self {
+        let fields = match fields.is_empty() {
+            true => FIELDS.iter().map(|field| field.to_string()).collect(),
+            false => fields,
+        };
+        Reclassifier { fields, rules }
+    }
+
+    /// Sets the level of the first matching rule, adding a `level` field to records without one,
+    /// and returns whether the record changed.
+    pub fn reclassify(&self, object: &mut Map<String, Value>) -> bool {
+        let level = match self.rules.iter().find(|(rule, _)| rule.matches(object)) {
+            Some((_, level)) => level.name(),
+            None => return false,
+        };
+        let field = self
+            .fields
+            .iter()
+            .find(|field| object.contains_key(*field))
+            .map_or("level", String::as_str);
+        if object.get(field).and_then(Value::as_str) == Some(level) {
+            return false;
+        }
+        object.insert(field.to_string(), Value::String(level.to_string()));
+        true
+    }
+}
+
+/// Parses the name of a level.
+pub fn parse_name(name: &str) -> Result<Level, String> {
+    Level::from_name(name).ok_or_else(|| format!("unknown level `{}`", name))
+}
+
+/// Parses a mapping of a custom value to a level, like `E=error` or `5=fatal`.
+pub fn parse_mapping(mapping: &str) -> Result<(String, Level), String> {
+    let (value, level) = mapping
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected VALUE=LEVEL instead of `{}`", mapping))?;
+    Ok((value.to_string(), parse_name(level)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect() {
+        for (record, level) in [
+            (json!({"level": "ERROR"}), Some(Level::Error)),
+            (json!({"severity": "warning"}), Some(Level::Warn)),
+            (json!({"level": 30}), Some(Level::Info)),
+            (json!({"lvl": "crit"}), Some(Level::Fatal)),
+            (json!({"level": "unknown"}), None),
+            (json!({"msg": "error"}), None),
+        ] {
+            assert_eq!(detect(record.as_object().unwrap()), level);
+        }
+    }
+
+    #[test]
+    fn test_level_fields() {
+        let fields = LevelFields::new(
+            vec!["sev".to_string()],
+            vec![parse_mapping("E=error").unwrap()],
+        );
+        assert_eq!(fields.level("sev", &json!("e")), Some(Level::Error));
+        assert_eq!(fields.level("sev", &json!("warn")), Some(Level::Warn));
+        assert_eq!(fields.level("level", &json!("warn")), None);
+        let fields = LevelFields::default();
+        assert_eq!(fields.level("lvl", &json!(50)), Some(Level::Error));
+        assert!(parse_mapping("E=bad").is_err());
+        assert!(parse_mapping("E").is_err());
+    }
+
+    #[test]
+    fn test_reclassify() {
+        let reclassifier = Reclassifier::new(
+            Vec::new(),
+            vec![
+                (
+                    Predicate::parse(r#"msg~"context canceled""#).unwrap(),
+                    Level::Debug,
+                ),
+                (Predicate::parse("status>=500").unwrap(), Level::Error),
+            ],
+        );
+        let reclassify = |record: Value| {
+            let mut object = record.as_object().unwrap().clone();
+            reclassifier
+                .reclassify(&mut object)
+                .then(|| Value::Object(object))
+        };
+        assert_eq!(
+            reclassify(json!({"severity": "error", "msg": "rpc: context canceled"})),
+            Some(json!({"severity": "debug", "msg": "rpc: context canceled"}))
+        );
+        assert_eq!(
+            reclassify(json!({"status": 503})),
+            Some(json!({"status": 503, "level": "error"}))
+        );
+        assert_eq!(reclassify(json!({"level": "error", "status": 500})), None);
+        assert_eq!(
+            reclassify(json!({"level": "error", "msg": "timeout"})),
+            None
+        );
+    }
+}
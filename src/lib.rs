@@ -0,0 +1,29 @@
+//! Formatting and colorizing of newline delimited JSON, the library behind the `ndjson` binary.
+//!
+//! [`format::Formatter`] writes lines with the colors of a [`termcolor::WriteColor`], or collects
+//! them as styled spans for other user interfaces:
+//!
+//! ```
+//! use ndjson::format::{Formatter, Spans};
+//!
+//! let mut formatter = Formatter::new(Spans::default());
+//! let spans = formatter.format_line(r#"{"level":"warn","msg":"disk full"}"#).unwrap();
+//! let text: String = spans.iter().map(|span| span.text.as_str()).collect();
+//! assert_eq!(text, "level: warn msg: disk full\n");
+//! ```
+
+pub mod duration;
+pub mod escalate;
+pub mod fields;
+pub mod format;
+pub mod level;
+pub mod logfmt;
+pub mod payload;
+pub mod predicate;
+pub mod scale;
+pub mod secrets;
+pub mod size;
+pub mod spotlight;
+pub mod theme;
+pub mod timestamp;
+pub mod trend;
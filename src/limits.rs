@@ -0,0 +1,185 @@
+use crate::size;
+use serde_json::{Map, Value};
+
+pub const DEFAULT: &str = "depth=64,string=1M,keys=10000";
+
+/// Characters of a rejected line kept in its summary.
+const PREVIEW: usize = 80;
+
+/// Bounds on the shape of JSON records, checked by scanning their bytes before they are parsed,
+/// so deeply nested or huge records can't exhaust the stack or flood the terminal.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Limits {
+    /// Nesting of objects and arrays.
+    depth: usize,
+    /// Bytes of a single string, key or value, as escaped in the line.
+    string: usize,
+    /// Keys of all objects of the record together.
+    keys: usize,
+}
+
+impl Limits {
+    /// Parses limits like `depth=32,string=64K,keys=1000`, leaving unnamed ones at their default,
+    /// or `off` to check nothing.
+    pub fn parse(limits: &str) -> Result<Self, String> {
+        let mut parsed = match limits {
+            "off" => return Ok(Limits::off()),
+            _ => Limits::default(),
+        };
+        for limit in limits.split(',').filter(|limit| !limit.is_empty()) {
+            let invalid = || {
+                format!(
+                    "expected depth=N, string=SIZE or keys=N instead of `{}`",
+                    limit
+                )
+            };
+            let (name, value) = limit.split_once('=').ok_or_else(invalid)?;
+            match name.trim() {
+                "depth" => parsed.depth = value.trim().parse().map_err(|_| invalid())?,
+                "string" => parsed.string = size::parse(value)? as usize,
+                "keys" => parsed.keys = value.trim().parse().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn off() -> Self {
+        Limits {
+            depth: usize::MAX,
+            string: usize::MAX,
+            keys: usize::MAX,
+        }
+    }
+
+    /// Returns the nesting allowed, which also bounds decoding binary input.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn is_off(&self) -> bool {
+        *self == Limits::off()
+    }
+
+    /// Returns which limit a line starting like JSON exceeds, if any. Other lines aren't checked.
+    pub fn check(&self, line: &[u8]) -> Option<String> {
+        if self.is_off() || !matches!(line.trim_ascii_start().first(), Some(b'{' | b'[')) {
+            return None;
+        }
+        let (mut depth, mut keys) = (0usize, 0usize);
+        // Start of the string being scanned, and whether the previous byte escapes the next one.
+        let mut string: Option<usize> = None;
+        let mut escaped = false;
+        for (index, byte) in line.iter().enumerate() {
+            if let Some(start) = string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => string = None,
+                    _ => {}
+                }
+                if index - start > self.string {
+                    return Some(format!("string over {}", size::format(self.string as u64)));
+                }
+                continue;
+            }
+            match byte {
+                b'"' => string = Some(index + 1),
+                b'{' | b'[' => {
+                    depth += 1;
+                    if depth > self.depth {
+                        return Some(format!("nesting deeper than {}", self.depth));
+                    }
+                }
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                b':' => {
+                    keys += 1;
+                    if keys > self.keys {
+                        return Some(format!("more than {} keys", self.keys));
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            depth: 64,
+            string: 1 << 20,
+            keys: 10_000,
+        }
+    }
+}
+
+/// Returns the record written in place of a line exceeding a limit, with its size and beginning.
+pub fn summary(line: &[u8], exceeded: String) -> Value {
+    let text = String::from_utf8_lossy(line);
+    let mut summary = Map::new();
+    summary.insert("limit_exceeded".to_string(), Value::String(exceeded));
+    summary.insert("bytes".to_string(), line.len().into());
+    summary.insert(
+        "preview".to_string(),
+        Value::String(text.chars().take(PREVIEW).collect()),
+    );
+    Value::Object(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Limits::parse(DEFAULT), Ok(Limits::default()));
+        let limits = Limits::parse("depth=2,string=1K").unwrap();
+        assert_eq!(
+            (limits.depth, limits.string, limits.keys),
+            (2, 1024, 10_000)
+        );
+        assert!(Limits::parse("off").unwrap().is_off());
+        assert!(Limits::parse("depth").is_err());
+        assert!(Limits::parse("width=3").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let limits = Limits::parse("depth=2,string=5,keys=3").unwrap();
+        assert_eq!(limits.check(br#"{"a":{"b":"12345"}}"#), None);
+        assert_eq!(
+            limits.check(br#"{"a":[[1]]}"#),
+            Some("nesting deeper than 2".to_string())
+        );
+        assert_eq!(
+            limits.check(br#"{"a":"123456"}"#),
+            Some("string over 5 B".to_string())
+        );
+        assert_eq!(limits.check(br#"{"a":"\"{{{"}"#), None);
+        assert_eq!(
+            limits.check(br#"{"a":1,"b":2,"c":{"d":4}}"#),
+            Some("more than 3 keys".to_string())
+        );
+        assert_eq!(
+            limits.check(b"[[[ not json"),
+            Some("nesting deeper than 2".to_string())
+        );
+        assert_eq!(limits.check(b"text {{{ with braces"), None);
+        assert_eq!(Limits::off().check(br#"{"a":[[[[1]]]]}"#), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = summary(br#"{"a":"xyz"}"#, "more than 0 keys".to_string());
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "limit_exceeded": "more than 0 keys",
+                "bytes": 11,
+                "preview": r#"{"a":"xyz"}"#,
+            })
+        );
+    }
+}
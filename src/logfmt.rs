@@ -0,0 +1,153 @@
+use serde_json::{Map, Value};
+
+/// Parses a logfmt line like `level=info msg="hello world" took=3ms` into an object, with numbers,
+/// booleans and `null` as JSON values and quoted values always as strings. Lines with a token
+/// which isn't a `key=value` pair aren't logfmt.
+pub fn parse(line: &[u8]) -> Option<Map<String, Value>> {
+    let mut rest = std::str::from_utf8(line).ok()?.trim();
+    let mut object = Map::new();
+    while !rest.is_empty() {
+        let equals = rest.find(|c: char| c == '=' || c == '"' || c.is_whitespace())?;
+        let key = &rest[..equals];
+        if key.is_empty() || !rest[equals..].starts_with('=') {
+            return None;
+        }
+        rest = &rest[equals + 1..];
+        let value = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let mut escaped = false;
+                let end = quoted.char_indices().find_map(|(index, c)| {
+                    let end = !escaped && c == '"';
+                    escaped = !escaped && c == '\\';
+                    end.then_some(index)
+                })?;
+                let value = serde_json::from_str(&rest[..end + 2]).ok()?;
+                rest = &quoted[end + 1..];
+                if rest.starts_with(|c: char| !c.is_whitespace()) {
+                    return None;
+                }
+                Value::String(value)
+            }
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let (value, after) = rest.split_at(end);
+                if value.contains('"') {
+                    return None;
+                }
+                rest = after;
+                match serde_json::from_str(value) {
+                    Ok(value @ (Value::Number(_) | Value::Bool(_) | Value::Null)) => value,
+                    _ => Value::String(value.to_string()),
+                }
+            }
+        };
+        object.insert(key.to_string(), value);
+        rest = rest.trim_start();
+    }
+    (!object.is_empty()).then_some(object)
+}
+
+/// Writes an object as a logfmt line, with the fields of nested objects under dotted keys and
+/// arrays as JSON. Values which would be read back as something else are quoted, like `"200"` or
+/// `"a b"`.
+pub fn format(object: &Map<String, Value>) -> String {
+    let mut pairs = Vec::new();
+    flatten(object, "", &mut pairs);
+    pairs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn flatten(object: &Map<String, Value>, prefix: &str, pairs: &mut Vec<(String, String)>) {
+    for (key, value) in object {
+        let key = key.replace(|c: char| c == '=' || c == '"' || c.is_whitespace(), "_");
+        let key = match (prefix, key.as_str()) {
+            ("", "") => "_".to_string(),
+            ("", _) => key,
+            (prefix, key) => format!("{}.{}", prefix, key),
+        };
+        let text = match value {
+            Value::Object(object) if !object.is_empty() => {
+                flatten(object, &key, pairs);
+                continue;
+            }
+            Value::String(string) => quote(string),
+            Value::Array(_) => quote(&value.to_string()),
+            value => value.to_string(),
+        };
+        pairs.push((key, text));
+    }
+}
+
+/// Quotes text unless it can be written as is and is read back as the same string.
+fn quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && !text
+            .chars()
+            .any(|c| c == '=' || c == '"' || c == '\\' || c.is_whitespace() || c.is_control())
+        && !matches!(
+            serde_json::from_str(text),
+            Ok(Value::Number(_) | Value::Bool(_) | Value::Null)
+        );
+    match plain {
+        true => text.to_string(),
+        false => Value::String(text.to_string()).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parsed(line: &str) -> Option<Value> {
+        parse(line.as_bytes()).map(Value::Object)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parsed(r#"level=info msg="hello \"world\"" took=3ms status=200 ok=true err= "#),
+            Some(json!({
+                "level": "info",
+                "msg": "hello \"world\"",
+                "took": "3ms",
+                "status": 200,
+                "ok": true,
+                "err": "",
+            }))
+        );
+        assert_eq!(
+            parsed("at=a.go:12 path=/x?a=b"),
+            Some(json!({"at": "a.go:12", "path": "/x?a=b"}))
+        );
+        assert_eq!(parsed("plain text"), None);
+        assert_eq!(parsed("error: failed x=1"), None);
+        assert_eq!(parsed("msg=\"unclosed"), None);
+        assert_eq!(parsed("msg=\"a\"b"), None);
+        assert_eq!(parsed("=1"), None);
+        assert_eq!(parsed(""), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let record = json!({
+            "level": "info",
+            "msg": "hello \"world\"",
+            "status": 200,
+            "code": "200",
+            "empty": "",
+            "http": {"path": "/a?b=c", "tags": ["x"]},
+            "a key": null,
+            "name": "é",
+        });
+        let line = format(record.as_object().unwrap());
+        assert_eq!(
+            line,
+            r#"level=info msg="hello \"world\"" status=200 code="200" empty="" http.path="/a?b=c" http.tags="[\"x\"]" a_key=null name=é"#
+        );
+        assert_eq!(parsed(&line).unwrap()["msg"], record["msg"]);
+    }
+}
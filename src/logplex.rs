@@ -0,0 +1,138 @@
+use crate::binary::read_bytes;
+use crate::syslog;
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::Sender;
+
+/// Receives the HTTP requests of a Heroku log drain on a connection, sending the records with
+/// their dyno like `web.1` as the tag.
+pub fn serve(stream: TcpStream, sender: &Sender<(String, String)>) -> io::Result<()> {
+    let reply = stream.try_clone()?;
+    handle(BufReader::new(stream), reply, sender)
+}
+
+fn handle<R: BufRead, W: Write>(
+    mut reader: R,
+    mut reply: W,
+    sender: &Sender<(String, String)>,
+) -> io::Result<()> {
+    loop {
+        let mut request = String::new();
+        if reader.read_line(&mut request)? == 0 {
+            return Ok(());
+        }
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 {
+                return Ok(());
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidData, "invalid Content-Length")
+                    })?;
+                }
+            }
+        }
+        let body = read_bytes(&mut reader, length)?;
+        if !request.starts_with("POST ") {
+            reply.write_all(b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\n\r\n")?;
+            continue;
+        }
+        for message in messages(&body) {
+            let (tag, line) = match syslog::parse(message) {
+                Some(mut record) => {
+                    let tag = match record.get("pid").or_else(|| record.get("app")) {
+                        Some(Value::String(tag)) => tag.clone(),
+                        _ => String::new(),
+                    };
+                    record.remove("pid");
+                    (tag, Value::Object(record).to_string())
+                }
+                None => (String::new(), String::from_utf8_lossy(message).into_owned()),
+            };
+            if sender.send((tag, line)).is_err() {
+                return Ok(());
+            }
+        }
+        reply.write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")?;
+    }
+}
+
+/// Splits a body in the octet counting framing of RFC 6587, messages preceded by their length
+/// like `5 hello`, stopping at the first malformed one.
+fn messages(body: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    let mut rest = body.trim_ascii_start();
+    while let Some(space) = rest.iter().position(|byte| *byte == b' ') {
+        let length = match std::str::from_utf8(&rest[..space]).map(str::parse::<usize>) {
+            Ok(Ok(length)) if rest.len() > space + length => length,
+            _ => break,
+        };
+        let message = &rest[space + 1..space + 1 + length];
+        messages.push(message.strip_suffix(b"\n").unwrap_or(message));
+        rest = rest[space + 1 + length..].trim_ascii_start();
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(
+            messages(b"5 hello6 world\n3 ab"),
+            [&b"hello"[..], &b"world"[..]]
+        );
+        assert!(messages(b"x hello").is_empty());
+    }
+
+    #[test]
+    fn test_handle() {
+        let body: String = [
+            "<40>1 2012-11-30T06:45:29+00:00 host app web.3 - State changed from starting to up\n",
+            r#"<190>1 2012-11-30T06:45:30+00:00 host app web.3 - {"status":200}"#,
+        ]
+        .iter()
+        .map(|message| format!("{} {}", message.len(), message))
+        .collect();
+        let request = format!(
+            "POST /logs HTTP/1.1\r\nContent-Type: application/logplex-1\r\ncontent-length: {}\r\n\r\n{}\
+             GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\n",
+            body.len(),
+            body
+        );
+        let (sender, receiver) = mpsc::channel();
+        let mut reply = Vec::new();
+        handle(request.as_bytes(), &mut reply, &sender).unwrap();
+        drop(sender);
+        assert_eq!(
+            String::from_utf8(reply).unwrap(),
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n\
+             HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\n\r\n"
+        );
+        let lines: Vec<_> = receiver.into_iter().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].0, "web.3");
+        assert_eq!(
+            serde_json::from_str::<Value>(&lines[1].1).unwrap(),
+            serde_json::json!({
+                "severity": "info",
+                "facility": "local7",
+                "time": "2012-11-30T06:45:30+00:00",
+                "host": "host",
+                "app": "app",
+                "status": 200,
+            })
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use clap::Args;
+use std::io::{self, BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// Runs a LogQL query against Loki with logcli, optionally tailing new lines
+#[derive(Args, Debug)]
+pub struct LokiOpt {
+    /// Address of the Loki server instead of $LOKI_ADDR
+    #[clap(long, value_name = "URL")]
+    url: Option<String>,
+    /// LogQL query like `{app="api"}`
+    #[clap(long, value_name = "LOGQL")]
+    query: String,
+    /// How far back to query, like `1h`
+    #[clap(long, value_name = "DURATION", default_value = "1h")]
+    since: String,
+    /// Maximum number of lines returned by the query
+    #[clap(long, value_name = "LINES", default_value = "1000")]
+    limit: u64,
+    /// Keeps tailing new lines over a websocket
+    #[clap(short = 'f', long)]
+    follow: bool,
+}
+
+pub fn run(opt: &LokiOpt) -> io::Result<()> {
+    let mut command = Command::new("logcli");
+    command.args(["query", "--quiet", "--output", "raw", "--forward"]);
+    command.args(["--since", &opt.since, "--limit", &opt.limit.to_string()]);
+    if let Some(url) = &opt.url {
+        command.args(["--addr", url]);
+    }
+    if opt.follow {
+        command.arg("--tail");
+    }
+    command.arg(&opt.query).stdout(Stdio::piped());
+    let mut child = command.spawn().map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => io::Error::new(err.kind(), "logcli not found in PATH"),
+        _ => err,
+    })?;
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    crate::print_lines(stdout.lines())?;
+    child.wait()?;
+    Ok(())
+}
@@ -1,7 +1,74 @@
-use clap::{IntoApp, Parser};
-use serde_json::Value;
-use std::io::{self, BufRead};
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use chrono::{SecondsFormat, Utc};
+use clap::{ArgEnum, IntoApp, Parser, Subcommand};
+use format::{Formatter, TokenKind};
+use input::Event;
+use ndjson::{
+    duration, escalate, fields, format, level, logfmt, predicate, scale, secrets, size, spotlight,
+    theme, timestamp, trend,
+};
+use pipeline::{Pipeline, Processed};
+use serde_json::{Map, Value};
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use termcolor::{Buffer, ColorChoice, StandardStream, WriteColor};
+
+mod alert;
+mod anonymize;
+mod avro;
+mod binary;
+mod cardinality;
+mod cbor;
+mod collapse;
+mod compose;
+mod config;
+mod dedup;
+mod doctor;
+mod fold;
+mod forward;
+mod geoip;
+mod grep;
+mod index;
+mod input;
+mod join;
+mod k8s;
+mod keys;
+mod limits;
+mod logplex;
+mod loki;
+mod merge;
+mod mqtt;
+mod msgpack;
+mod nats;
+mod nested;
+mod panes;
+mod peek;
+mod pick;
+mod pipeline;
+mod proto;
+mod query;
+mod rate;
+mod redis;
+mod relaxed;
+mod replay;
+mod route;
+mod sample;
+mod sandbox;
+mod session;
+mod slice;
+mod sse;
+mod stats;
+mod summary;
+mod suppress;
+mod syslog;
+mod template;
+mod update;
+mod useragent;
+mod volume;
+mod wrap;
+mod xml;
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -9,224 +76,1009 @@ use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
     about = "Formats and colorizes newline delimited JSON for better readability.\n\
     The input remains unchanged for non-JSON lines or when stdout isn't a terminal.",
     override_usage = "ndjson < file
+    ndjson app.log other.log
+    ndjson -f app.log
+    ndjson --where 'status>=500' --where 'path=~^/api/' app.log
+    ndjson --color always app.log | less -R
+    ndjson --format json --exclude 'http.headers.*' app.log > clean.ndjson
     tail -f file | ndjson
     docker logs --tail 100 -f container 2>&1 | ndjson
-    kubectl logs --tail 100 -f pod | ndjson"
+    kubectl logs --tail 100 -f pod | ndjson
+    ndjson join access.log app.log --on request_id
+    ndjson keys < file
+    ndjson peek file
+    ndjson compose -f docker-compose.yml web worker
+    ndjson k8s -l app=web --all-containers --previous
+    ndjson volume --by service --window 1h --monthly app.log
+    ndjson merge app.log db.log --offset db.log=+2.5s
+    ndjson unwrap response.json | ndjson wrap
+    ndjson pick --template '{ts} {level} {msg}' app.log | jq .
+    ndjson listen --logplex :8080
+    ndjson redis --url redis://cache:6379 --stream events --channel 'alerts.*'
+    ndjson nats --server nats://nats:4222 --subject 'logs.>'
+    ndjson mqtt --broker mqtt://broker:1883 --topic 'devices/+/telemetry'
+    ndjson sse https://api.example.com/events
+    ndjson self-update --check
+    ndjson config validate"
 )]
-struct Opt;
+struct Opt {
+    /// Files to read one after another instead of stdin
+    files: Vec<PathBuf>,
+    /// Keeps reading the last file as it grows, also after it was rotated or truncated
+    #[clap(short, long)]
+    follow: bool,
+    /// Annotates records with the country, city and ASN of an IP address field
+    #[clap(long, value_name = "FIELD[:MMDB]")]
+    geoip: Option<String>,
+    /// Derives browser, OS and device fields from a user-agent field
+    #[clap(long, value_name = "FIELD")]
+    parse_ua: Option<String>,
+    /// Keeps only lines whose text or a value of whose record matches a regex, like `grep`
+    #[clap(long, value_name = "REGEX")]
+    grep: Option<regex::Regex>,
+    /// Also keeps this many lines after each line matching --grep
+    #[clap(short = 'A', long, value_name = "N", requires = "grep")]
+    after_context: Option<usize>,
+    /// Also keeps this many lines before each line matching --grep
+    #[clap(short = 'B', long, value_name = "N", requires = "grep")]
+    before_context: Option<usize>,
+    /// Also keeps this many lines before and after each line matching --grep
+    #[clap(short = 'C', long, value_name = "N", requires = "grep")]
+    context: Option<usize>,
+    /// Keeps only JSON records matching all conditions like `status>=500`, `level=error`, `msg~timeout` or `path=~^/api/`
+    #[clap(
+        long = "where",
+        value_name = "CONDITION",
+        multiple_occurrences(true),
+        number_of_values = 1,
+        parse(try_from_str = predicate::Predicate::parse)
+    )]
+    conditions: Vec<predicate::Predicate>,
+    /// Paints the parts of values matching a regex, like `timeout|refused`, on a highlight background
+    #[clap(
+        long,
+        value_name = "REGEX",
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    highlight: Vec<regex::Regex>,
+    /// Drops JSON records which exactly repeat an earlier record
+    #[clap(long)]
+    dedup: bool,
+    /// Limits the records per distinct value of a field, like `user_id=10/min`, so one noisy value can't drown out the others
+    #[clap(long, value_name = "FIELD=N/WINDOW", parse(try_from_str = sample::SampleBy::parse))]
+    sample_by: Option<sample::SampleBy>,
+    /// Drops records repeating one seen within a duration like `10s`, ignoring the time and --volatile-fields, and reports the number of repeats once it passed
+    #[clap(long, value_name = "DURATION", parse(try_from_str = duration::parse))]
+    suppress_repeats: Option<Duration>,
+    /// Fields ignored by --suppress-repeats besides the time, like `pid,request_id`
+    #[clap(
+        long,
+        value_name = "FIELDS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true),
+        require_delimiter = true,
+        multiple_occurrences(true),
+        requires = "suppress-repeats"
+    )]
+    volatile_fields: Vec<String>,
+    /// Collapses runs of records repeating the one before them into it, with a `×N` counter updated in place on a terminal
+    #[clap(long)]
+    collapse: bool,
+    /// Fields ignored when comparing records for --collapse, like `ts,request_id`
+    #[clap(
+        long,
+        value_name = "FIELDS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true),
+        require_delimiter = true,
+        multiple_occurrences(true),
+        requires = "collapse"
+    )]
+    collapse_ignore: Vec<String>,
+    /// Warns when a value of a supposedly unique field like `request_id` appears in more than one record
+    #[clap(long, value_name = "FIELD")]
+    check_unique: Option<String>,
+    /// Zeroes the host bits of an IP address field
+    #[clap(long, value_name = "FIELD")]
+    anonymize_ip: Option<String>,
+    /// Highlights values resembling API keys, tokens and private keys, warning on stderr
+    #[clap(long)]
+    detect_secrets: bool,
+    /// Replaces values resembling API keys, tokens and private keys, warning on stderr
+    #[clap(long)]
+    redact_secrets: bool,
+    /// Replaces the values of fields with these keys at any depth by [REDACTED], like `password,authorization`, globs like `*_secret` or key paths like `db.url`
+    #[clap(long, value_name = "PATTERN,...", use_delimiter = true, require_delimiter = true, multiple_occurrences(true), parse(try_from_str = fields::Pattern::parse))]
+    redact: Vec<fields::Pattern>,
+    /// Highlights values of a numeric field which are outliers compared to the recent records
+    #[clap(long, value_name = "FIELD")]
+    spotlight: Option<String>,
+    /// Appends a sparkline of the last values of a numeric field to its value, like `queue_depth` or `queue_depth:50`
+    #[clap(
+        long,
+        value_name = "FIELD[:N]",
+        parse(try_from_str = trend::Trend::parse),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    trend: Vec<trend::Trend>,
+    /// Shows an alert line when a rolling condition like `count(level==error) > 10 per 1m` starts to hold
+    #[clap(
+        long,
+        value_name = "CONDITION",
+        parse(try_from_str = alert::Alert::parse),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    alert: Vec<alert::Alert>,
+    /// Highlights records whose field value repeats suspiciously often, like `same msg 5 times in 1m → highlight` or `→ banner`
+    #[clap(
+        long,
+        value_name = "RULE",
+        parse(try_from_str = escalate::Escalation::parse),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    escalate: Vec<escalate::Escalation>,
+    /// Also appends all records to a file, whose path may contain fields and strftime specifiers like `logs/{service}/%Y-%m-%d.ndjson`
+    #[clap(
+        long,
+        value_name = "PATH",
+        parse(try_from_str = route::Route::parse_all),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    output_file: Vec<route::Route>,
+    /// Also appends the records matching a condition to a file, like `level>=error → errors.ndjson` or `level>=error → errors/{service}.ndjson`
+    #[clap(
+        long,
+        value_name = "RULE",
+        parse(try_from_str = route::Route::parse),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    route: Vec<route::Route>,
+    /// Reports records, time range, levels, top errors and parse failures on stderr after the input ended or on Ctrl-C
+    #[clap(long)]
+    summary: bool,
+    /// Format of reports like the summary
+    #[clap(long, arg_enum, default_value = "text")]
+    output: OutputFormat,
+    /// Format of the records, json and logfmt write them as single lines of it narrowed like formatted records, even to a terminal
+    #[clap(long, arg_enum, default_value = "text")]
+    format: RecordFormat,
+    /// Sorts the keys of objects in records written by --format json
+    #[clap(long)]
+    sort_keys: bool,
+    /// Writes the sources, arguments, counts and first and last timestamps of the session to a JSON file
+    #[clap(long, value_name = "FILE")]
+    session_log: Option<PathBuf>,
+    /// Exits when no input arrived for the duration, like `60s` or `5m`
+    #[clap(long, value_name = "DURATION", parse(try_from_str = duration::parse))]
+    exit_idle: Option<Duration>,
+    /// Keeps a status line below the output on a terminal with the lines read, lines per second, unparsed lines and records per level
+    #[clap(long)]
+    stats: bool,
+    /// Writes each line written to a FIFO, created if missing, as a marker between the records, like `echo deployed > /tmp/ndjson.ctl`
+    #[clap(long, value_name = "FIFO")]
+    control: Option<PathBuf>,
+    /// Colors a numeric field on a gradient like `latency_ms:0=green,500=yellow,2000=red`
+    #[clap(
+        long,
+        value_name = "FIELD:VALUE=COLOR,...",
+        parse(try_from_str = scale::ColorScale::parse),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    scale: Vec<scale::ColorScale>,
+    /// Collapses the records from one matching a condition like `event=="request_start"` up to --unfold-on into a summary line
+    #[clap(
+        long,
+        value_name = "CONDITION",
+        parse(try_from_str = predicate::Predicate::parse),
+        requires = "unfold-on"
+    )]
+    fold_on: Option<predicate::Predicate>,
+    /// Ends a transaction started by --fold-on, like `event=="request_end"`
+    #[clap(
+        long,
+        value_name = "CONDITION",
+        parse(try_from_str = predicate::Predicate::parse),
+        requires = "fold-on"
+    )]
+    unfold_on: Option<predicate::Predicate>,
+    /// Format of the records on stdin, binary records are read back to back and `avro` reads an object container file
+    #[clap(long, arg_enum, default_value = "json")]
+    input: InputFormat,
+    /// Reads json, msgpack or cbor records prefixed by their length in bytes instead of separated by newlines
+    #[clap(long, arg_enum)]
+    framing: Option<Framing>,
+    /// Reads the elements of a JSON array as separate records, for lines which are an array or an array spanning the whole input
+    #[clap(long)]
+    explode: bool,
+    /// Parses string values holding JSON objects or arrays, like double-encoded payloads, into nested values
+    #[clap(long)]
+    parse_nested: bool,
+    /// Converts fields holding XML, like `body` or `request.payload`, into JSON with attributes under `@` keys
+    #[clap(
+        long,
+        value_name = "FIELDS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true)
+    )]
+    parse_xml: Vec<String>,
+    /// Parses lines of JSON5-ish JSON with single quotes, unquoted keys, trailing commas and comments, writing them as JSON
+    #[clap(long)]
+    relaxed: bool,
+    /// Treats logfmt lines like `level=info msg="started" took=3ms` as records, formatting and filtering them like JSON while writing them as read to a pipe
+    #[clap(long)]
+    logfmt: bool,
+    /// Parses RFC 5424 and RFC 3164 syslog lines into records with their priority, host and app, and the fields of JSON messages
+    #[clap(long)]
+    syslog: bool,
+    /// Replaces JSON records nested deeper, with longer strings or with more keys than these limits by a summary, or `off`
+    #[clap(long, value_name = "LIMITS", default_value = limits::DEFAULT, parse(try_from_str = limits::Limits::parse))]
+    limits: limits::Limits,
+    /// Forbids network access and writing files once the input is opened, on Linux, for formatting untrusted logs
+    #[clap(long, conflicts_with_all = &["route", "output-file", "session-log", "control"])]
+    sandbox: bool,
+    /// Descriptor set compiled by `protoc --include_imports --descriptor_set_out` for --input proto
+    #[clap(long, value_name = "FILE", requires = "message-type")]
+    descriptor: Option<PathBuf>,
+    /// Message type of the varint length-prefixed records for --input proto, like `my.pkg.LogEntry`
+    #[clap(long = "type", value_name = "NAME", requires = "descriptor")]
+    message_type: Option<String>,
+    /// Reads only the lines in a range counted from 1 with an exclusive end, like `1000..2000` for lines 1000 to 1999, `1000..` or `..2000`, seeking close to it in a file indexed with `ndjson index`
+    #[clap(
+        long,
+        value_name = "START..END",
+        parse(try_from_str = slice::Slice::parse_lines),
+        conflicts_with = "bytes"
+    )]
+    lines: Option<slice::Slice>,
+    /// Reads only the lines starting in a byte range like `1GB..`, seeking to it in files and when stdin is a file
+    #[clap(long, value_name = "START..END", parse(try_from_str = slice::Slice::parse_bytes))]
+    bytes: Option<slice::Slice>,
+    /// Writes `error` and `err` objects inline instead of as a block with their stack
+    #[clap(long)]
+    inline_errors: bool,
+    /// Fields holding the level of records, colored by severity, instead of `level`, `severity`, `lvl` and `loglevel`
+    #[clap(
+        long,
+        value_name = "FIELD",
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    level_field: Vec<String>,
+    /// Maps a custom level value to a level, like `E=error` or `5=fatal`
+    #[clap(
+        long,
+        value_name = "VALUE=LEVEL",
+        parse(try_from_str = level::parse_mapping),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    level_map: Vec<(String, level::Level)>,
+    /// Writes only the fields matching these key paths, globs like `http.*` or regexes like `/_id$/`. The most specific pattern of --include, --exclude and --dim wins
+    #[clap(long, value_name = "PATTERN,...", use_delimiter = true, require_delimiter = true, multiple_occurrences(true), parse(try_from_str = fields::Pattern::parse))]
+    include: Vec<fields::Pattern>,
+    /// Doesn't write the fields matching these key paths, like `trace_id,http.headers.*`
+    #[clap(long, value_name = "PATTERN,...", use_delimiter = true, require_delimiter = true, multiple_occurrences(true), parse(try_from_str = fields::Pattern::parse))]
+    exclude: Vec<fields::Pattern>,
+    /// Writes the fields matching these key paths dimmed, like `pid,hostname`
+    #[clap(long, value_name = "PATTERN,...", use_delimiter = true, require_delimiter = true, multiple_occurrences(true), parse(try_from_str = fields::Pattern::parse))]
+    dim: Vec<fields::Pattern>,
+    /// Appends the size of each record as read, like `(2.3 KB)`
+    #[clap(long)]
+    show_size: bool,
+    /// Writes the records of each group into its own column side by side, like `by=service`
+    #[clap(long, value_name = "by=FIELD", parse(try_from_str = panes::parse))]
+    panes: Option<String>,
+    /// Drops records smaller than the size in bytes, like `10KB`
+    #[clap(long, value_name = "SIZE", parse(try_from_str = size::parse))]
+    min_size: Option<u64>,
+    /// Drops records larger than the size in bytes, like `1MB`
+    #[clap(long, value_name = "SIZE", parse(try_from_str = size::parse))]
+    max_size: Option<u64>,
+    /// Whether to color the output, `auto` respects `NO_COLOR` and `CLICOLOR_FORCE`, `always` also formats output written to a pipe
+    #[clap(long, arg_enum, value_name = "WHEN", default_value = "auto")]
+    color: ColorMode,
+    /// Formats the output as for a terminal even if stdout is a pipe, like for capturing it in CI artifacts
+    #[clap(long)]
+    assume_tty: bool,
+    /// Width of the terminal in columns for wrapping and --panes, like `160`, instead of the detected one
+    #[clap(long, value_name = "COLUMNS")]
+    width: Option<usize>,
+    /// Leaves wrapping lines wider than the terminal to it, instead of breaking them at spaces with indented continuations
+    #[clap(long)]
+    no_wrap: bool,
+    /// Writes records indented over multiple lines instead of one line each
+    #[clap(long)]
+    pretty: bool,
+    /// Writes records with a line per field and aligned keys, below a line numbering them, like `\G` in the MySQL client
+    #[clap(long, conflicts_with = "pretty")]
+    vertical: bool,
+    /// Writes these top-level keys as aligned columns below a header, a row per record, like `status,path,duration`, sized to the recent records
+    #[clap(long, value_name = "KEYS", use_delimiter = true, require_delimiter = true, multiple_occurrences(true), conflicts_with_all = &["pretty", "vertical"])]
+    table: Vec<String>,
+    /// Lays out SQL, XML and URL query strings found in string values over indented lines with --pretty
+    #[clap(long, requires = "pretty")]
+    payloads: bool,
+    /// Writes these top-level keys first, like `time,level,msg`, instead of in the order of the input
+    #[clap(
+        long,
+        value_name = "KEYS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true)
+    )]
+    first: Vec<String>,
+    /// Writes these top-level keys last, like `stack`
+    #[clap(
+        long,
+        value_name = "KEYS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true)
+    )]
+    last: Vec<String>,
+    /// Writes fields like `request` indented over multiple lines below their record, keeping the rest on one line
+    #[clap(
+        long,
+        value_name = "FIELDS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true)
+    )]
+    expand: Vec<String>,
+    /// Cuts off string values after this many characters, showing their length, except in --expand fields
+    #[clap(long, value_name = "CHARS")]
+    max_string_length: Option<usize>,
+    /// Writes timestamp fields like `ts` holding epoch numbers or ISO-8601 strings as readable local time
+    #[clap(long)]
+    humanize_time: bool,
+    /// Writes timestamp fields relative to now, like `3s ago`
+    #[clap(long)]
+    relative_time: bool,
+    /// Format of humanized timestamps with strftime specifiers
+    #[clap(long, value_name = "FORMAT", default_value = timestamp::DEFAULT_FORMAT, parse(try_from_str = timestamp::parse_format))]
+    time_format: String,
+    /// Fields holding timestamps to humanize instead of `time`, `ts`, `timestamp` and `@timestamp`
+    #[clap(
+        long,
+        value_name = "FIELDS",
+        use_delimiter = true,
+        require_delimiter = true,
+        multiple_occurrences(true)
+    )]
+    time_field: Vec<String>,
+    /// Writes only ASCII symbols instead of box drawing characters, ellipses and bars, for legacy terminals
+    #[clap(long, global = true)]
+    ascii: bool,
+    /// Writes only a sub-value of each record selected by a jq-style path like `.request.path` or `.items[].id`, or a projection like `{path: .request.path, status}`
+    #[clap(long, value_name = "QUERY", parse(try_from_str = query::Query::parse))]
+    query: Option<query::Query>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(ArgEnum, Copy, Clone, PartialEq, Debug)]
+enum InputFormat {
+    Json,
+    Msgpack,
+    Cbor,
+    Avro,
+    Proto,
+}
+
+/// Length prefix of records in a binary stream.
+#[derive(ArgEnum, Copy, Clone, PartialEq, Debug)]
+enum Framing {
+    /// A little-endian base 128 varint, like protobuf's delimited messages.
+    Varint,
+    U32be,
+    U32le,
+}
+
+#[derive(ArgEnum, Copy, Clone, PartialEq, Debug)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(ArgEnum, Copy, Clone, PartialEq, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(ArgEnum, Copy, Clone, PartialEq, Debug)]
+enum RecordFormat {
+    /// Records formatted on a terminal and as read otherwise.
+    Text,
+    /// Records as compact JSON lines.
+    Json,
+    /// Records as logfmt lines.
+    Logfmt,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    Join(join::JoinOpt),
+    Keys(keys::KeysOpt),
+    Peek(peek::PeekOpt),
+    Index(index::IndexOpt),
+    Compose(compose::ComposeOpt),
+    K8s(k8s::K8sOpt),
+    Loki(loki::LokiOpt),
+    Listen(forward::ListenOpt),
+    Redis(redis::RedisOpt),
+    Nats(nats::NatsOpt),
+    Mqtt(mqtt::MqttOpt),
+    Sse(sse::SseOpt),
+    Volume(volume::VolumeOpt),
+    Cardinality(cardinality::CardinalityOpt),
+    Rate(rate::RateOpt),
+    Replay(replay::ReplayOpt),
+    Merge(merge::MergeOpt),
+    Doctor(doctor::DoctorOpt),
+    Pick(pick::PickOpt),
+    Wrap(wrap::WrapOpt),
+    Unwrap(wrap::UnwrapOpt),
+    SelfUpdate(update::UpdateOpt),
+    Config(config::ConfigOpt),
+}
 
 fn main() -> io::Result<()> {
-    Opt::parse();
+    let opt = Opt::parse();
+    let ascii = opt.ascii;
+
+    match opt.command {
+        Some(Command::Join(opt)) => return join::run(&opt),
+        Some(Command::Keys(opt)) => return keys::run(&opt),
+        Some(Command::Peek(opt)) => return peek::run(&opt),
+        Some(Command::Index(opt)) => return index::run(&opt),
+        Some(Command::Compose(opt)) => return compose::run(&opt),
+        Some(Command::K8s(opt)) => return k8s::run(&opt),
+        Some(Command::Loki(opt)) => return loki::run(&opt),
+        Some(Command::Listen(opt)) => return forward::run(&opt),
+        Some(Command::Redis(opt)) => return redis::run(&opt),
+        Some(Command::Nats(opt)) => return nats::run(&opt),
+        Some(Command::Mqtt(opt)) => return mqtt::run(&opt),
+        Some(Command::Sse(opt)) => return sse::run(&opt),
+        Some(Command::Volume(opt)) => return volume::run(&opt),
+        Some(Command::Cardinality(opt)) => return cardinality::run(&opt),
+        Some(Command::Rate(opt)) => return rate::run(&opt, ascii),
+        Some(Command::Replay(opt)) => return replay::run(&opt),
+        Some(Command::Merge(opt)) => return merge::run(&opt),
+        Some(Command::Doctor(opt)) => return doctor::run(&opt),
+        Some(Command::Pick(opt)) => return pick::run(&opt),
+        Some(Command::Wrap(opt)) => return wrap::run_wrap(&opt),
+        Some(Command::Unwrap(opt)) => return wrap::run_unwrap(&opt),
+        Some(Command::SelfUpdate(opt)) => return update::run(&opt),
+        Some(Command::Config(opt)) => return config::run(&opt),
+        None => {}
+    }
 
-    if atty::is(atty::Stream::Stdin) {
+    if opt.files.is_empty() && atty::is(atty::Stream::Stdin) {
         if atty::is(atty::Stream::Stdout) {
             Opt::into_app().print_help()?;
         }
         std::process::exit(1);
     }
 
-    if !atty::is(atty::Stream::Stdout) {
-        let mut stdin = io::stdin();
-        let mut stdout = io::stdout();
-        io::copy(&mut stdin, &mut stdout)?;
-        return Ok(());
+    let config = config::load()?;
+    let mut pipeline = Pipeline::new(&opt, &config)?;
+    let slice = opt.lines.or(opt.bytes);
+    if slice.is_some() && (opt.input != InputFormat::Json || opt.framing.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--lines and --bytes only apply to JSON input",
+        ));
     }
-
-    let stdin = io::stdin();
-    let mut stdout = ColoredWriter::new(StandardStream::stdout(ColorChoice::Always));
-
-    for line in stdin.lock().lines() {
-        write_line(&mut stdout, &line?)?;
+    if opt.framing.is_some() && matches!(opt.input, InputFormat::Avro | InputFormat::Proto) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--framing only applies to json, msgpack and cbor input",
+        ));
     }
 
-    Ok(())
-}
-
-fn write_line<T: WriteColor>(writer: &mut ColoredWriter<T>, line: &str) -> io::Result<()> {
-    match serde_json::from_str(line) {
-        Ok(Value::Object(object)) if !object.is_empty() => {
-            write_object(writer, &object)?;
-            writer.set_kind(TokenKind::None);
+    let proto = match (opt.input, &opt.descriptor, &opt.message_type) {
+        (InputFormat::Proto, Some(descriptor), Some(message_type)) => {
+            Some(proto::ProtoDecoder::load(descriptor, message_type)?)
         }
-        Ok(value) if value.as_array().map_or(false, |array| !array.is_empty()) => {
-            write_value(writer, &value)?;
-            writer.set_kind(TokenKind::None);
+        (InputFormat::Proto, _, _) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--input proto requires --descriptor and --type",
+            ))
         }
-        _ => writer.set_kind(TokenKind::Unknown).write(line)?,
-    }
-    writer.write("\n")
-}
+        (_, None, _) => None,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--descriptor and --type only apply to --input proto",
+            ))
+        }
+    };
 
-fn write_value<T: WriteColor>(writer: &mut ColoredWriter<T>, value: &Value) -> io::Result<()> {
-    match value {
-        Value::String(string) => writer.set_kind(TokenKind::String).write(string),
-        Value::Array(array) => {
-            writer.set_kind(TokenKind::None).write("[")?;
-            for (index, value) in array.iter().enumerate() {
-                if index != 0 {
-                    writer.set_kind(TokenKind::None).write(", ")?;
+    let colors = match opt.color {
+        ColorMode::Auto => env_colors().unwrap_or(true),
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+    };
+    let formatted = opt.assume_tty
+        || opt.color == ColorMode::Always
+        || (opt.color == ColorMode::Auto && env_colors() == Some(true))
+        || atty::is(atty::Stream::Stdout);
+    let shape = fields::Shape {
+        rules: fields::FieldRules::new(&opt.include, &opt.exclude, &opt.dim),
+        first: opt.first.clone(),
+        last: opt.last.clone(),
+        max_string_length: opt.max_string_length,
+        expand: opt.expand.clone(),
+    };
+    let mut output = if opt.format == RecordFormat::Json {
+        Output::Json(io::stdout(), shape, opt.sort_keys)
+    } else if opt.format == RecordFormat::Logfmt {
+        Output::Logfmt(io::stdout(), shape)
+    } else if formatted {
+        match &opt.panes {
+            Some(field) => {
+                let buffer = match colors {
+                    true => Buffer::ansi(),
+                    false => Buffer::no_color(),
+                };
+                let formatter = configure(Formatter::new(buffer), &opt, &config);
+                let panes = panes::Panes::new(field.clone(), formatter, opt.width);
+                Output::Panes(panes, io::stdout())
+            }
+            None => {
+                let mut formatter = configure(
+                    Formatter::new(StandardStream::stdout(match colors {
+                        true => ColorChoice::Always,
+                        false => ColorChoice::Never,
+                    })),
+                    &opt,
+                    &config,
+                );
+                if !opt.no_wrap && opt.table.is_empty() {
+                    formatter.writer.width = opt.width.or_else(terminal_width);
                 }
-                write_value(writer, value)?;
+                Output::Formatted(formatter)
             }
-            writer.set_kind(TokenKind::None).write("]")
         }
-        Value::Object(object) => {
-            if object.is_empty() {
-                writer.set_kind(TokenKind::None).write("{}")
-            } else {
-                writer.set_kind(TokenKind::None).write("{ ")?;
-                write_object(writer, object)?;
-                writer.set_kind(TokenKind::None).write(" }")
+    } else if pipeline.is_empty()
+        && opt.exit_idle.is_none()
+        && opt.control.is_none()
+        && opt.files.is_empty()
+        && !opt.explode
+        && slice.is_none()
+        && opt.input == InputFormat::Json
+        && opt.framing.is_none()
+        && opt.grep.is_none()
+        && !opt.collapse
+    {
+        if opt.sandbox {
+            sandbox::enter()?;
+        }
+        io::copy(&mut io::stdin(), &mut io::stdout())?;
+        return Ok(());
+    } else {
+        Output::Raw(io::stdout())
+    };
+
+    if opt.follow && opt.files.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--follow requires a file",
+        ));
+    }
+    let mut grep = opt.grep.clone().map(|regex| {
+        grep::Grep::new(
+            regex,
+            opt.before_context.or(opt.context).unwrap_or(0),
+            opt.after_context.or(opt.context).unwrap_or(0),
+        )
+    });
+    let mut collapser = opt
+        .collapse
+        .then(|| collapse::Collapser::new(opt.collapse_ignore.clone()));
+    let terminal = atty::is(atty::Stream::Stdout);
+    let mut stats = (opt.stats && terminal).then(|| {
+        let levels = level::LevelFields::new(opt.level_field.clone(), opt.level_map.clone());
+        stats::Stats::new(levels, Instant::now())
+    });
+    let status_width = opt.width.or_else(terminal_width).unwrap_or(80);
+    if opt.sandbox {
+        sandbox::enter()?;
+    }
+    let events = input::input_events(
+        opt.files.clone(),
+        opt.follow,
+        slice,
+        pipeline.skippable_conditions(),
+        input::Decoding {
+            format: opt.input,
+            framing: opt.framing,
+            proto,
+            depth: opt.limits.depth().min(binary::MAX_DEPTH),
+        },
+        opt.explode,
+        opt.control.clone(),
+    )?;
+    let mut last_event = Instant::now();
+    loop {
+        // The status line is redrawn while no input arrives, for the rate to drop.
+        let timeout = match (opt.exit_idle, &stats) {
+            (Some(idle), Some(_)) => Some(
+                idle.saturating_sub(last_event.elapsed())
+                    .min(stats::INTERVAL),
+            ),
+            (Some(idle), None) => Some(idle),
+            (None, Some(_)) => Some(stats::INTERVAL),
+            (None, None) => None,
+        };
+        let event = match timeout {
+            Some(timeout) => events.recv_timeout(timeout),
+            None => events.recv().map_err(RecvTimeoutError::from),
+        };
+        let now = Instant::now();
+        if event.is_ok() {
+            last_event = now;
+        }
+        match event {
+            Err(RecvTimeoutError::Timeout)
+                if opt.exit_idle.is_none_or(|idle| last_event.elapsed() < idle) =>
+            {
+                if let Some(stats) = &mut stats {
+                    stats.tick(now);
+                    output.write_status(&stats.status(), status_width)?;
+                }
+            }
+            Ok(Event::Line(line)) => {
+                let line = line?;
+                let processed = pipeline.process(input::trim_newline(&line));
+                if let Some(stats) = &mut stats {
+                    stats.observe(&line, &processed, now);
+                }
+                let lines = match &mut grep {
+                    Some(grep) => grep.filter(line, processed),
+                    None => vec![(line, processed)],
+                };
+                for (line, processed) in lines {
+                    match collapser
+                        .as_mut()
+                        .and_then(|collapser| collapser.repeat(&line, &processed))
+                    {
+                        Some(count) => output.write_repeats(count)?,
+                        None => output.write(&line, processed)?,
+                    }
+                }
+                for alert in pipeline.take_alerts() {
+                    output.write_banner(&alert)?;
+                }
+                if let Some(stats) = &mut stats {
+                    if stats.is_due(now) {
+                        output.write_status(&stats.status(), status_width)?;
+                    }
+                }
+            }
+            Ok(Event::Annotation(message)) => {
+                let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+                output.write_banner(&format!("{} {}", now, message))?;
+            }
+            Ok(Event::Interrupted) => {
+                output.reset()?;
+                report_collapsed(collapser.as_ref(), opt.output);
+                pipeline.finish(true)?;
+                std::process::exit(130);
+            }
+            Ok(Event::End) | Err(_) => {
+                if let Some(summary) = pipeline.flush() {
+                    output.write(b"", Processed::Changed(summary))?;
+                }
+                if let Some(stats) = &mut stats {
+                    stats.tick(now);
+                    output.write_status(&stats.status(), status_width)?;
+                    output.keep_status()?;
+                }
+                output.reset()?;
+                report_collapsed(collapser.as_ref(), opt.output);
+                return pipeline.finish(false);
             }
         }
-        _ => writer.set_kind(TokenKind::Value).write(&value.to_string()),
     }
 }
 
-fn write_object<T: WriteColor>(
-    writer: &mut ColoredWriter<T>,
-    object: &serde_json::Map<String, Value>,
-) -> io::Result<()> {
-    for (index, (key, value)) in object.iter().enumerate() {
-        if index != 0 {
-            writer.write(" ")?;
+/// Reports the number of repeats left out by --collapse.
+fn report_collapsed(collapser: Option<&collapse::Collapser>, output: OutputFormat) {
+    let collapsed = match collapser {
+        Some(collapser) => collapser.collapsed(),
+        None => return,
+    };
+    match output {
+        OutputFormat::Text => {
+            eprintln!("ndjson: collapsed {} repeated records", collapsed)
         }
-        writer.set_kind(TokenKind::Key).write(key)?;
-        writer.set_kind(TokenKind::None).write(": ")?;
-        write_value(writer, value)?;
+        OutputFormat::Json => eprintln!(r#"{{"repeats_collapsed":{}}}"#, collapsed),
     }
-    Ok(())
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-enum TokenKind {
-    Unknown,
-    None,
-    Key,
-    Value,
-    String,
+/// Applies the display options to a formatter for the terminal.
+fn configure<T: WriteColor>(
+    mut formatter: Formatter<T>,
+    opt: &Opt,
+    config: &config::Config,
+) -> Formatter<T> {
+    formatter.writer.theme = config.theme.clone();
+    if opt.detect_secrets {
+        formatter.writer.secrets = Some(secrets::SecretScanner::default());
+    }
+    formatter.writer.highlights = opt.highlight.clone();
+    formatter.spotlight = opt.spotlight.as_deref().map(spotlight::Spotlight::new);
+    formatter.scales = opt.scale.clone();
+    formatter.error_blocks = !opt.inline_errors;
+    formatter.show_size = opt.show_size;
+    formatter.ascii = opt.ascii;
+    formatter.pretty = opt.pretty;
+    formatter.vertical = opt.vertical;
+    formatter.table = opt.table.clone();
+    formatter.payloads = opt.payloads;
+    formatter.escalations = opt.escalate.clone();
+    formatter.trends = opt.trend.clone();
+    formatter.fields = fields::FieldRules::new(&opt.include, &opt.exclude, &opt.dim);
+    formatter.levels = level::LevelFields::new(opt.level_field.clone(), opt.level_map.clone());
+    formatter.expand = opt.expand.clone();
+    formatter.max_string_length = opt.max_string_length;
+    formatter.first = opt.first.clone();
+    formatter.last = opt.last.clone();
+    if opt.humanize_time || opt.relative_time {
+        formatter.times = Some(timestamp::TimeDisplay::new(
+            opt.time_field.clone(),
+            opt.time_format.clone(),
+            opt.relative_time,
+        ));
+    }
+    formatter
 }
 
-struct ColoredWriter<T: WriteColor> {
-    writer: T,
-    current_kind: TokenKind,
-    written_kind: TokenKind,
+/// Destination of the processed lines.
+enum Output {
+    /// Lines are written unformatted, because stdout isn't a terminal.
+    Raw(io::Stdout),
+    Formatted(Formatter<StandardStream>),
+    Panes(panes::Panes, io::Stdout),
+    /// Records are written as compact JSON, with sorted keys if set, other lines as read.
+    Json(io::Stdout, fields::Shape, bool),
+    /// Records are written as logfmt lines, other lines as read.
+    Logfmt(io::Stdout, fields::Shape),
 }
 
-impl<T: WriteColor> ColoredWriter<T> {
-    pub fn new(writer: T) -> Self {
-        ColoredWriter {
-            writer,
-            current_kind: TokenKind::Unknown,
-            written_kind: TokenKind::Unknown,
+impl Output {
+    /// Writes a line as read including its terminator, unchanged lines are copied byte for byte.
+    fn write(&mut self, line: &[u8], processed: Processed) -> io::Result<()> {
+        match (self, processed) {
+            (Output::Raw(stdout), Processed::Unchanged(_)) => stdout.write_all(line),
+            (Output::Raw(stdout), Processed::Changed(value)) => writeln!(stdout, "{}", value),
+            (Output::Formatted(formatter), Processed::Unchanged(value)) => {
+                formatter.write_parsed_line(input::trim_newline(line), value.as_ref())
+            }
+            (Output::Formatted(formatter), Processed::Changed(Value::Object(object)))
+                if !object.is_empty() =>
+            {
+                formatter.write_parsed_line(input::trim_newline(line), Some(&Value::Object(object)))
+            }
+            (Output::Formatted(formatter), Processed::Changed(value)) => {
+                formatter.write_value_line(&value)
+            }
+            (Output::Panes(panes, stdout), Processed::Unchanged(value)) => {
+                panes.write(stdout, input::trim_newline(line), value.as_ref())
+            }
+            (Output::Panes(panes, stdout), Processed::Changed(value)) => {
+                panes.write(stdout, value.to_string().as_bytes(), Some(&value))
+            }
+            (Output::Json(stdout, shape, sorted), Processed::Unchanged(Some(value)))
+            | (Output::Json(stdout, shape, sorted), Processed::Changed(value)) => {
+                let value = match value {
+                    Value::Object(object) => match shape.apply(&object) {
+                        Some(object) => Value::Object(object),
+                        None => return Ok(()),
+                    },
+                    value => value,
+                };
+                match sorted {
+                    true => writeln!(stdout, "{}", sort_keys(&value)),
+                    false => writeln!(stdout, "{}", value),
+                }
+            }
+            (Output::Json(stdout, ..), Processed::Unchanged(None)) => stdout.write_all(line),
+            (Output::Logfmt(stdout, shape), Processed::Unchanged(Some(Value::Object(object))))
+            | (Output::Logfmt(stdout, shape), Processed::Changed(Value::Object(object))) => {
+                match shape.apply(&object) {
+                    Some(object) => writeln!(stdout, "{}", logfmt::format(&object)),
+                    None => Ok(()),
+                }
+            }
+            (Output::Logfmt(stdout, _), Processed::Unchanged(_)) => stdout.write_all(line),
+            (Output::Logfmt(stdout, _), Processed::Changed(value)) => {
+                writeln!(stdout, "{}", value)
+            }
+            (_, Processed::Dropped) => Ok(()),
         }
     }
 
-    pub fn set_kind(&mut self, kind: TokenKind) -> &mut Self {
-        self.current_kind = kind;
-        if kind == TokenKind::Unknown {
-            self.written_kind = kind;
+    /// Counts a repeat of the last record under it if formatted, other outputs leave repeats out.
+    fn write_repeats(&mut self, count: u64) -> io::Result<()> {
+        match self {
+            Output::Formatted(formatter) => formatter.write_repeats(count),
+            _ => Ok(()),
         }
-        self
     }
 
-    pub fn write(&mut self, string: &str) -> io::Result<()> {
-        if string.is_empty() {
-            return Ok(());
+    /// Writes the status line of --stats below the output if formatted.
+    fn write_status(&mut self, parts: &[(TokenKind, String)], width: usize) -> io::Result<()> {
+        match self {
+            Output::Formatted(formatter) => formatter.write_status(parts, width),
+            _ => Ok(()),
         }
-        if self.written_kind != self.current_kind {
-            let color = match self.current_kind {
-                TokenKind::None | TokenKind::Unknown => None,
-                TokenKind::Key => Some(Color::Yellow),
-                TokenKind::Value => Some(Color::Green),
-                TokenKind::String => Some(Color::Cyan),
-            };
-            match color {
-                _ if self.current_kind == TokenKind::Unknown => {}
-                None => self.writer.reset()?,
-                Some(color) => self
-                    .writer
-                    .set_color(ColorSpec::new().set_fg(Some(color)).set_intense(true))?,
-            };
-            self.written_kind = self.current_kind
+    }
+
+    fn keep_status(&mut self) -> io::Result<()> {
+        match self {
+            Output::Formatted(formatter) => formatter.keep_status(),
+            _ => Ok(()),
         }
-        self.writer.write_all(string.as_bytes())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use termcolor::Buffer;
+    /// Writes a message which isn't part of the input, to stderr if stdout isn't a terminal.
+    fn write_banner(&mut self, message: &str) -> io::Result<()> {
+        match self {
+            Output::Raw(_) | Output::Json(..) | Output::Logfmt(..) => {
+                writeln!(io::stderr(), "ndjson: {}", message)
+            }
+            Output::Formatted(formatter) => formatter.write_banner(message),
+            Output::Panes(panes, stdout) => panes.write_banner(stdout, message),
+        }
+    }
 
-    fn format(buffer: Buffer, input: &str) -> String {
-        let mut buffer = ColoredWriter::new(buffer);
-        for line in input.split('\n') {
-            write_line(&mut buffer, line).unwrap();
+    fn reset(&mut self) -> io::Result<()> {
+        match self {
+            Output::Raw(stdout) | Output::Json(stdout, ..) | Output::Logfmt(stdout, _) => {
+                stdout.flush()
+            }
+            Output::Formatted(formatter) => formatter.reset(),
+            Output::Panes(_, stdout) => stdout.flush(),
         }
-        let mut output = String::from_utf8(buffer.writer.into_inner()).unwrap();
-        assert_eq!(output.pop(), Some('\n'));
-        output
     }
+}
 
-    #[test]
-    fn test_color() {
-        assert_eq!(
-            format(
-                Buffer::ansi(),
-                r#"{"null":null,"string":"string","array":[1],"object":{"key":"value"}}"#
-            ),
-            "[0m[38;5;11mnull[0m: [0m[38;5;10mnull [0m[38;5;11mstring[0m: [0m[38;5;14mstring [0m[38;5;11marray[0m: [[0m[38;5;10m1[0m] [0m[38;5;11mobject[0m: { [0m[38;5;11mkey[0m: [0m[38;5;14mvalue[0m }"
-        );
-        assert_eq!(format(Buffer::ansi(), r#"[""]"#), "[0m[]");
+/// Returns the value with the keys of its objects in sorted order.
+fn sort_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(array) => Value::Array(array.iter().map(sort_keys).collect()),
+        value => value.clone(),
     }
+}
+
+/// Returns whether colors are forced on by `CLICOLOR_FORCE` or off by `NO_COLOR`, which wins.
+fn env_colors() -> Option<bool> {
+    let set = |name| env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0");
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        Some(false)
+    } else if set("CLICOLOR_FORCE") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Returns the width of the terminal stdout is written to, if it is one.
+#[cfg(unix)]
+fn terminal_width() -> Option<usize> {
+    // SAFETY: TIOCGWINSZ only writes the size of the terminal into the zeroed struct.
+    let size = unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        match libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) {
+            0 => size,
+            _ => return None,
+        }
+    };
+    (size.ws_col > 0).then_some(size.ws_col as usize)
+}
 
-    #[test]
-    fn test_unchanged() {
-        for s in ["text", "0", "{   }", "[   ]"] {
-            assert_eq!(format(Buffer::ansi(), s), s);
+#[cfg(windows)]
+fn terminal_width() -> Option<usize> {
+    use winapi::um::processenv::GetStdHandle;
+    use winapi::um::winbase::STD_OUTPUT_HANDLE;
+    use winapi::um::wincon::{GetConsoleScreenBufferInfo, CONSOLE_SCREEN_BUFFER_INFO};
+    // SAFETY: the call only writes the state of the console into the zeroed struct, and fails for
+    // handles which aren't one.
+    let info = unsafe {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = std::mem::zeroed();
+        match GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT_HANDLE), &mut info) {
+            0 => return None,
+            _ => info,
         }
+    };
+    let width = info.srWindow.Right - info.srWindow.Left + 1;
+    (width > 0).then_some(width as usize)
+}
+
+/// Returns the colors of formatted output of subcommands, which only respect `NO_COLOR`.
+fn color_choice() -> ColorChoice {
+    match env_colors() {
+        Some(false) => ColorChoice::Never,
+        _ => ColorChoice::Always,
     }
+}
 
-    #[test]
-    fn test_collections() {
-        for (input, output) in [
-            (r#"{"key":"value"}"#, "key: value"),
-            (r#"["value"]"#, "[value]"),
-            (r#"{"array":[],"object":{}}"#, "array: [] object: {}"),
-            (r#"[[],{}]"#, "[[], {}]"),
-            (
-                r#"{"array": ["value"],"object":{"key":"value"}}"#,
-                "array: [value] object: { key: value }",
-            ),
-            (
-                r#"[["value"],{"key":"value"}]"#,
-                "[[value], { key: value }]",
-            ),
-        ] {
-            assert_eq!(format(Buffer::no_color(), input), output);
+/// Writes generated lines, formatted when stdout is a terminal.
+fn print_lines<I: Iterator<Item = io::Result<String>>>(lines: I) -> io::Result<()> {
+    if !atty::is(atty::Stream::Stdout) {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        for line in lines {
+            writeln!(stdout, "{}", line?)?;
         }
+        return Ok(());
+    }
+
+    let mut stdout = Formatter::new(StandardStream::stdout(color_choice()));
+    stdout.writer.theme = config::load()?.theme;
+    for line in lines {
+        stdout.write_line(&line?)?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn test_numbers() {
-        for (input, output) in [
-            ("0", "0"),
-            ("1234567890", "1234567890"),
-            ("0.01", "0.01"),
-            ("0.00", "0.0"),
-            ("1e2", "100.0"),
-        ] {
-            assert_eq!(
-                format(Buffer::no_color(), &format!("[{}]", input)),
-                format!("[{}]", output)
-            );
+/// Writes lines with their source if any, as a field of records or a prefix of other lines when piped.
+fn print_tagged_lines<I>(field: &str, lines: I) -> io::Result<()>
+where
+    I: Iterator<Item = io::Result<(String, String)>>,
+{
+    if !atty::is(atty::Stream::Stdout) {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        for line in lines {
+            let (tag, line) = line?;
+            match serde_json::from_str(&line) {
+                _ if tag.is_empty() => writeln!(stdout, "{}", line)?,
+                Ok(Value::Object(object)) => {
+                    let mut tagged = Map::new();
+                    tagged.insert(field.to_string(), Value::String(tag));
+                    tagged.extend(object);
+                    writeln!(stdout, "{}", Value::Object(tagged))?;
+                }
+                _ => writeln!(stdout, "{} | {}", tag, line)?,
+            }
         }
+        return Ok(());
     }
 
-    #[test]
-    fn test_empty_string() {
-        assert_eq!(format(Buffer::no_color(), r#"{"":""}"#), ": ");
-        assert_eq!(format(Buffer::no_color(), r#"[""]"#), "[]");
+    let mut stdout = Formatter::new(StandardStream::stdout(color_choice()));
+    stdout.writer.theme = config::load()?.theme;
+    for line in lines {
+        let (tag, line) = line?;
+        if !tag.is_empty() {
+            stdout.write_tag(&tag)?;
+        }
+        stdout.write_line(&line)?;
     }
+    Ok(())
 }
@@ -0,0 +1,169 @@
+use crate::{duration, timestamp};
+use clap::Args;
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Lines};
+use std::path::{Path, PathBuf};
+
+/// Interleaves the records of files by their timestamps, tagged with their file
+#[derive(Args, Debug)]
+pub struct MergeOpt {
+    /// Files with records in time order
+    #[clap(required = true)]
+    files: Vec<PathBuf>,
+    /// Corrects the clock skew of a file before ordering, like `db.log=+2.5s` or `app.log=-300ms`
+    #[clap(
+        long,
+        value_name = "FILE=OFFSET",
+        parse(try_from_str = parse_offset),
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    offset: Vec<(String, i64)>,
+}
+
+/// Parses a file and a signed duration into milliseconds.
+fn parse_offset(offset: &str) -> Result<(String, i64), String> {
+    let (file, duration) = offset
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected FILE=OFFSET instead of `{}`", offset))?;
+    let (sign, duration) = match duration.strip_prefix('-') {
+        Some(duration) => (-1, duration),
+        None => (1, duration.strip_prefix('+').unwrap_or(duration)),
+    };
+    Ok((
+        file.to_string(),
+        sign * duration::parse(duration)?.as_millis() as i64,
+    ))
+}
+
+/// A file with its next line, keyed by the corrected time of its last timestamp.
+struct Source {
+    tag: String,
+    lines: Lines<BufReader<File>>,
+    offset: i64,
+    time: i64,
+}
+
+impl Source {
+    /// Reads the next line, lines without a timestamp keep the time of the previous one.
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        let line = match self.lines.next() {
+            Some(line) => line?,
+            None => return Ok(None),
+        };
+        if let Ok(Value::Object(object)) = serde_json::from_str(&line) {
+            if let Some(time) = timestamp::detect(&object) {
+                self.time = time.timestamp_millis() + self.offset;
+            }
+        }
+        Ok(Some(line))
+    }
+}
+
+pub fn run(opt: &MergeOpt) -> io::Result<()> {
+    let mut sources = Vec::new();
+    for path in &opt.files {
+        let offset = opt
+            .offset
+            .iter()
+            .filter(|(file, _)| matches(path, file))
+            .map(|(_, offset)| offset)
+            .sum();
+        sources.push(Source {
+            tag: path.to_string_lossy().into_owned(),
+            lines: BufReader::new(File::open(path)?).lines(),
+            offset,
+            time: i64::MIN,
+        });
+    }
+    crate::print_tagged_lines("source", Merged::new(sources)?)
+}
+
+/// Whether an offset given for `file` applies to the path, by its full path or file name.
+fn matches(path: &Path, file: &str) -> bool {
+    path == Path::new(file) || path.file_name().is_some_and(|name| name == file)
+}
+
+/// Yields the tagged lines of all sources, always from the one with the earliest time.
+struct Merged {
+    sources: Vec<Source>,
+    next: BinaryHeap<Reverse<(i64, usize, String)>>,
+}
+
+impl Merged {
+    fn new(mut sources: Vec<Source>) -> io::Result<Self> {
+        let mut next = BinaryHeap::new();
+        for (index, source) in sources.iter_mut().enumerate() {
+            if let Some(line) = source.next_line()? {
+                next.push(Reverse((source.time, index, line)));
+            }
+        }
+        Ok(Merged { sources, next })
+    }
+}
+
+impl Iterator for Merged {
+    type Item = io::Result<(String, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((_, index, line)) = self.next.pop()?;
+        let source = &mut self.sources[index];
+        match source.next_line() {
+            Ok(Some(next)) => self.next.push(Reverse((source.time, index, next))),
+            Ok(None) => {}
+            Err(err) => return Some(Err(err)),
+        }
+        Some(Ok((source.tag.clone(), line)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(
+            parse_offset("db.log=+2.5s"),
+            Ok(("db.log".to_string(), 2500))
+        );
+        assert_eq!(parse_offset("a=b=-300ms"), Ok(("a=b".to_string(), -300)));
+        assert_eq!(parse_offset("db.log=1m"), Ok(("db.log".to_string(), 60000)));
+        assert!(parse_offset("db.log").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let dir = std::env::temp_dir().join(format!("ndjson-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.log"), dir.join("b.log"));
+        fs::write(&a, "{\"ts\":10,\"n\":1}\ntext\n{\"ts\":13,\"n\":3}\n").unwrap();
+        fs::write(&b, "{\"ts\":9,\"n\":2}\n{\"ts\":14,\"n\":4}\n").unwrap();
+        let source = |path: &PathBuf, offset| Source {
+            tag: path.file_name().unwrap().to_string_lossy().into_owned(),
+            lines: BufReader::new(File::open(path).unwrap()).lines(),
+            offset,
+            time: i64::MIN,
+        };
+        let merged: Vec<_> = Merged::new(vec![source(&a, 0), source(&b, 2000)])
+            .unwrap()
+            .map(|line| line.unwrap())
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        let line = |tag: &str, line: &str| (tag.to_string(), line.to_string());
+        assert_eq!(
+            merged,
+            [
+                line("a.log", r#"{"ts":10,"n":1}"#),
+                line("a.log", "text"),
+                line("b.log", r#"{"ts":9,"n":2}"#),
+                line("a.log", r#"{"ts":13,"n":3}"#),
+                line("b.log", r#"{"ts":14,"n":4}"#),
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::binary::{float, invalid, nested, read_bytes, read_first, read_uint};
+use crate::format::binary_value;
+use serde_json::{json, Map, Value};
+use std::convert::TryInto;
+use std::io::{self, Read};
+
+/// Reads the next MessagePack value with arrays and maps nested at most `depth` levels, or `None`
+/// if the input ended before it.
+///
+/// Binary data becomes an array of bytes, timestamps become epoch seconds and other extensions
+/// become an object with their `type` and `data`.
+pub fn read_value<R: Read>(reader: &mut R, depth: usize) -> io::Result<Option<Value>> {
+    match read_first(reader)? {
+        Some(marker) => read_marked(reader, marker, depth).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn read_marked<R: Read>(reader: &mut R, marker: u8, depth: usize) -> io::Result<Value> {
+    Ok(match marker {
+        0x00..=0x7f => Value::from(marker),
+        0x80..=0x8f => read_map(reader, (marker & 0x0f) as usize, depth)?,
+        0x90..=0x9f => read_array(reader, (marker & 0x0f) as usize, depth)?,
+        0xa0..=0xbf => read_str(reader, (marker & 0x1f) as usize)?,
+        0xc0 => Value::Null,
+        0xc2 => Value::Bool(false),
+        0xc3 => Value::Bool(true),
+        0xc4..=0xc6 => {
+            let length = read_uint(reader, 1 << (marker - 0xc4))?;
+            binary_value(&read_bytes(reader, length as usize)?)
+        }
+        0xc7..=0xc9 => {
+            let length = read_uint(reader, 1 << (marker - 0xc7))?;
+            read_ext(reader, length as usize)?
+        }
+        0xca => float(f32::from_bits(read_uint(reader, 4)? as u32) as f64),
+        0xcb => float(f64::from_bits(read_uint(reader, 8)?)),
+        0xcc => Value::from(read_uint(reader, 1)?),
+        0xcd => Value::from(read_uint(reader, 2)?),
+        0xce => Value::from(read_uint(reader, 4)?),
+        0xcf => Value::from(read_uint(reader, 8)?),
+        0xd0 => Value::from(read_uint(reader, 1)? as u8 as i8),
+        0xd1 => Value::from(read_uint(reader, 2)? as u16 as i16),
+        0xd2 => Value::from(read_uint(reader, 4)? as u32 as i32),
+        0xd3 => Value::from(read_uint(reader, 8)? as i64),
+        0xd4..=0xd8 => read_ext(reader, 1 << (marker - 0xd4))?,
+        0xd9..=0xdb => {
+            let length = read_uint(reader, 1 << (marker - 0xd9))?;
+            read_str(reader, length as usize)?
+        }
+        0xdc | 0xdd => {
+            let length = read_uint(reader, 2 << (marker - 0xdc))?;
+            read_array(reader, length as usize, depth)?
+        }
+        0xde | 0xdf => {
+            let length = read_uint(reader, 2 << (marker - 0xde))?;
+            read_map(reader, length as usize, depth)?
+        }
+        0xe0..=0xff => Value::from(marker as i8),
+        0xc1 => return Err(invalid("reserved MessagePack marker 0xc1")),
+    })
+}
+
+fn read_next<R: Read>(reader: &mut R, depth: usize) -> io::Result<Value> {
+    read_value(reader, depth)?.ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+}
+
+fn read_array<R: Read>(reader: &mut R, length: usize, depth: usize) -> io::Result<Value> {
+    let depth = nested(depth)?;
+    (0..length)
+        .map(|_| read_next(reader, depth))
+        .collect::<io::Result<_>>()
+        .map(Value::Array)
+}
+
+/// Reads a map, converting keys which aren't strings to their JSON text.
+fn read_map<R: Read>(reader: &mut R, length: usize, depth: usize) -> io::Result<Value> {
+    let depth = nested(depth)?;
+    let mut map = Map::new();
+    for _ in 0..length {
+        let key = match read_next(reader, depth)? {
+            Value::String(key) => key,
+            key => key.to_string(),
+        };
+        map.insert(key, read_next(reader, depth)?);
+    }
+    Ok(Value::Object(map))
+}
+
+fn read_str<R: Read>(reader: &mut R, length: usize) -> io::Result<Value> {
+    let bytes = read_bytes(reader, length)?;
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| invalid("MessagePack string isn't valid UTF-8"))
+}
+
+fn read_ext<R: Read>(reader: &mut R, length: usize) -> io::Result<Value> {
+    let kind = read_uint(reader, 1)? as u8 as i8;
+    let data = read_bytes(reader, length)?;
+    Ok(match (kind, data.len()) {
+        (-1, 4) => Value::from(u32::from_be_bytes(data[..].try_into().unwrap())),
+        (-1, 8) => {
+            let value = u64::from_be_bytes(data[..].try_into().unwrap());
+            float((value & 0x3_ffff_ffff) as f64 + (value >> 34) as f64 / 1e9)
+        }
+        (-1, 12) => {
+            let nanos = u32::from_be_bytes(data[..4].try_into().unwrap());
+            let seconds = i64::from_be_bytes(data[4..].try_into().unwrap());
+            float(seconds as f64 + nanos as f64 / 1e9)
+        }
+        _ => json!({"type": kind, "data": data}),
+    })
+}
+
+/// Appends a string, for the few replies which are written as MessagePack.
+pub fn write_str(buffer: &mut Vec<u8>, string: &str) {
+    match string.len() {
+        length @ 0..=31 => buffer.push(0xa0 | length as u8),
+        length @ 32..=255 => buffer.extend([0xd9, length as u8]),
+        length @ 256..=65535 => {
+            buffer.push(0xda);
+            buffer.extend((length as u16).to_be_bytes());
+        }
+        length => {
+            buffer.push(0xdb);
+            buffer.extend((length as u32).to_be_bytes());
+        }
+    }
+    buffer.extend(string.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> Value {
+        read_value(&mut &bytes[..], 64).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_read_value() {
+        assert_eq!(
+            decode(b"\x83\xa3msg\xa2hi\xa5count\xcd\x01\x00\x01\x93\xc0\xc3\xff"),
+            json!({"msg": "hi", "count": 256, "1": [null, true, -1]})
+        );
+        assert_eq!(decode(b"\xcb\x3f\xf8\x00\x00\x00\x00\x00\x00"), json!(1.5));
+        assert_eq!(decode(b"\xd0\x80"), json!(-128));
+        assert_eq!(decode(b"\xc4\x02\x01\x02"), json!({"$binary": "0102"}));
+        assert_eq!(decode(b"\xd6\xff\x00\x00\x00\x2a"), json!(42));
+        assert_eq!(
+            decode(b"\xd5\x05\x01\x02"),
+            json!({"type": 5, "data": [1, 2]})
+        );
+        assert!(read_value(&mut &b""[..], 64).unwrap().is_none());
+        assert!(read_value(&mut &b"\xdb\xff\xff\xff\xff"[..], 64).is_err());
+        assert!(read_value(&mut &b"\xc1"[..], 64).is_err());
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(
+            read_value(&mut &b"\x91\x81\xa1a\x01"[..], 2).unwrap(),
+            Some(json!([{"a": 1}]))
+        );
+        assert!(read_value(&mut &b"\x91\x91\x91\x01"[..], 2).is_err());
+        let deep = [0x91; 1 << 18];
+        assert!(read_value(&mut &deep[..], 64).is_err());
+    }
+
+    #[test]
+    fn test_write_str() {
+        let mut buffer = Vec::new();
+        write_str(&mut buffer, "ack");
+        write_str(&mut buffer, &"a".repeat(32));
+        assert_eq!(&buffer[..6], b"\xa3ack\xd9\x20");
+        assert_eq!(decode(&buffer), json!("ack"));
+    }
+}
@@ -0,0 +1,59 @@
+use serde_json::Value;
+
+/// Replaces strings holding a JSON object or array by the parsed value, also within the parsed
+/// values, returning whether any string was replaced.
+pub fn parse_nested(value: &mut Value) -> bool {
+    match value {
+        Value::String(string) => {
+            let trimmed = string.trim();
+            let bracketed = |open, close| trimmed.starts_with(open) && trimmed.ends_with(close);
+            if !bracketed('{', '}') && !bracketed('[', ']') {
+                return false;
+            }
+            match serde_json::from_str(trimmed) {
+                Ok(parsed) => {
+                    *value = parsed;
+                    parse_nested(value);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+        Value::Object(object) => object
+            .values_mut()
+            .fold(false, |changed, value| parse_nested(value) | changed),
+        Value::Array(array) => array
+            .iter_mut()
+            .fold(false, |changed, value| parse_nested(value) | changed),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_nested() {
+        let mut record = json!({
+            "payload": r#"{"a":1,"inner":"[1, {\"b\": true}]"}"#,
+            "list": [" {\"c\":null} "],
+            "msg": "{not json}",
+            "n": 1,
+        });
+        assert!(parse_nested(&mut record));
+        assert_eq!(
+            record,
+            json!({
+                "payload": {"a": 1, "inner": [1, {"b": true}]},
+                "list": [{"c": null}],
+                "msg": "{not json}",
+                "n": 1,
+            })
+        );
+        assert!(!parse_nested(
+            &mut json!({"msg": "[INFO] started", "n": "1"})
+        ));
+    }
+}
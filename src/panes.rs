@@ -0,0 +1,235 @@
+use crate::format::{Formatter, TokenKind};
+use serde_json::Value;
+use std::env;
+use std::io::{self, Write};
+use termcolor::Buffer;
+
+const DEFAULT_WIDTH: usize = 120;
+const SEPARATOR: &str = " │ ";
+const ASCII_SEPARATOR: &str = " | ";
+
+/// Writes the records of each group into its own column, side by side in the order they arrive.
+pub struct Panes {
+    field: String,
+    groups: Vec<String>,
+    width: usize,
+    formatter: Formatter<Buffer>,
+}
+
+/// Parses a pane layout like `by=service`, returning the field to group by.
+pub fn parse(layout: &str) -> Result<String, String> {
+    match layout.strip_prefix("by=") {
+        Some(field) if !field.is_empty() => Ok(field.to_string()),
+        _ => Err(format!("expected by=FIELD instead of `{}`", layout)),
+    }
+}
+
+impl Panes {
+    /// Creates panes as wide as given or as the terminal according to `COLUMNS`.
+    pub fn new(field: String, formatter: Formatter<Buffer>, width: Option<usize>) -> Self {
+        let width = width
+            .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH);
+        Panes {
+            field,
+            groups: Vec::new(),
+            width,
+            formatter,
+        }
+    }
+
+    /// Writes a record into the pane of its group, starting a new pane with a header for new groups.
+    pub fn write<W: Write>(
+        &mut self,
+        out: &mut W,
+        line: &[u8],
+        value: Option<&Value>,
+    ) -> io::Result<()> {
+        let group = match value.and_then(|value| value.get(&self.field)) {
+            Some(Value::String(group)) => group.clone(),
+            Some(group) => group.to_string(),
+            None => "-".to_string(),
+        };
+        let index = match self.groups.iter().position(|known| *known == group) {
+            Some(index) => index,
+            None => {
+                self.groups.push(group);
+                let headers: Vec<String> = self
+                    .groups
+                    .iter()
+                    .map(|group| format!("{} {}", self.formatter.symbol("━━", "=="), group))
+                    .collect();
+                let row: Vec<_> = (0..self.groups.len())
+                    .map(|index| Some(headers[index].as_str()))
+                    .collect();
+                self.write_row(out, &row, "")?;
+                self.groups.len() - 1
+            }
+        };
+        self.formatter.write_parsed_line(line, value)?;
+        let rendered =
+            String::from_utf8_lossy(self.formatter.writer.writer.as_slice()).into_owned();
+        self.formatter.writer.writer.clear();
+        self.formatter.writer.set_kind(TokenKind::Unknown);
+        let mut color = String::new();
+        for line in rendered.lines() {
+            let mut row = vec![None; self.groups.len()];
+            row[index] = Some(line);
+            color = self.write_row(out, &row, &color)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a prominent line across all panes.
+    pub fn write_banner<W: Write>(&mut self, out: &mut W, message: &str) -> io::Result<()> {
+        self.formatter.write_banner(message)?;
+        out.write_all(self.formatter.writer.writer.as_slice())?;
+        self.formatter.writer.writer.clear();
+        self.formatter.writer.set_kind(TokenKind::Unknown);
+        Ok(())
+    }
+
+    /// Writes the cells of a row, continuing the color active at the end of the previous line of a
+    /// cell and returning the one active at its end.
+    fn write_row<W: Write>(
+        &self,
+        out: &mut W,
+        cells: &[Option<&str>],
+        color: &str,
+    ) -> io::Result<String> {
+        let separator = self.formatter.symbol(SEPARATOR, ASCII_SEPARATOR);
+        let ellipsis = self.formatter.symbol("…", ".");
+        let count = cells.len().max(1);
+        let width = (self
+            .width
+            .saturating_sub(separator.chars().count() * (count - 1))
+            / count)
+            .max(1);
+        let mut active = String::new();
+        for (index, cell) in cells.iter().enumerate() {
+            if index != 0 {
+                out.write_all(separator.as_bytes())?;
+            }
+            let (fitted, color) = fit(color, cell.unwrap_or(""), width, ellipsis);
+            out.write_all(fitted.as_bytes())?;
+            if cell.is_some() {
+                active = color;
+            }
+        }
+        out.write_all(b"\n")?;
+        Ok(active)
+    }
+}
+
+/// Cuts or pads a line with ANSI colors to a number of visible characters, returning it with the
+/// color active at its end. The ellipsis marking a cut line is a single character.
+fn fit(color: &str, line: &str, width: usize, ellipsis: &str) -> (String, String) {
+    let tokens = tokens(line);
+    let length = tokens.iter().filter(|token| token.is_err()).count();
+    let keep = if length > width { width - 1 } else { length };
+    let mut fitted = color.to_string();
+    let mut active = color.to_string();
+    let mut visible = 0;
+    for token in tokens {
+        match token {
+            Ok(sequence) => {
+                match sequence {
+                    "\x1b[0m" => active.clear(),
+                    sequence => active.push_str(sequence),
+                }
+                fitted.push_str(sequence);
+            }
+            Err(c) if visible < keep => {
+                fitted.push(c);
+                visible += 1;
+            }
+            Err(_) => {}
+        }
+    }
+    if length > width {
+        fitted.push_str(ellipsis);
+        visible += 1;
+    }
+    if !active.is_empty() {
+        fitted.push_str("\x1b[0m");
+    }
+    fitted.push_str(&" ".repeat(width - visible));
+    (fitted, active)
+}
+
+/// Splits a line into escape sequences and visible characters.
+fn tokens(line: &str) -> Vec<Result<&str, char>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices();
+    while let Some((start, c)) = chars.next() {
+        if c != '\x1b' {
+            tokens.push(Err(c));
+            continue;
+        }
+        let end = chars
+            .find(|(_, c)| c.is_ascii_alphabetic())
+            .map_or(line.len(), |(end, _)| end + 1);
+        tokens.push(Ok(&line[start..end]));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("by=service"), Ok("service".to_string()));
+        assert!(parse("service").is_err());
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("", "abc", 5, "…"), ("abc  ".to_string(), String::new()));
+        assert_eq!(
+            fit("", "abcdef", 4, "…"),
+            ("abc…".to_string(), String::new())
+        );
+        assert_eq!(
+            fit("", "abcd\x1b[0m", 4, "…"),
+            ("abcd\x1b[0m".to_string(), String::new())
+        );
+        assert_eq!(
+            fit("\x1b[31m", "ab", 3, "…"),
+            ("\x1b[31mab\x1b[0m ".to_string(), "\x1b[31m".to_string())
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let mut panes = Panes::new(
+            "app".to_string(),
+            Formatter::new(Buffer::no_color()),
+            Some(23),
+        );
+        let mut out = Vec::new();
+        for line in [
+            r#"{"app":"web","msg":"a"}"#,
+            r#"{"app":"api","msg":"b"}"#,
+            r#"{"app":"web","msg":"c"}"#,
+        ] {
+            let value: Value = serde_json::from_str(line).unwrap();
+            panes
+                .write(&mut out, line.as_bytes(), Some(&value))
+                .unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            [
+                "━━ web                 ",
+                "app: web msg: a        ",
+                "━━ web     │ ━━ api    ",
+                "           │ app: api …",
+                "app: web … │           ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
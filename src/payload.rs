@@ -0,0 +1,354 @@
+/// Languages of payloads embedded in string values which are laid out over multiple lines.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Language {
+    Sql,
+    Xml,
+    /// A URL query string like `a=1&b=x%20y`, alone or after a path.
+    Query,
+}
+
+/// Kinds of the parts of a laid out payload, colored like the JSON tokens of the same role.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Token {
+    Plain,
+    /// SQL keywords, XML tags and query string keys.
+    Keyword,
+    /// Quoted SQL strings, XML text and query string values.
+    Literal,
+    Number,
+}
+
+const STATEMENTS: [&str; 8] = [
+    "SELECT", "INSERT", "UPDATE", "DELETE", "WITH", "CREATE", "ALTER", "DROP",
+];
+
+/// Keywords showing that a statement keyword starts SQL rather than prose.
+const OBJECTS: [&str; 7] = ["FROM", "INTO", "SET", "TABLE", "INDEX", "VIEW", "AS"];
+
+/// Keywords starting a clause on a new line.
+const CLAUSES: [&str; 19] = [
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "GROUP",
+    "ORDER",
+    "HAVING",
+    "LIMIT",
+    "OFFSET",
+    "UNION",
+    "VALUES",
+    "SET",
+    "RETURNING",
+    "JOIN",
+    "LEFT",
+    "RIGHT",
+    "INNER",
+    "FULL",
+    "CROSS",
+    "NATURAL",
+];
+
+const KEYWORDS: [&str; 38] = [
+    "ALL", "ALTER", "AND", "AS", "ASC", "BETWEEN", "BY", "CASE", "CREATE", "DELETE", "DESC",
+    "DISTINCT", "DROP", "ELSE", "END", "EXISTS", "IN", "INSERT", "INTO", "IS", "LIKE", "NOT",
+    "NULL", "ON", "OR", "OUTER", "TABLE", "THEN", "UPDATE", "WHEN", "WITH", "COUNT", "SUM", "MIN",
+    "MAX", "AVG", "INDEX", "VIEW",
+];
+
+/// Returns the language of a string holding a payload, if it is one.
+pub fn detect(text: &str) -> Option<Language> {
+    let text = text.trim();
+    let mut words = text.split_whitespace().map(str::to_uppercase);
+    let statement = words.next()?;
+    if STATEMENTS.contains(&statement.as_str())
+        && words.any(|word| OBJECTS.contains(&word.as_str()))
+    {
+        return Some(Language::Sql);
+    }
+    if text.starts_with('<')
+        && text.ends_with('>')
+        && (text.contains("</") || text.contains("/>"))
+        && text[1..].starts_with(|c: char| c.is_alphabetic() || c == '?' || c == '!')
+    {
+        return Some(Language::Xml);
+    }
+    if !text.contains(char::is_whitespace) {
+        let (path, query) = match text.split_once('?') {
+            Some((path, query)) => (Some(path), query),
+            None => (None, text),
+        };
+        let pairs: Vec<&str> = query.split('&').collect();
+        let valid = pairs
+            .iter()
+            .all(|pair| pair.split_once('=').is_some_and(|(key, _)| !key.is_empty()));
+        if valid && (path.is_some() || pairs.len() > 1) {
+            return Some(Language::Query);
+        }
+    }
+    None
+}
+
+/// Lays out a payload over lines, with nested parts indented by two spaces.
+pub fn layout(text: &str, language: Language) -> Vec<(Token, String)> {
+    match language {
+        Language::Sql => layout_sql(text.trim()),
+        Language::Xml => layout_xml(text.trim()),
+        Language::Query => layout_query(text.trim()),
+    }
+}
+
+/// Starts clauses on new lines and conditions joined by `AND` or `OR` on indented ones, outside of
+/// parentheses.
+fn layout_sql(text: &str) -> Vec<(Token, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let (mut depth, mut space, mut between) = (0usize, false, false);
+    let mut previous = String::new();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        let token = if c == '\'' || c == '"' || c == '`' {
+            let mut escaped = false;
+            for (index, next) in chars.by_ref() {
+                end = index + next.len_utf8();
+                if next == c && !escaped {
+                    break;
+                }
+                escaped = !escaped && next == '\\';
+            }
+            match c {
+                '\'' => Token::Literal,
+                _ => Token::Plain,
+            }
+        } else if c.is_alphanumeric() || c == '_' || c == '.' {
+            while let Some((index, next)) = chars.peek().copied() {
+                if !(next.is_alphanumeric() || next == '_' || next == '.') {
+                    break;
+                }
+                end = index + next.len_utf8();
+                chars.next();
+            }
+            Token::Plain
+        } else {
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            Token::Plain
+        };
+        let part = &text[start..end];
+        let word = part.to_uppercase();
+        let token = match token {
+            Token::Plain if KEYWORDS.contains(&word.as_str()) => Token::Keyword,
+            Token::Plain if CLAUSES.contains(&word.as_str()) => Token::Keyword,
+            Token::Plain if c.is_ascii_digit() && part.parse::<f64>().is_ok() => Token::Number,
+            token => token,
+        };
+        let joined = matches!(
+            previous.as_str(),
+            "LEFT" | "RIGHT" | "INNER" | "OUTER" | "FULL" | "CROSS" | "NATURAL"
+        );
+        let separator = if tokens.is_empty() {
+            ""
+        } else if depth == 0 && CLAUSES.contains(&word.as_str()) && !joined {
+            "\n"
+        } else if depth == 0 && (word == "OR" || (word == "AND" && !between)) {
+            "\n  "
+        } else if space && c != ',' && c != ')' && !previous.ends_with('(') {
+            " "
+        } else {
+            ""
+        };
+        if word == "BETWEEN" {
+            between = true;
+        } else if word == "AND" {
+            between = false;
+        }
+        if !separator.is_empty() {
+            tokens.push((Token::Plain, separator.to_string()));
+        }
+        tokens.push((token, part.to_string()));
+        previous = word;
+        space = false;
+    }
+    tokens
+}
+
+/// Writes each element on a line of its own indented by its depth, and elements only holding text
+/// on one line.
+fn layout_xml(text: &str) -> Vec<(Token, String)> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let terminator = match rest {
+                rest if rest.starts_with("<!--") => "-->",
+                rest if rest.starts_with("<![CDATA[") => "]]>",
+                _ => ">",
+            };
+            let end = rest
+                .find(terminator)
+                .map_or(rest.len(), |end| end + terminator.len());
+            parts.push(&rest[..end]);
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            if !rest[..end].trim().is_empty() {
+                parts.push(rest[..end].trim());
+            }
+            rest = &rest[end..];
+        }
+    }
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut index = 0;
+    while index < parts.len() {
+        let part = parts[index];
+        let closing = part.starts_with("</");
+        let opening = !closing
+            && part.starts_with('<')
+            && !part.starts_with("<?")
+            && !part.starts_with("<!")
+            && !part.ends_with("/>");
+        if closing {
+            depth = depth.saturating_sub(1);
+        }
+        if !tokens.is_empty() {
+            tokens.push((Token::Plain, format!("\n{}", "  ".repeat(depth))));
+        }
+        let kind = match part.starts_with('<') {
+            true if part.starts_with("<!--") => Token::Plain,
+            true => Token::Keyword,
+            false => Token::Literal,
+        };
+        tokens.push((kind, part.to_string()));
+        // An element only holding text stays on one line.
+        let text = parts.get(index + 1).filter(|text| !text.starts_with('<'));
+        let closed = parts
+            .get(index + 2)
+            .is_some_and(|part| part.starts_with("</"));
+        match (opening, text) {
+            (true, Some(text)) if closed => {
+                tokens.push((Token::Literal, text.to_string()));
+                tokens.push((Token::Keyword, parts[index + 2].to_string()));
+                index += 3;
+                continue;
+            }
+            (true, _) => depth += 1,
+            _ => {}
+        }
+        index += 1;
+    }
+    tokens
+}
+
+/// Writes the path if any, and each parameter on a line of its own with its value decoded.
+fn layout_query(text: &str) -> Vec<(Token, String)> {
+    let mut tokens = Vec::new();
+    let (indent, query) = match text.split_once('?') {
+        Some((path, query)) => {
+            tokens.push((Token::Plain, path.to_string()));
+            ("\n  ", query)
+        }
+        None => ("\n", text),
+    };
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if !tokens.is_empty() {
+            tokens.push((Token::Plain, indent.to_string()));
+        }
+        tokens.push((Token::Keyword, decode(key)));
+        tokens.push((Token::Plain, ": ".to_string()));
+        tokens.push((Token::Literal, decode(value)));
+    }
+    tokens
+}
+
+/// Decodes `+` and percent escapes, keeping invalid escapes as they are.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[index], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+            (b'+', None) => decoded.push(b' '),
+            (byte, None) => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn laid_out(text: &str) -> String {
+        let language = detect(text).unwrap();
+        layout(text, language)
+            .into_iter()
+            .map(|(_, part)| part)
+            .collect()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("select id from users"), Some(Language::Sql));
+        assert_eq!(detect("Select an option"), None);
+        assert_eq!(detect("<a><b>x</b></a>"), Some(Language::Xml));
+        assert_eq!(detect("<not xml>"), None);
+        assert_eq!(detect("/search?q=a+b"), Some(Language::Query));
+        assert_eq!(detect("a=1&b=2"), Some(Language::Query));
+        assert_eq!(detect("a=1"), None);
+        assert_eq!(detect("x & y"), None);
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(
+            laid_out(
+                "SELECT u.id, count(*) FROM users u LEFT JOIN orders o ON o.user_id = u.id \
+                 WHERE u.age BETWEEN 18 AND 30 AND u.name = 'a b' GROUP BY u.id LIMIT 10"
+            ),
+            [
+                "SELECT u.id, count(*)",
+                "FROM users u",
+                "LEFT JOIN orders o ON o.user_id = u.id",
+                "WHERE u.age BETWEEN 18 AND 30",
+                "  AND u.name = 'a b'",
+                "GROUP BY u.id",
+                "LIMIT 10",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            laid_out(r#"<?xml version="1.0"?><a id="1"><b>x y</b><c/><!-- c --></a>"#),
+            [
+                r#"<?xml version="1.0"?>"#,
+                r#"<a id="1">"#,
+                "  <b>x y</b>",
+                "  <c/>",
+                "  <!-- c -->",
+                "</a>",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            laid_out("/search?q=a+b%21&page=2&bad=%zz"),
+            "/search\n  q: a b!\n  page: 2\n  bad: %zz"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{duration, timestamp};
+use chrono::SecondsFormat;
+use clap::Args;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+const SAMPLE_LINES: usize = 1000;
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Prints the first and last records of a file with its record count and time range, without reading it all
+#[derive(Args, Debug)]
+pub struct PeekOpt {
+    file: PathBuf,
+}
+
+pub fn run(opt: &PeekOpt) -> io::Result<()> {
+    let mut file = File::open(&opt.file)?;
+    let size = file.metadata()?.len();
+    let head = read_head(BufReader::new(&mut file))?;
+    let last = read_last(&mut file, size)?;
+    let mut summary = Map::new();
+    match head.complete {
+        true => summary.insert("records".to_string(), head.records.into()),
+        false => summary.insert(
+            "estimated_records".to_string(),
+            (size * head.records / head.bytes.max(1)).into(),
+        ),
+    };
+    let first_time = head.first.as_ref().and_then(timestamp::detect);
+    let last_time = last.as_ref().and_then(timestamp::detect);
+    if let (Some(first), Some(last)) = (first_time, last_time) {
+        summary.insert(
+            "from".to_string(),
+            first.to_rfc3339_opts(SecondsFormat::Millis, true).into(),
+        );
+        summary.insert(
+            "to".to_string(),
+            last.to_rfc3339_opts(SecondsFormat::Millis, true).into(),
+        );
+        if let Ok(span) = (last - first).to_std() {
+            summary.insert("span".to_string(), duration::format(span).into());
+        }
+    }
+    let lines = vec![head.first, last, Some(summary)];
+    crate::print_lines(
+        lines
+            .into_iter()
+            .flatten()
+            .map(|object| Ok(Value::Object(object).to_string())),
+    )
+}
+
+/// The first record and the records counted in the first lines.
+struct Head {
+    first: Option<Map<String, Value>>,
+    records: u64,
+    bytes: u64,
+    /// Whether the sample covered the whole file.
+    complete: bool,
+}
+
+fn read_head<R: BufRead>(mut reader: R) -> io::Result<Head> {
+    let mut head = Head {
+        first: None,
+        records: 0,
+        bytes: 0,
+        complete: false,
+    };
+    let mut line = Vec::new();
+    for _ in 0..SAMPLE_LINES {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            head.complete = true;
+            return Ok(head);
+        }
+        head.bytes += read as u64;
+        if let Ok(Value::Object(object)) = serde_json::from_slice(&line) {
+            head.records += 1;
+            head.first.get_or_insert(object);
+        }
+    }
+    head.complete = reader.fill_buf()?.is_empty();
+    Ok(head)
+}
+
+/// Reads chunks backwards from the end until a complete line holds a record.
+fn read_last<R: Read + Seek>(reader: &mut R, size: u64) -> io::Result<Option<Map<String, Value>>> {
+    let mut end = size;
+    let mut buffer = Vec::new();
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_SIZE);
+        let mut chunk = vec![0; (end - start) as usize];
+        reader.seek(SeekFrom::Start(start))?;
+        reader.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+        end = start;
+        let mut lines = buffer.split(|byte| *byte == b'\n').collect::<Vec<_>>();
+        if start > 0 {
+            lines.remove(0);
+        }
+        for line in lines.into_iter().rev() {
+            if let Ok(Value::Object(object)) = serde_json::from_slice(line) {
+                return Ok(Some(object));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_head() {
+        let head = read_head(Cursor::new("text\n{\"a\":1}\n{\"a\":2}\n")).unwrap();
+        assert_eq!(head.first.unwrap()["a"], 1);
+        assert_eq!((head.records, head.bytes, head.complete), (2, 21, true));
+        let lines = "{}\n".repeat(SAMPLE_LINES + 1);
+        let head = read_head(Cursor::new(lines)).unwrap();
+        assert_eq!((head.records, head.complete), (SAMPLE_LINES as u64, false));
+    }
+
+    #[test]
+    fn test_last() {
+        let mut lines = "{\"a\":1}\n".repeat(20000);
+        lines.push_str("{\"a\":2}\n\ntext");
+        let size = lines.len() as u64;
+        let last = read_last(&mut Cursor::new(lines), size).unwrap();
+        assert_eq!(last.unwrap()["a"], 2);
+        assert_eq!(read_last(&mut Cursor::new("text"), 4).unwrap(), None);
+    }
+}
@@ -0,0 +1,150 @@
+use clap::Args;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Picks records in the fuzzy finder fzf, writing the selected ones unchanged
+#[derive(Args, Debug)]
+pub struct PickOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+    /// Line shown for each record with field placeholders like `{ts} {level} {msg}`, the record itself by default
+    #[clap(long, value_name = "TEMPLATE", parse(try_from_str = parse_template))]
+    template: Option<String>,
+    /// Allows selecting several records with Tab
+    #[clap(short, long)]
+    multi: bool,
+}
+
+pub fn run(opt: &PickOpt) -> io::Result<()> {
+    let mut command = Command::new("fzf");
+    command.args(["--delimiter", "\t", "--with-nth", "2..", "--no-sort"]);
+    if opt.multi {
+        command.arg("--multi");
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => {
+                io::Error::new(err.kind(), "ndjson pick requires fzf to be installed")
+            }
+            _ => err,
+        })?;
+    // The records are streamed to fzf, so they can be picked before the input ends.
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let mut fzf = child.stdin.take().unwrap();
+    let (files, template, shared) = (opt.files.clone(), opt.template.clone(), records.clone());
+    thread::spawn(move || {
+        let mut send = |reader: &mut dyn BufRead| -> io::Result<()> {
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let mut records = shared.lock().unwrap();
+                writeln!(
+                    fzf,
+                    "{}\t{}",
+                    records.len(),
+                    display(template.as_deref(), &line)
+                )?;
+                records.push(line);
+            }
+            Ok(())
+        };
+        if files.is_empty() {
+            let _ = send(&mut io::stdin().lock());
+        }
+        for path in files {
+            let sent = File::open(&path).and_then(|file| send(&mut BufReader::new(file)));
+            if let Err(err) = sent {
+                eprintln!("ndjson: can't read {}: {}", path.display(), err);
+                return;
+            }
+        }
+    });
+    let mut selected = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut selected)?;
+    // fzf exits with 1 without a match and 130 when cancelled, which both select nothing.
+    child.wait()?;
+    let records = records.lock().unwrap();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for index in selected
+        .lines()
+        .filter_map(|line| line.split('\t').next()?.parse::<usize>().ok())
+    {
+        if let Some(record) = records.get(index) {
+            writeln!(stdout, "{}", record)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_template(template: &str) -> Result<String, String> {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed `{{` in `{}`", template))?;
+        rest = &rest[open + close + 1..];
+    }
+    Ok(template.to_string())
+}
+
+/// Returns the line shown for a record on one line, rendered from the template if it is an object.
+fn display(template: Option<&str>, line: &str) -> String {
+    let display = match (template, serde_json::from_str(line)) {
+        (Some(template), Ok(Value::Object(object))) => render(template, &object),
+        _ => line.to_string(),
+    };
+    display.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Replaces the placeholders of top-level or dotted nested fields like `{http.status}` by their
+/// values, missing ones by nothing.
+fn render(template: &str, object: &Map<String, Value>) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+        rendered.push_str(&rest[..open]);
+        let mut path = rest[open + 1..close].split('.');
+        let value = path.next().and_then(|key| object.get(key));
+        let value = path.fold(value, |value, key| value?.get(key));
+        match value {
+            Some(Value::String(string)) => rendered.push_str(string),
+            Some(Value::Null) | None => {}
+            Some(value) => rendered.push_str(&value.to_string()),
+        }
+        rest = &rest[close + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let line = r#"{"level":"warn","msg":"disk\tfull","http":{"status":507}}"#;
+        assert_eq!(
+            display(Some("[{level}] {msg} {http.status}{missing}"), line),
+            "[warn] disk full 507"
+        );
+        assert_eq!(display(None, line), line);
+        assert_eq!(display(Some("{msg}"), "plain text"), "plain text");
+        assert!(parse_template("{level} {msg").is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use crate::fields::{self, Pattern};
+use crate::level::Reclassifier;
+use crate::predicate::Predicate;
+use crate::{
+    alert, anonymize, config::Config, dedup, fold, geoip, limits, logfmt, nested, query, relaxed,
+    route, sample, secrets, session, summary, suppress, syslog, useragent, xml, Opt, OutputFormat,
+};
+use serde_json::{Map, Value};
+use std::io;
+use std::time::Instant;
+
+/// Outcome of passing a line through the pipeline.
+pub enum Processed {
+    /// The line is written as is, with its parsed JSON value if any.
+    Unchanged(Option<Value>),
+    /// The line is replaced by the transformed record.
+    Changed(Value),
+    /// The line is filtered out.
+    Dropped,
+}
+
+/// Filters and transforms applied to JSON objects before writing them.
+pub struct Pipeline {
+    limits: limits::Limits,
+    /// Records replaced by a summary for exceeding the limits.
+    limited: u64,
+    relaxed: bool,
+    syslog: bool,
+    logfmt: bool,
+    /// Whether the last line was JSON, which is routed as read rather than serialized.
+    json: bool,
+    parse_nested: bool,
+    parse_xml: Vec<String>,
+    reclassifier: Option<Reclassifier>,
+    conditions: Vec<Predicate>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    dedup: Option<dedup::Deduplicator>,
+    sample_by: Option<sample::SampleBy>,
+    suppressor: Option<suppress::RepeatSuppressor>,
+    /// Field whose values are checked for repeats, with the recently seen ones.
+    unique: Option<(String, dedup::Deduplicator)>,
+    geoip: Option<geoip::GeoIp>,
+    user_agent: Option<useragent::UserAgentParser>,
+    anonymizer: Option<anonymize::IpAnonymizer>,
+    secrets: Option<secrets::SecretScanner>,
+    redact_secrets: bool,
+    redact: Vec<Pattern>,
+    folder: Option<fold::Folder>,
+    alerts: Vec<alert::Alert>,
+    fired: Vec<String>,
+    summary: Option<summary::Summary>,
+    router: Option<route::Router>,
+    query: Option<query::Query>,
+    session: Option<session::SessionLog>,
+    output: OutputFormat,
+}
+
+impl Pipeline {
+    pub fn new(opt: &Opt, config: &Config) -> io::Result<Self> {
+        Ok(Pipeline {
+            limits: opt.limits,
+            limited: 0,
+            relaxed: opt.relaxed,
+            syslog: opt.syslog,
+            logfmt: opt.logfmt,
+            json: false,
+            parse_nested: opt.parse_nested,
+            parse_xml: opt.parse_xml.clone(),
+            reclassifier: (!config.reclassify.is_empty())
+                .then(|| Reclassifier::new(opt.level_field.clone(), config.reclassify.clone())),
+            conditions: opt.conditions.clone(),
+            min_size: opt.min_size,
+            max_size: opt.max_size,
+            dedup: opt.dedup.then(dedup::Deduplicator::default),
+            sample_by: opt.sample_by.clone(),
+            suppressor: opt
+                .suppress_repeats
+                .map(|window| suppress::RepeatSuppressor::new(window, opt.volatile_fields.clone())),
+            unique: opt
+                .check_unique
+                .clone()
+                .map(|field| (field, dedup::Deduplicator::default())),
+            geoip: opt.geoip.as_deref().map(geoip::GeoIp::open).transpose()?,
+            user_agent: opt.parse_ua.as_deref().map(useragent::UserAgentParser::new),
+            anonymizer: opt
+                .anonymize_ip
+                .as_deref()
+                .map(anonymize::IpAnonymizer::new),
+            secrets: (opt.detect_secrets || opt.redact_secrets)
+                .then(secrets::SecretScanner::default),
+            redact_secrets: opt.redact_secrets,
+            redact: opt.redact.clone(),
+            folder: match (&opt.fold_on, &opt.unfold_on) {
+                (Some(start), Some(end)) => Some(fold::Folder::new(start.clone(), end.clone())),
+                _ => None,
+            },
+            alerts: opt.alert.clone(),
+            fired: Vec::new(),
+            summary: opt
+                .summary
+                .then(|| summary::Summary::new(opt.output, opt.ascii)),
+            router: match opt.route.is_empty() && opt.output_file.is_empty() {
+                true => None,
+                false => Some(route::Router::open(
+                    &[&opt.output_file[..], &opt.route[..]].concat(),
+                )?),
+            },
+            query: opt.query.clone(),
+            session: opt
+                .session_log
+                .clone()
+                .map(|path| session::SessionLog::new(path, sources(opt))),
+            output: opt.output,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.relaxed
+            && !self.syslog
+            && !self.logfmt
+            && !self.parse_nested
+            && self.parse_xml.is_empty()
+            && self.reclassifier.is_none()
+            && self.conditions.is_empty()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.dedup.is_none()
+            && self.sample_by.is_none()
+            && self.suppressor.is_none()
+            && self.unique.is_none()
+            && self.geoip.is_none()
+            && self.user_agent.is_none()
+            && self.anonymizer.is_none()
+            && self.secrets.is_none()
+            && self.redact.is_empty()
+            && self.folder.is_none()
+            && self.alerts.is_empty()
+            && self.summary.is_none()
+            && self.router.is_none()
+            && self.query.is_none()
+            && self.session.is_none()
+    }
+
+    /// Returns the conditions which alone decide whether records are dropped, so blocks of records
+    /// an index shows can't match them may be skipped unread. There are none if records failing
+    /// them still count, like in the summary, or may be changed before they are checked.
+    pub fn skippable_conditions(&self) -> Vec<Predicate> {
+        let limits = self.limits.is_off() || self.limits == limits::Limits::default();
+        let counted = self.summary.is_some() || self.session.is_some();
+        let changed =
+            self.parse_nested || !self.parse_xml.is_empty() || self.reclassifier.is_some();
+        match limits && !counted && !changed {
+            true => self.conditions.clone(),
+            false => Vec::new(),
+        }
+    }
+
+    /// Processes a line without its terminator, which is only parsed if it is valid UTF-8.
+    pub fn process(&mut self, line: &[u8]) -> Processed {
+        let processed = self.process_record(line);
+        if let Some(router) = &mut self.router {
+            match &processed {
+                Processed::Unchanged(Some(Value::Object(object))) if self.json => {
+                    router.route(object, line)
+                }
+                Processed::Unchanged(Some(Value::Object(object)))
+                | Processed::Changed(Value::Object(object)) => {
+                    router.route(object, Value::Object(object.clone()).to_string().as_bytes())
+                }
+                _ => {}
+            }
+        }
+        if let Some(session) = &mut self.session {
+            session.count(&processed);
+        }
+        // Records are routed whole, only the output is narrowed to the query.
+        match (&self.query, processed) {
+            (Some(query), Processed::Unchanged(Some(value)) | Processed::Changed(value)) => {
+                Processed::Changed(query.evaluate(&value))
+            }
+            (_, processed) => processed,
+        }
+    }
+
+    fn process_record(&mut self, line: &[u8]) -> Processed {
+        if let Some(exceeded) = self.limits.check(line) {
+            self.limited += 1;
+            return Processed::Changed(limits::summary(line, exceeded));
+        }
+        let mut value = serde_json::from_slice(line).ok();
+        self.json = value.is_some();
+        // Lines only parsed leniently are rewritten as JSON.
+        let mut changed = false;
+        if value.is_none() && self.relaxed {
+            value = relaxed::parse(line);
+            changed = value.is_some();
+        }
+        if value.is_none() && self.syslog {
+            value = syslog::parse(line).map(Value::Object);
+            changed = value.is_some();
+        }
+        // logfmt lines are kept as they are, only their fields are treated like those of JSON.
+        if value.is_none() && self.logfmt {
+            value = logfmt::parse(line).map(Value::Object);
+        }
+        let mut object = match value {
+            Some(Value::Object(object)) => object,
+            value => {
+                if let (Some(summary), None) = (&mut self.summary, &value) {
+                    if !line.iter().all(u8::is_ascii_whitespace) {
+                        summary.add_unparsed();
+                    }
+                }
+                return match (changed, value) {
+                    (true, Some(value)) => Processed::Changed(value),
+                    (_, value) => Processed::Unchanged(value),
+                };
+            }
+        };
+        if self.parse_nested {
+            for value in object.values_mut() {
+                changed |= nested::parse_nested(value);
+            }
+        }
+        for field in &self.parse_xml {
+            changed |= xml::parse_field(&mut object, field);
+        }
+        if let Some(reclassifier) = &self.reclassifier {
+            changed |= reclassifier.reclassify(&mut object);
+        }
+        if let Some(summary) = &mut self.summary {
+            summary.add_record(&object);
+        }
+        if !self
+            .conditions
+            .iter()
+            .all(|condition| condition.matches(&object))
+        {
+            return Processed::Dropped;
+        }
+        let size = line.len() as u64;
+        if self.min_size.is_some_and(|min| size < min)
+            || self.max_size.is_some_and(|max| size > max)
+        {
+            return Processed::Dropped;
+        }
+        if let Some(dedup) = &mut self.dedup {
+            if dedup.is_duplicate(line) {
+                return Processed::Dropped;
+            }
+        }
+        let now = Instant::now();
+        if let Some(suppressor) = &mut self.suppressor {
+            self.fired.extend(suppressor.expire(now));
+            if suppressor.is_repeat(&object, now) {
+                return Processed::Dropped;
+            }
+        }
+        if let Some(sample_by) = &mut self.sample_by {
+            if sample_by.is_over_limit(&object, now) {
+                return Processed::Dropped;
+            }
+        }
+        if let Some((field, seen)) = &mut self.unique {
+            if let Some(value) = object.get(field) {
+                let value = value.to_string();
+                if seen.is_duplicate(value.as_bytes()) {
+                    eprintln!(
+                        "ndjson: {} {} appears in more than one record",
+                        field, value
+                    );
+                }
+            }
+        }
+        for alert in &mut self.alerts {
+            self.fired.extend(alert.observe(&object, now));
+        }
+        if let Some(geoip) = &self.geoip {
+            changed |= geoip.annotate(&mut object);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            changed |= user_agent.annotate(&mut object);
+        }
+        if let Some(anonymizer) = &self.anonymizer {
+            changed |= anonymizer.anonymize(&mut object);
+        }
+        if let Some(secrets) = &self.secrets {
+            if secrets.warn(&object) && self.redact_secrets {
+                changed |= secrets.redact(&mut object);
+            }
+        }
+        if !self.redact.is_empty() {
+            changed |= fields::redact(&mut object, &self.redact);
+        }
+        if let Some(folder) = &mut self.folder {
+            object = match folder.fold(object) {
+                fold::Folded::Outside(object) => object,
+                fold::Folded::Hidden => return Processed::Dropped,
+                fold::Folded::Summary(summary) => {
+                    return Processed::Changed(Value::Object(summary))
+                }
+            };
+        }
+        if changed {
+            Processed::Changed(Value::Object(object))
+        } else {
+            Processed::Unchanged(Some(Value::Object(object)))
+        }
+    }
+
+    /// Returns the summary of a transaction which was still open when the input ended.
+    pub fn flush(&mut self) -> Option<Value> {
+        self.folder
+            .as_mut()
+            .and_then(fold::Folder::finish)
+            .map(Value::Object)
+    }
+
+    /// Returns the messages of the alerts which started since the last call.
+    pub fn take_alerts(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.fired)
+    }
+
+    /// Reports the totals after the input ended or was interrupted.
+    pub fn finish(&self, interrupted: bool) -> io::Result<()> {
+        if self.limited > 0 {
+            match self.output {
+                OutputFormat::Text => eprintln!(
+                    "ndjson: summarized {} records exceeding the --limits",
+                    self.limited
+                ),
+                OutputFormat::Json => eprintln!(r#"{{"records_over_limits":{}}}"#, self.limited),
+            }
+        }
+        if let Some(dedup) = &self.dedup {
+            match self.output {
+                OutputFormat::Text => {
+                    eprintln!("ndjson: removed {} duplicate records", dedup.removed())
+                }
+                OutputFormat::Json => eprintln!(r#"{{"duplicates_removed":{}}}"#, dedup.removed()),
+            }
+        }
+        if let Some(suppressor) = &self.suppressor {
+            match self.output {
+                OutputFormat::Text => eprintln!(
+                    "ndjson: suppressed {} repeated records",
+                    suppressor.suppressed()
+                ),
+                OutputFormat::Json => {
+                    eprintln!(r#"{{"repeats_suppressed":{}}}"#, suppressor.suppressed())
+                }
+            }
+        }
+        if let Some(sample_by) = &self.sample_by {
+            match self.output {
+                OutputFormat::Text => eprintln!(
+                    "ndjson: dropped {} records over the limit per {}",
+                    sample_by.dropped(),
+                    sample_by.field()
+                ),
+                OutputFormat::Json => eprintln!(
+                    "{}",
+                    serde_json::json!({"field": sample_by.field(), "sampled_out": sample_by.dropped()})
+                ),
+            }
+        }
+        if let Some((field, seen)) = &self.unique {
+            match self.output {
+                OutputFormat::Text => {
+                    eprintln!(
+                        "ndjson: found {} repeated values of {}",
+                        seen.removed(),
+                        field
+                    )
+                }
+                OutputFormat::Json => eprintln!(
+                    "{}",
+                    serde_json::json!({"field": field, "repeated_values": seen.removed()})
+                ),
+            }
+        }
+        if let Some(summary) = &self.summary {
+            summary.report(&mut io::stderr())?;
+        }
+        if let Some(session) = &self.session {
+            session.write(interrupted)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the names of the inputs, the files or stdin.
+fn sources(opt: &Opt) -> Vec<String> {
+    match opt.files.is_empty() {
+        true => vec!["stdin".to_string()],
+        false => opt
+            .files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+    }
+}
+
+/// Inserts fields directly after an existing key, or at the end if it is missing.
+pub fn insert_after(object: &mut Map<String, Value>, key: &str, fields: Vec<(String, Value)>) {
+    if fields.is_empty() {
+        return;
+    }
+    let mut fields = Some(fields);
+    let mut inserted = Map::new();
+    for (existing_key, value) in std::mem::take(object) {
+        let found = existing_key == key;
+        inserted.insert(existing_key, value);
+        if found {
+            inserted.extend(fields.take().into_iter().flatten());
+        }
+    }
+    inserted.extend(fields.into_iter().flatten());
+    *object = inserted;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    #[test]
+    fn test_insert_after() {
+        let mut object = serde_json::json!({"a": 1, "b": 2})
+            .as_object()
+            .unwrap()
+            .clone();
+        insert_after(&mut object, "a", vec![("c".to_string(), Value::Null)]);
+        insert_after(&mut object, "d", vec![("e".to_string(), Value::Null)]);
+        assert_eq!(
+            Value::Object(object).to_string(),
+            r#"{"a":1,"c":null,"b":2,"e":null}"#
+        );
+    }
+
+    #[test]
+    fn test_route_logfmt() {
+        let path = std::env::temp_dir().join(format!("ndjson-logfmt-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let opt = Opt::parse_from(["ndjson", "--output-file", path.to_str().unwrap()]);
+        let mut pipeline = Pipeline::new(&opt, &Config::default()).unwrap();
+        assert!(matches!(
+            pipeline.process(b"level=info msg=a"),
+            Processed::Unchanged(None)
+        ));
+        let opt = Opt::parse_from([
+            "ndjson",
+            "--logfmt",
+            "--output-file",
+            path.to_str().unwrap(),
+        ]);
+        let mut pipeline = Pipeline::new(&opt, &Config::default()).unwrap();
+        pipeline.process(b"level=info msg=\"b c\"");
+        pipeline.process(b"{\"msg\": \"d\"}");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"level\":\"info\",\"msg\":\"b c\"}\n{\"msg\": \"d\"}\n"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_delimited_options() {
+        let opt = Opt::parse_from(["ndjson", "--include", "a,b", "f.log"]);
+        assert_eq!(opt.include.len(), 2);
+        assert_eq!(opt.files, [std::path::PathBuf::from("f.log")]);
+        let opt = Opt::parse_from([
+            "ndjson", "--first", "msg", "--first", "ts", "--last", "ts", "s.ndjson",
+        ]);
+        assert_eq!(opt.first, ["msg", "ts"]);
+        assert_eq!(opt.last, ["ts"]);
+        assert_eq!(opt.files, [std::path::PathBuf::from("s.ndjson")]);
+    }
+}
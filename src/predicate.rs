@@ -0,0 +1,228 @@
+use crate::level::Level;
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/// A comparison of a field against a value, like `level=="error"` or `status>=500`. Levels are
+/// ordered by severity, like `level>=warn`. Text can be searched with `msg~timeout` and matched
+/// with `msg=~^GET`.
+#[derive(Clone, Debug)]
+pub struct Predicate {
+    path: Vec<String>,
+    operator: Operator,
+    value: Value,
+    regex: Option<Regex>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    /// A substring of text, or an element of an array.
+    Contains,
+    Matches,
+}
+
+const OPERATORS: [(&str, Operator); 9] = [
+    ("==", Operator::Equal),
+    ("=~", Operator::Matches),
+    ("!=", Operator::NotEqual),
+    (">=", Operator::GreaterEqual),
+    ("<=", Operator::LessEqual),
+    ("=", Operator::Equal),
+    (">", Operator::Greater),
+    ("<", Operator::Less),
+    ("~", Operator::Contains),
+];
+
+impl Predicate {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let (index, token, operator) = OPERATORS
+            .iter()
+            .filter_map(|(token, operator)| {
+                expression
+                    .find(token)
+                    .map(|index| (index, *token, *operator))
+            })
+            .min_by_key(|(index, token, _)| (*index, usize::MAX - token.len()))
+            .ok_or_else(|| format!("missing comparison operator in `{}`", expression))?;
+        let field = expression[..index].trim();
+        if field.is_empty() {
+            return Err(format!("missing field in `{}`", expression));
+        }
+        let value = expression[index + token.len()..].trim();
+        let regex = match operator {
+            Operator::Matches => Some(
+                Regex::new(value).map_err(|err| format!("invalid regex `{}`: {}", value, err))?,
+            ),
+            _ => None,
+        };
+        Ok(Predicate {
+            path: field.split('.').map(str::to_string).collect(),
+            operator,
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string())),
+            regex,
+        })
+    }
+
+    pub fn matches(&self, object: &Map<String, Value>) -> bool {
+        let mut path = self.path.iter();
+        let mut value = match path.next().and_then(|key| object.get(key)) {
+            Some(value) => value,
+            None => return self.operator == Operator::NotEqual,
+        };
+        for key in path {
+            value = match value.get(key) {
+                Some(value) => value,
+                None => return self.operator == Operator::NotEqual,
+            };
+        }
+        match (self.operator, &self.regex, value) {
+            (Operator::Matches, Some(regex), _) => return regex.is_match(&as_text(value)),
+            (Operator::Contains, _, Value::Array(array)) => {
+                return array
+                    .iter()
+                    .any(|element| compare(element, &self.value) == Some(Ordering::Equal))
+            }
+            (Operator::Contains, _, _) => {
+                return as_text(value).contains(&as_text(&self.value));
+            }
+            _ => {}
+        }
+        let ordering = match self.operator {
+            Operator::Equal | Operator::NotEqual => compare(value, &self.value),
+            _ => compare_levels(value, &self.value).or_else(|| compare(value, &self.value)),
+        };
+        match self.operator {
+            Operator::Equal => ordering == Some(Ordering::Equal),
+            Operator::NotEqual => ordering != Some(Ordering::Equal),
+            Operator::Greater => ordering == Some(Ordering::Greater),
+            Operator::GreaterEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            Operator::Less => ordering == Some(Ordering::Less),
+            Operator::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Operator::Contains | Operator::Matches => false,
+        }
+    }
+
+    /// Returns whether a record whose `level` field holds a value of the level can match, if the
+    /// predicate compares that field with a level, like `level>=warn` or `level=error`. Records
+    /// without the field never match these.
+    pub fn matches_level(&self, level: Level) -> Option<bool> {
+        if self.path != ["level"] || !self.value.is_string() {
+            return None;
+        }
+        let ordering = level.cmp(&Level::parse(&self.value)?);
+        match self.operator {
+            // Equal values as text are also the same level, other values of it may still differ.
+            Operator::Equal => Some(ordering == Ordering::Equal),
+            Operator::Greater => Some(ordering == Ordering::Greater),
+            Operator::GreaterEqual => Some(ordering != Ordering::Less),
+            Operator::Less => Some(ordering == Ordering::Less),
+            Operator::LessEqual => Some(ordering != Ordering::Greater),
+            _ => None,
+        }
+    }
+}
+
+/// Compares numbers numerically, even if one of them is a numeric string, and everything else as text.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (as_number(left), as_number(right)) {
+        (Some(left), Some(right)) => left.partial_cmp(&right),
+        _ => Some(as_text(left).cmp(&as_text(right))),
+    }
+}
+
+/// Compares by severity if the expected value is a level name and the value a level.
+fn compare_levels(value: &Value, expected: &Value) -> Option<Ordering> {
+    if !expected.is_string() {
+        return None;
+    }
+    Some(Level::parse(value)?.cmp(&Level::parse(expected)?))
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn matches(expression: &str, record: Value) -> bool {
+        Predicate::parse(expression)
+            .unwrap()
+            .matches(record.as_object().unwrap())
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(matches(r#"level=="error""#, json!({"level": "error"})));
+        assert!(matches("level=error", json!({"level": "error"})));
+        assert!(!matches("level!=error", json!({"level": "error"})));
+        assert!(matches("level!=error", json!({})));
+        assert!(matches("status>=500", json!({"status": 503})));
+        assert!(matches("status>=500", json!({"status": "500"})));
+        assert!(!matches("status<500", json!({"status": 503})));
+        assert!(matches("http.status>499", json!({"http": {"status": 503}})));
+        assert!(matches("ok==true", json!({"ok": true})));
+        assert!(matches("level>=error", json!({"level": "fatal"})));
+        assert!(matches("level>=warn", json!({"level": 50})));
+        assert!(!matches("level>=error", json!({"level": "warn"})));
+        assert!(matches("level<info", json!({"level": "DEBUG"})));
+    }
+
+    #[test]
+    fn test_matches_level() {
+        let level =
+            |expression: &str, level| Predicate::parse(expression).unwrap().matches_level(level);
+        assert_eq!(level("level>=warn", Level::Error), Some(true));
+        assert_eq!(level("level>=warn", Level::Info), Some(false));
+        assert_eq!(level("level<warn", Level::Info), Some(true));
+        assert_eq!(level("level=error", Level::Error), Some(true));
+        assert_eq!(level("level=error", Level::Warn), Some(false));
+        assert_eq!(level("level!=error", Level::Warn), None);
+        assert_eq!(level("severity>=warn", Level::Warn), None);
+        assert_eq!(level("level>=30", Level::Warn), None);
+        assert_eq!(level("level>=loud", Level::Warn), None);
+    }
+
+    #[test]
+    fn test_text() {
+        assert!(matches(
+            "msg~timeout",
+            json!({"msg": "read timeout after 5s"})
+        ));
+        assert!(!matches("msg~timeout", json!({"msg": "ok"})));
+        assert!(matches("tags~prod", json!({"tags": ["web", "prod"]})));
+        assert!(!matches("tags~pro", json!({"tags": ["web", "prod"]})));
+        assert!(matches(
+            "path=~^/api/v[12]/",
+            json!({"path": "/api/v2/users"})
+        ));
+        assert!(!matches("path=~^/api/v[12]/", json!({"path": "/health"})));
+        assert!(matches("status=~^5", json!({"status": 503})));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Predicate::parse("msg=~(").is_err());
+        assert!(Predicate::parse("level").is_err());
+        assert!(Predicate::parse("==error").is_err());
+    }
+}
@@ -0,0 +1,408 @@
+use crate::binary::{float, invalid, read_frame, read_varint, zigzag};
+use crate::format::binary_value;
+use crate::Framing;
+use chrono::{SecondsFormat, TimeZone, Utc};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+const TIMESTAMP: &str = ".google.protobuf.Timestamp";
+
+enum Wire<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+#[derive(Default)]
+struct Message {
+    fields: HashMap<u64, Field>,
+    map_entry: bool,
+}
+
+struct Field {
+    name: String,
+    /// Value of `FieldDescriptorProto.Type`, like 9 for strings.
+    kind: u64,
+    type_name: String,
+    repeated: bool,
+}
+
+/// Decodes length-prefixed protobuf messages of one type, using a compiled descriptor set.
+pub struct ProtoDecoder {
+    messages: HashMap<String, Message>,
+    enums: HashMap<String, HashMap<i32, String>>,
+    message_type: String,
+}
+
+impl ProtoDecoder {
+    /// Loads a `FileDescriptorSet` as written by `protoc --descriptor_set_out`.
+    pub fn load(descriptor: &Path, message_type: &str) -> io::Result<Self> {
+        ProtoDecoder::new(&fs::read(descriptor)?, message_type)
+    }
+
+    fn new(set: &[u8], message_type: &str) -> io::Result<Self> {
+        let mut decoder = ProtoDecoder {
+            messages: HashMap::new(),
+            enums: HashMap::new(),
+            message_type: format!(".{}", message_type.trim_start_matches('.')),
+        };
+        decoder.add_files(set)?;
+        if !decoder.messages.contains_key(&decoder.message_type) {
+            let message = format!("message type {} isn't in the descriptor set", message_type);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+        Ok(decoder)
+    }
+
+    fn add_files(&mut self, mut set: &[u8]) -> io::Result<()> {
+        while !set.is_empty() {
+            if let (1, Wire::Bytes(mut file)) = read_field(&mut set)? {
+                let mut files = Vec::new();
+                let mut package = String::new();
+                while !file.is_empty() {
+                    match read_field(&mut file)? {
+                        (2, Wire::Bytes(name)) => package = format!(".{}", string(name)?),
+                        (number @ 4, Wire::Bytes(bytes)) | (number @ 5, Wire::Bytes(bytes)) => {
+                            files.push((number, bytes))
+                        }
+                        _ => {}
+                    }
+                }
+                for (number, bytes) in files {
+                    match number {
+                        4 => self.add_message(&package, bytes)?,
+                        _ => self.add_enum(&package, bytes)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_message(&mut self, scope: &str, mut bytes: &[u8]) -> io::Result<()> {
+        let mut message = Message::default();
+        let mut name = String::new();
+        let mut nested = Vec::new();
+        while !bytes.is_empty() {
+            match read_field(&mut bytes)? {
+                (1, Wire::Bytes(bytes)) => name = format!("{}.{}", scope, string(bytes)?),
+                (2, Wire::Bytes(bytes)) => {
+                    let (number, field) = parse_field(bytes)?;
+                    message.fields.insert(number, field);
+                }
+                (number @ 3, Wire::Bytes(bytes)) | (number @ 4, Wire::Bytes(bytes)) => {
+                    nested.push((number, bytes))
+                }
+                (7, Wire::Bytes(mut options)) => {
+                    while !options.is_empty() {
+                        if let (7, Wire::Varint(value)) = read_field(&mut options)? {
+                            message.map_entry = value != 0;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (number, bytes) in nested {
+            match number {
+                3 => self.add_message(&name, bytes)?,
+                _ => self.add_enum(&name, bytes)?,
+            }
+        }
+        self.messages.insert(name, message);
+        Ok(())
+    }
+
+    fn add_enum(&mut self, scope: &str, mut bytes: &[u8]) -> io::Result<()> {
+        let mut name = String::new();
+        let mut values = HashMap::new();
+        while !bytes.is_empty() {
+            match read_field(&mut bytes)? {
+                (1, Wire::Bytes(bytes)) => name = format!("{}.{}", scope, string(bytes)?),
+                (2, Wire::Bytes(mut bytes)) => {
+                    let (mut symbol, mut number) = (String::new(), 0);
+                    while !bytes.is_empty() {
+                        match read_field(&mut bytes)? {
+                            (1, Wire::Bytes(bytes)) => symbol = string(bytes)?,
+                            (2, Wire::Varint(value)) => number = value as i32,
+                            _ => {}
+                        }
+                    }
+                    values.insert(number, symbol);
+                }
+                _ => {}
+            }
+        }
+        self.enums.insert(name, values);
+        Ok(())
+    }
+
+    /// Reads the next message prefixed by its varint length, or `None` if the input ended before it.
+    pub fn read_value<R: Read>(&self, reader: &mut R) -> io::Result<Option<Value>> {
+        match read_frame(reader, Framing::Varint)? {
+            Some(bytes) => self.decode_message(&self.message_type, &bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes a message with the field names of its type, skipping unknown fields.
+    fn decode_message(&self, type_name: &str, mut bytes: &[u8]) -> io::Result<Value> {
+        if type_name == TIMESTAMP && !self.messages.contains_key(TIMESTAMP) {
+            return decode_timestamp(bytes);
+        }
+        let message = self.messages.get(type_name).ok_or_else(|| {
+            let message = format!("message type {} isn't in the descriptor set", type_name);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        let mut record = Map::new();
+        while !bytes.is_empty() {
+            let (number, wire) = read_field(&mut bytes)?;
+            let field = match message.fields.get(&number) {
+                Some(field) => field,
+                None => continue,
+            };
+            let wires = match wire {
+                Wire::Bytes(packed) if field.repeated && wire_type(field.kind) != 2 => {
+                    unpack(field.kind, packed)?
+                }
+                wire => vec![wire],
+            };
+            for wire in wires {
+                let value = self.decode_field(field, wire)?;
+                if !field.repeated {
+                    record.insert(field.name.clone(), value);
+                } else if self.is_map_entry(field) {
+                    let entries = record
+                        .entry(field.name.clone())
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    if let (Value::Object(entries), Value::Object(mut entry)) = (entries, value) {
+                        let key = match entry.remove("key") {
+                            Some(Value::String(key)) => key,
+                            Some(key) => key.to_string(),
+                            None => String::new(),
+                        };
+                        entries.insert(key, entry.remove("value").unwrap_or(Value::Null));
+                    }
+                } else {
+                    let values = record
+                        .entry(field.name.clone())
+                        .or_insert_with(|| Value::Array(Vec::new()));
+                    if let Value::Array(values) = values {
+                        values.push(value);
+                    }
+                }
+            }
+        }
+        Ok(Value::Object(record))
+    }
+
+    fn decode_field(&self, field: &Field, wire: Wire) -> io::Result<Value> {
+        Ok(match (field.kind, wire) {
+            (1, Wire::Fixed64(value)) => float(f64::from_bits(value)),
+            (2, Wire::Fixed32(value)) => float(f32::from_bits(value) as f64),
+            (3, Wire::Varint(value)) | (16, Wire::Fixed64(value)) => Value::from(value as i64),
+            (4, Wire::Varint(value)) | (6, Wire::Fixed64(value)) => Value::from(value),
+            (5, Wire::Varint(value)) => Value::from(value as i32),
+            (7, Wire::Fixed32(value)) => Value::from(value),
+            (8, Wire::Varint(value)) => Value::Bool(value != 0),
+            (9, Wire::Bytes(bytes)) => Value::String(string(bytes)?),
+            (11, Wire::Bytes(bytes)) => self.decode_message(&field.type_name, bytes)?,
+            (12, Wire::Bytes(bytes)) => binary_value(bytes),
+            (13, Wire::Varint(value)) => Value::from(value as u32),
+            (14, Wire::Varint(value)) => {
+                let symbol = self
+                    .enums
+                    .get(&field.type_name)
+                    .and_then(|values| values.get(&(value as i32)));
+                symbol.map_or(Value::from(value as i32), |symbol| {
+                    Value::from(symbol.as_str())
+                })
+            }
+            (15, Wire::Fixed32(value)) => Value::from(value as i32),
+            (17, Wire::Varint(value)) | (18, Wire::Varint(value)) => Value::from(zigzag(value)),
+            _ => return Err(invalid("protobuf field doesn't match its declared type")),
+        })
+    }
+
+    fn is_map_entry(&self, field: &Field) -> bool {
+        field.kind == 11
+            && self
+                .messages
+                .get(&field.type_name)
+                .is_some_and(|message| message.map_entry)
+    }
+}
+
+fn parse_field(mut bytes: &[u8]) -> io::Result<(u64, Field)> {
+    let mut number = 0;
+    let mut field = Field {
+        name: String::new(),
+        kind: 0,
+        type_name: String::new(),
+        repeated: false,
+    };
+    while !bytes.is_empty() {
+        match read_field(&mut bytes)? {
+            (1, Wire::Bytes(name)) => field.name = string(name)?,
+            (3, Wire::Varint(value)) => number = value,
+            (4, Wire::Varint(label)) => field.repeated = label == 3,
+            (5, Wire::Varint(kind)) => field.kind = kind,
+            (6, Wire::Bytes(name)) => field.type_name = string(name)?,
+            _ => {}
+        }
+    }
+    Ok((number, field))
+}
+
+/// Reads the number and value of the next field in a message.
+fn read_field<'a>(bytes: &mut &'a [u8]) -> io::Result<(u64, Wire<'a>)> {
+    let key = read_varint(bytes)?;
+    let wire = match key & 7 {
+        0 => Wire::Varint(read_varint(bytes)?),
+        1 => Wire::Fixed64(u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap())),
+        2 => {
+            let length = read_varint(bytes)? as usize;
+            Wire::Bytes(take(bytes, length)?)
+        }
+        5 => Wire::Fixed32(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap())),
+        _ => return Err(invalid("unsupported protobuf wire type")),
+    };
+    Ok((key >> 3, wire))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], length: usize) -> io::Result<&'a [u8]> {
+    if bytes.len() < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (head, tail) = bytes.split_at(length);
+    *bytes = tail;
+    Ok(head)
+}
+
+/// Returns the wire type of a field type, which decides how packed repeated values are read.
+fn wire_type(kind: u64) -> u64 {
+    match kind {
+        1 | 6 | 16 => 1,
+        2 | 7 | 15 => 5,
+        9 | 11 | 12 => 2,
+        _ => 0,
+    }
+}
+
+fn unpack(kind: u64, mut bytes: &[u8]) -> io::Result<Vec<Wire<'_>>> {
+    let mut wires = Vec::new();
+    while !bytes.is_empty() {
+        wires.push(match wire_type(kind) {
+            1 => Wire::Fixed64(u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap())),
+            5 => Wire::Fixed32(u32::from_le_bytes(take(&mut bytes, 4)?.try_into().unwrap())),
+            _ => Wire::Varint(read_varint(&mut bytes)?),
+        });
+    }
+    Ok(wires)
+}
+
+/// Renders a `google.protobuf.Timestamp` as RFC 3339, like its canonical JSON mapping.
+fn decode_timestamp(mut bytes: &[u8]) -> io::Result<Value> {
+    let (mut seconds, mut nanos) = (0, 0);
+    while !bytes.is_empty() {
+        match read_field(&mut bytes)? {
+            (1, Wire::Varint(value)) => seconds = value as i64,
+            (2, Wire::Varint(value)) => nanos = value as u32,
+            _ => {}
+        }
+    }
+    let time = Utc
+        .timestamp_opt(seconds, nanos)
+        .single()
+        .ok_or_else(|| invalid("protobuf timestamp is out of range"))?;
+    Ok(Value::String(
+        time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    ))
+}
+
+fn string(bytes: &[u8]) -> io::Result<String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| invalid("protobuf string isn't valid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bytes(number: u8, bytes: &[u8]) -> Vec<u8> {
+        let mut field = vec![number << 3 | 2];
+        let mut length = bytes.len();
+        while length >= 0x80 {
+            field.push(length as u8 | 0x80);
+            length >>= 7;
+        }
+        field.push(length as u8);
+        field.extend(bytes);
+        field
+    }
+
+    fn field(name: &str, number: u8, label: u8, kind: u8, type_name: &str) -> Vec<u8> {
+        let mut field = bytes(1, name.as_bytes());
+        field.extend([3 << 3, number, 4 << 3, label, 5 << 3, kind]);
+        if !type_name.is_empty() {
+            field.extend(bytes(6, type_name.as_bytes()));
+        }
+        bytes(2, &field)
+    }
+
+    /// Describes `log.Entry { string msg = 1; repeated sint32 codes = 2; Level level = 3;
+    /// google.protobuf.Timestamp time = 4; map<string, string> labels = 5; }`.
+    fn decoder() -> ProtoDecoder {
+        let mut entry = bytes(1, b"Entry");
+        entry.extend(field("msg", 1, 1, 9, ""));
+        entry.extend(field("codes", 2, 3, 17, ""));
+        entry.extend(field("level", 3, 1, 14, ".log.Level"));
+        entry.extend(field("time", 4, 1, 11, TIMESTAMP));
+        entry.extend(field("labels", 5, 3, 11, ".log.Entry.LabelsEntry"));
+        let mut labels = bytes(1, b"LabelsEntry");
+        labels.extend(field("key", 1, 1, 9, ""));
+        labels.extend(field("value", 2, 1, 9, ""));
+        labels.extend(bytes(7, &[7 << 3, 1]));
+        entry.extend(bytes(3, &labels));
+        let mut level = bytes(1, b"Level");
+        level.extend(bytes(2, &[&bytes(1, b"INFO")[..], &[2 << 3, 0]].concat()));
+        level.extend(bytes(2, &[&bytes(1, b"WARN")[..], &[2 << 3, 1]].concat()));
+        let mut file = bytes(2, b"log");
+        file.extend(bytes(4, &entry));
+        file.extend(bytes(5, &level));
+        ProtoDecoder::new(&bytes(1, &file), "log.Entry").unwrap()
+    }
+
+    #[test]
+    fn test_read_value() {
+        let decoder = decoder();
+        let mut message = bytes(1, b"hi");
+        message.extend(bytes(2, &[1, 4]));
+        message.extend([3 << 3, 1, 6 << 3, 5]);
+        message.extend(bytes(4, &[1 << 3, 0x80, 0xe2, 0xcf, 0xaa, 0x06]));
+        message.extend(bytes(
+            5,
+            &[&bytes(1, b"app")[..], &bytes(2, b"api")].concat(),
+        ));
+        let mut input = vec![message.len() as u8];
+        input.extend(message);
+        let mut reader = &input[..];
+        assert_eq!(
+            decoder.read_value(&mut reader).unwrap(),
+            Some(json!({
+                "msg": "hi",
+                "codes": [-1, 2],
+                "level": "WARN",
+                "time": "2023-11-14T22:13:20Z",
+                "labels": {"app": "api"}
+            }))
+        );
+        assert!(decoder.read_value(&mut reader).unwrap().is_none());
+        assert!(decoder.read_value(&mut &b"\x05\x0a\x02"[..]).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use serde_json::{Map, Value};
+
+/// A jq-style expression selecting a sub-value of records like `.request.path` or `.items[0].id`,
+/// or a projection of several like `{path: .request.path, status}`.
+#[derive(Clone, Debug)]
+pub enum Query {
+    Path(Vec<Segment>),
+    Projection(Vec<(String, Vec<Segment>)>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Segment {
+    Key(String),
+    /// An array index, counted from the end if negative.
+    Index(i64),
+    /// All elements of an array, like `.tags[]`, or values of an object.
+    Each,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let invalid = |message: &str| format!("{} in query `{}`", message, query);
+        let mut cursor = Cursor(query.trim());
+        let parsed = match cursor.0.strip_prefix('{') {
+            Some(rest) => {
+                cursor.0 = rest;
+                let mut entries = Vec::new();
+                loop {
+                    cursor.skip_whitespace();
+                    if cursor.eat('}') {
+                        break;
+                    }
+                    let key = match cursor.0.starts_with('"') {
+                        true => cursor.string().ok_or_else(|| invalid("unclosed string"))?,
+                        false => cursor.identifier().to_string(),
+                    };
+                    if key.is_empty() {
+                        return Err(invalid("missing key"));
+                    }
+                    cursor.skip_whitespace();
+                    let path = match cursor.eat(':') {
+                        true => cursor.path().map_err(|message| invalid(&message))?,
+                        false => vec![Segment::Key(key.clone())],
+                    };
+                    entries.push((key, path));
+                    cursor.skip_whitespace();
+                    if cursor.eat('}') {
+                        break;
+                    }
+                    if !cursor.eat(',') {
+                        return Err(invalid("expected `,` or `}`"));
+                    }
+                }
+                Query::Projection(entries)
+            }
+            None => Query::Path(cursor.path().map_err(|message| invalid(&message))?),
+        };
+        cursor.skip_whitespace();
+        match cursor.0.is_empty() {
+            true => Ok(parsed),
+            false => Err(invalid(&format!("unexpected `{}`", cursor.0))),
+        }
+    }
+
+    /// Returns the selected value, `null` for missing paths like jq.
+    pub fn evaluate(&self, value: &Value) -> Value {
+        match self {
+            Query::Path(path) => select(value, path),
+            Query::Projection(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, path)| (key.clone(), select(value, path)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+fn select(value: &Value, path: &[Segment]) -> Value {
+    let (segment, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return value.clone(),
+    };
+    let selected = match (segment, value) {
+        (Segment::Key(key), Value::Object(object)) => object.get(key),
+        (Segment::Index(index), Value::Array(array)) => {
+            let index = match *index < 0 {
+                true => array.len().checked_sub(index.unsigned_abs() as usize),
+                false => Some(*index as usize),
+            };
+            index.and_then(|index| array.get(index))
+        }
+        (Segment::Each, Value::Array(array)) => {
+            return Value::Array(array.iter().map(|value| select(value, rest)).collect())
+        }
+        (Segment::Each, Value::Object(object)) => {
+            return Value::Array(object.values().map(|value| select(value, rest)).collect())
+        }
+        _ => None,
+    };
+    match selected {
+        Some(selected) => select(selected, rest),
+        None => Value::Null,
+    }
+}
+
+/// The rest of the query to parse.
+struct Cursor<'a>(&'a str);
+
+impl Cursor<'_> {
+    fn skip_whitespace(&mut self) {
+        self.0 = self.0.trim_start();
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        match self.0.strip_prefix(expected) {
+            Some(rest) => {
+                self.0 = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn identifier(&mut self) -> &str {
+        let end = self
+            .0
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '@'))
+            .unwrap_or(self.0.len());
+        let (identifier, rest) = self.0.split_at(end);
+        self.0 = rest;
+        identifier
+    }
+
+    /// Parses a double quoted string with the escapes of JSON.
+    fn string(&mut self) -> Option<String> {
+        let mut escaped = false;
+        let end = self.0.char_indices().skip(1).find_map(|(index, c)| {
+            let end = !escaped && c == '"';
+            escaped = !escaped && c == '\\';
+            end.then_some(index)
+        })?;
+        let string = serde_json::from_str(&self.0[..=end]).ok()?;
+        self.0 = &self.0[end + 1..];
+        Some(string)
+    }
+
+    /// Parses a path like `.a.b`, `.["a b"][0]` or `.items[].id`, where `.` alone is the record.
+    fn path(&mut self) -> Result<Vec<Segment>, String> {
+        self.skip_whitespace();
+        if !self.0.starts_with('.') {
+            return Err("expected a path starting with `.`".to_string());
+        }
+        let mut path = Vec::new();
+        loop {
+            if self.eat('[') {
+                let segment = if self.eat(']') {
+                    Segment::Each
+                } else if self.0.starts_with('"') {
+                    let key = self.string().ok_or("unclosed string")?;
+                    Segment::Key(key)
+                } else {
+                    let end = self.0.find(']').ok_or("unclosed `[`")?;
+                    let index = self.0[..end]
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid index `{}`", &self.0[..end]))?;
+                    self.0 = &self.0[end..];
+                    Segment::Index(index)
+                };
+                if segment != Segment::Each && !self.eat(']') {
+                    return Err("unclosed `[`".to_string());
+                }
+                path.push(segment);
+            } else if self.eat('.') {
+                if self.0.starts_with('"') {
+                    path.push(Segment::Key(self.string().ok_or("unclosed string")?));
+                } else {
+                    let key = self.identifier();
+                    if !key.is_empty() {
+                        path.push(Segment::Key(key.to_string()));
+                    } else if !path.is_empty() && !self.0.starts_with('[') {
+                        return Err("missing key after `.`".to_string());
+                    }
+                }
+            } else {
+                return Ok(path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(query: &str, record: Value) -> Value {
+        Query::parse(query).unwrap().evaluate(&record)
+    }
+
+    #[test]
+    fn test_path() {
+        let record = json!({"request": {"path": "/", "headers": {"x-id": 1}}, "items": [{"id": 1}, {"id": 2}]});
+        assert_eq!(query(".request.path", record.clone()), json!("/"));
+        assert_eq!(query(".request.headers.x-id", record.clone()), json!(1));
+        assert_eq!(
+            query(r#".request["headers"]"#, record.clone()),
+            json!({"x-id": 1})
+        );
+        assert_eq!(query(".items[-1].id", record.clone()), json!(2));
+        assert_eq!(query(".items[].id", record.clone()), json!([1, 2]));
+        assert_eq!(query(".items[5]", record.clone()), Value::Null);
+        assert_eq!(query(".missing.path", record.clone()), Value::Null);
+        assert_eq!(query(".", record.clone()), record);
+        assert_eq!(query(r#"."a b""#, json!({"a b": true})), json!(true));
+    }
+
+    #[test]
+    fn test_projection() {
+        let record = json!({"request": {"path": "/"}, "status": 200});
+        assert_eq!(
+            query("{path: .request.path, status, \"a b\": .missing}", record),
+            json!({"path": "/", "status": 200, "a b": null})
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Query::parse("request.path").is_err());
+        assert!(Query::parse(".items[").is_err());
+        assert!(Query::parse(".items[x]").is_err());
+        assert!(Query::parse(".a..b").is_err());
+        assert!(Query::parse("{path: .a").is_err());
+        assert!(Query::parse(".a | .b").is_err());
+    }
+}
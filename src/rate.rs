@@ -0,0 +1,189 @@
+use crate::predicate::Predicate;
+use crate::{duration, timestamp};
+use chrono::{SecondsFormat, TimeZone, Utc};
+use clap::Args;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Columns of the longest bar.
+const BAR_WIDTH: usize = 60;
+const MAX_BUCKETS: i64 = 10_000;
+const EIGHTHS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+/// Charts the number of records per time bucket over the time range of the input
+#[derive(Args, Debug)]
+pub struct RateOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+    /// Duration of each bucket, like `1m` or `1h`
+    #[clap(long, value_name = "DURATION", default_value = "1m", parse(try_from_str = duration::parse))]
+    bucket: Duration,
+    /// Counts only records matching a condition like `level=error`
+    #[clap(long = "where", value_name = "CONDITION", parse(try_from_str = Predicate::parse))]
+    condition: Option<Predicate>,
+}
+
+/// Counts records by bucket start in epoch milliseconds.
+struct Rate {
+    bucket: i64,
+    condition: Option<Predicate>,
+    counts: BTreeMap<i64, u64>,
+}
+
+pub fn run(opt: &RateOpt, ascii: bool) -> io::Result<()> {
+    let mut rate = Rate {
+        bucket: opt.bucket.as_millis().max(1) as i64,
+        condition: opt.condition.clone(),
+        counts: BTreeMap::new(),
+    };
+    if opt.files.is_empty() {
+        rate.add_lines(io::stdin().lock())?;
+    }
+    for path in &opt.files {
+        rate.add_lines(BufReader::new(File::open(path)?))?;
+    }
+    let buckets = rate.buckets()?;
+    if atty::is(atty::Stream::Stdout) {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        for line in chart(&buckets, rate.bucket, ascii) {
+            writeln!(stdout, "{}", line)?;
+        }
+        return Ok(());
+    }
+    crate::print_lines(buckets.iter().map(|(start, count)| {
+        let start = Utc.timestamp_millis_opt(*start).unwrap();
+        let start = start.to_rfc3339_opts(SecondsFormat::Secs, true);
+        Ok(json!({"bucket": start, "records": count}).to_string())
+    }))
+}
+
+impl Rate {
+    fn add_lines<R: BufRead>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? != 0 {
+            self.add_line(&line);
+            line.clear();
+        }
+        Ok(())
+    }
+
+    fn add_line(&mut self, line: &[u8]) {
+        let object = match serde_json::from_slice(line) {
+            Ok(Value::Object(object)) => object,
+            _ => return,
+        };
+        if let Some(condition) = &self.condition {
+            if !condition.matches(&object) {
+                return;
+            }
+        }
+        if let Some(time) = timestamp::detect(&object) {
+            let start = time.timestamp_millis().div_euclid(self.bucket) * self.bucket;
+            *self.counts.entry(start).or_default() += 1;
+        }
+    }
+
+    /// Returns the count of every bucket from the first to the last, including empty ones.
+    fn buckets(&self) -> io::Result<Vec<(i64, u64)>> {
+        let (first, last) = match (self.counts.keys().next(), self.counts.keys().last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Ok(Vec::new()),
+        };
+        if (last - first) / self.bucket >= MAX_BUCKETS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("more than {} buckets, use a larger --bucket", MAX_BUCKETS),
+            ));
+        }
+        Ok((0..=(last - first) / self.bucket)
+            .map(|index| first + index * self.bucket)
+            .map(|start| (start, self.counts.get(&start).copied().unwrap_or(0)))
+            .collect())
+    }
+}
+
+/// Renders a line per bucket with its start, a bar scaled to the largest count and the count.
+///
+/// ASCII bars are rounded to whole characters instead of eighths.
+fn chart(buckets: &[(i64, u64)], bucket: i64, ascii: bool) -> Vec<String> {
+    let max = buckets
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let format = match bucket % 60_000 {
+        0 => "%Y-%m-%d %H:%M",
+        _ => "%Y-%m-%d %H:%M:%S",
+    };
+    buckets
+        .iter()
+        .map(|(start, count)| {
+            let eighths = (*count as f64 / max as f64 * (BAR_WIDTH * 8) as f64).round() as usize;
+            let bar = match ascii {
+                true => "#".repeat((eighths + 4) / 8),
+                false => "█".repeat(eighths / 8) + EIGHTHS[eighths % 8],
+            };
+            let start = Utc.timestamp_millis_opt(*start).unwrap().format(format);
+            format!("{} {:<width$} {}", start, bar, count, width = BAR_WIDTH)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(condition: Option<&str>, lines: &[&str]) -> Rate {
+        let mut rate = Rate {
+            bucket: 60_000,
+            condition: condition.map(|condition| Predicate::parse(condition).unwrap()),
+            counts: BTreeMap::new(),
+        };
+        for line in lines {
+            rate.add_line(line.as_bytes());
+        }
+        rate
+    }
+
+    #[test]
+    fn test_buckets() {
+        let rate = rate(
+            Some("level=error"),
+            &[
+                r#"{"ts":"2023-01-01T00:00:10Z","level":"error"}"#,
+                r#"{"ts":"2023-01-01T00:00:50Z","level":"error"}"#,
+                r#"{"ts":"2023-01-01T00:01:00Z","level":"info"}"#,
+                r#"{"ts":"2023-01-01T00:02:30Z","level":"error"}"#,
+                "text",
+            ],
+        );
+        let minute = 1672531200000;
+        assert_eq!(
+            rate.buckets().unwrap(),
+            [(minute, 2), (minute + 60_000, 0), (minute + 120_000, 1)]
+        );
+    }
+
+    #[test]
+    fn test_chart() {
+        let buckets = [(0, 4), (60_000, 1), (120_000, 3)];
+        let unicode = chart(&buckets, 60_000, false);
+        assert_eq!(unicode[0], format!("1970-01-01 00:00 {} 4", "█".repeat(60)));
+        assert_eq!(
+            unicode[1],
+            format!("1970-01-01 00:01 {}{} 1", "█".repeat(15), " ".repeat(45))
+        );
+        let ascii = chart(&buckets, 60_000, true);
+        assert_eq!(ascii[0], format!("1970-01-01 00:00 {} 4", "#".repeat(60)));
+        assert_eq!(
+            ascii[2],
+            format!("1970-01-01 00:02 {}{} 3", "#".repeat(45), " ".repeat(15))
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use serde_json::{Map, Number, Value};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Nesting of objects and arrays parsed at most, like the recursion limit of serde_json, so lines
+/// the --limits scan doesn't recognize as JSON, like ones starting with a comment, can't overflow
+/// the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Parses a line of JSON5-ish JSON, with single quoted strings, unquoted keys, trailing commas,
+/// comments and numbers like `.5`, `+1` or `0xff`.
+pub fn parse(line: &[u8]) -> Option<Value> {
+    let text = std::str::from_utf8(line).ok()?;
+    let mut parser = Parser {
+        text,
+        chars: text.char_indices().peekable(),
+        depth: MAX_DEPTH,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace()?;
+    match parser.chars.next() {
+        None => Some(value),
+        Some(_) => None,
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Nesting of objects and arrays left below the current value.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.chars
+            .next()
+            .filter(|(_, c)| *c == expected)
+            .map(|_| ())
+    }
+
+    /// Skips whitespace and comments, failing on an unterminated block comment.
+    fn skip_whitespace(&mut self) -> Option<()> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                Some('/') => {
+                    self.chars.next();
+                    match self.chars.next()?.1 {
+                        '/' => {
+                            for (_, c) in self.chars.by_ref() {
+                                if c == '\n' {
+                                    break;
+                                }
+                            }
+                        }
+                        '*' => {
+                            let mut previous = ' ';
+                            loop {
+                                let c = self.chars.next()?.1;
+                                if previous == '*' && c == '/' {
+                                    break;
+                                }
+                                previous = c;
+                            }
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => return Some(()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.skip_whitespace()?;
+        match self.peek()? {
+            '{' | '[' => {
+                self.depth = self.depth.checked_sub(1)?;
+                let value = match self.peek()? {
+                    '{' => self.object(),
+                    _ => self.array(),
+                };
+                self.depth += 1;
+                value
+            }
+            '"' | '\'' => self.string().map(Value::String),
+            _ => {
+                let word = self.word();
+                match word {
+                    "true" => Some(Value::Bool(true)),
+                    "false" => Some(Value::Bool(false)),
+                    "null" => Some(Value::Null),
+                    word => number(word).map(Value::Number),
+                }
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Value> {
+        self.expect('{')?;
+        let mut object = Map::new();
+        loop {
+            self.skip_whitespace()?;
+            let key = match self.peek()? {
+                '}' => break,
+                '"' | '\'' => self.string()?,
+                _ => Some(self.word())
+                    .filter(|word| !word.is_empty())?
+                    .to_string(),
+            };
+            self.skip_whitespace()?;
+            self.expect(':')?;
+            object.insert(key, self.value()?);
+            self.skip_whitespace()?;
+            match self.peek()? {
+                ',' => {
+                    self.chars.next();
+                }
+                '}' => break,
+                _ => return None,
+            }
+        }
+        self.expect('}')?;
+        Some(Value::Object(object))
+    }
+
+    fn array(&mut self) -> Option<Value> {
+        self.expect('[')?;
+        let mut array = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek()? == ']' {
+                break;
+            }
+            array.push(self.value()?);
+            self.skip_whitespace()?;
+            match self.peek()? {
+                ',' => {
+                    self.chars.next();
+                }
+                ']' => break,
+                _ => return None,
+            }
+        }
+        self.expect(']')?;
+        Some(Value::Array(array))
+    }
+
+    /// Parses a string in single or double quotes with the escapes of JSON, `\'` and `\xff`.
+    fn string(&mut self) -> Option<String> {
+        let quote = self.chars.next()?.1;
+        let mut string = String::new();
+        loop {
+            match self.chars.next()?.1 {
+                c if c == quote => return Some(string),
+                '\\' => match self.chars.next()?.1 {
+                    'b' => string.push('\u{8}'),
+                    'f' => string.push('\u{c}'),
+                    'n' => string.push('\n'),
+                    'r' => string.push('\r'),
+                    't' => string.push('\t'),
+                    '0' => string.push('\0'),
+                    'x' => string.push(char::from_u32(self.code(2)?)?),
+                    'u' => string.push(self.unicode()?),
+                    // An escaped line break continues the string on the next line.
+                    '\n' => {}
+                    c => string.push(c),
+                },
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// Parses the code point of a `\u` escape, combining a UTF-16 surrogate pair.
+    fn unicode(&mut self) -> Option<char> {
+        let high = self.code(4)?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high);
+        }
+        self.expect('\\')?;
+        self.expect('u')?;
+        let low = self
+            .code(4)?
+            .checked_sub(0xdc00)
+            .filter(|low| *low < 0x400)?;
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + low)
+    }
+
+    /// Parses a number of hex digits.
+    fn code(&mut self, digits: usize) -> Option<u32> {
+        let mut code = 0;
+        for _ in 0..digits {
+            code = code * 16 + self.chars.next()?.1.to_digit(16)?;
+        }
+        Some(code)
+    }
+
+    /// Returns the identifier or number at the current position.
+    fn word(&mut self) -> &str {
+        let start = self
+            .chars
+            .peek()
+            .map_or(self.text.len(), |(index, _)| *index);
+        let mut end = start;
+        while let Some((index, c)) = self.chars.peek().copied() {
+            if !(c.is_alphanumeric() || "_$.+-".contains(c)) {
+                break;
+            }
+            end = index + c.len_utf8();
+            self.chars.next();
+        }
+        &self.text[start..end]
+    }
+}
+
+/// Parses an integer or float with an optional sign, a hex integer or a float without digits on
+/// one side of the point.
+fn number(word: &str) -> Option<Number> {
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word.strip_prefix('+').unwrap_or(word)),
+    };
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        let number = i64::from_str_radix(hex, 16).ok()?;
+        return Some(Number::from(if negative { -number } else { number }));
+    }
+    let sign = if negative { "-" } else { "" };
+    if let Ok(number) = format!("{}{}", sign, digits).parse::<i64>() {
+        return Some(Number::from(number));
+    }
+    if let Ok(number) = digits.parse::<u64>() {
+        return (!negative).then(|| Number::from(number));
+    }
+    digits
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .and_then(|number| Number::from_f64(if negative { -number } else { number }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(b"{level: 'warn', msg: \"it's\", tags: ['a', 'b',], n: .5, hex: 0x1f,}"),
+            Some(json!({"level": "warn", "msg": "it's", "tags": ["a", "b"], "n": 0.5, "hex": 31}))
+        );
+        assert_eq!(
+            parse(b"/* sent by */ {'a': +1, // count\n b: null}"),
+            Some(json!({"a": 1, "b": null}))
+        );
+        assert_eq!(
+            parse(r"{'quote': 'say \'hi\' é😀'}".as_bytes()),
+            Some(json!({"quote": "say 'hi' \u{e9}\u{1f600}"}))
+        );
+        assert_eq!(
+            parse(br"{s: '\x41\u00e9\ud83d\ude00'}"),
+            Some(json!({"s": "A\u{e9}\u{1f600}"}))
+        );
+        assert_eq!(
+            parse(b"[1, -2.5e3, false]"),
+            Some(json!([1, -2500.0, false]))
+        );
+        assert_eq!(parse(b"{a: 1} trailing"), None);
+        assert_eq!(parse(b"{a: 1,, b: 2}"), None);
+        assert_eq!(parse(b"{a: 1 /* open"), None);
+        assert_eq!(parse(b"{a: undefined}"), None);
+        assert_eq!(parse(b"plain text"), None);
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(nested(MAX_DEPTH).as_bytes()).is_some());
+        assert_eq!(parse(nested(MAX_DEPTH + 1).as_bytes()), None);
+        assert_eq!(
+            parse(format!("/*x*/ {}", "[".repeat(1 << 18)).as_bytes()),
+            None
+        );
+    }
+}
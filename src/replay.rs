@@ -0,0 +1,109 @@
+use crate::timestamp;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Writes the records of a file again, paced by the time between their timestamps
+#[derive(Args, Debug)]
+pub struct ReplayOpt {
+    /// File to replay instead of stdin
+    file: Option<PathBuf>,
+    /// Factor to speed up the replay by, like `2x` or `0.5`
+    #[clap(long, value_name = "FACTOR", default_value = "1x", parse(try_from_str = parse_speed))]
+    speed: f64,
+}
+
+fn parse_speed(speed: &str) -> Result<f64, String> {
+    speed
+        .strip_suffix('x')
+        .unwrap_or(speed)
+        .parse::<f64>()
+        .ok()
+        .filter(|speed| *speed > 0.0 && speed.is_finite())
+        .ok_or_else(|| format!("invalid speed `{}`", speed))
+}
+
+pub fn run(opt: &ReplayOpt) -> io::Result<()> {
+    let reader: Box<dyn BufRead> = match &opt.file {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+    let mut pacer = Pacer::new(opt.speed);
+    let lines = reader.lines().map(move |line| {
+        let line = line?;
+        if let Some(delay) = pacer.delay(&line, Instant::now()) {
+            thread::sleep(delay);
+        }
+        Ok(line)
+    });
+    crate::print_lines(lines)
+}
+
+/// Schedules records relative to the first timestamp, so sleeping late doesn't add up.
+struct Pacer {
+    speed: f64,
+    start: Option<(DateTime<Utc>, Instant)>,
+}
+
+impl Pacer {
+    fn new(speed: f64) -> Self {
+        Pacer { speed, start: None }
+    }
+
+    /// Returns how long to wait before writing the line, `None` if it is due or has no timestamp.
+    fn delay(&mut self, line: &str, now: Instant) -> Option<Duration> {
+        let time = match serde_json::from_str(line) {
+            Ok(Value::Object(object)) => timestamp::detect(&object)?,
+            _ => return None,
+        };
+        let (first, started) = *self.start.get_or_insert((time, now));
+        let offset = (time - first).to_std().ok()?.div_f64(self.speed);
+        (started + offset)
+            .checked_duration_since(now)
+            .filter(|delay| !delay.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("2x"), Ok(2.0));
+        assert_eq!(parse_speed("0.5"), Ok(0.5));
+        assert!(parse_speed("0x").is_err());
+        assert!(parse_speed("fast").is_err());
+    }
+
+    #[test]
+    fn test_delay() {
+        let mut pacer = Pacer::new(2.0);
+        let start = Instant::now();
+        assert_eq!(pacer.delay(r#"{"ts":"2023-01-01T00:00:00Z"}"#, start), None);
+        assert_eq!(pacer.delay("text", start), None);
+        assert_eq!(
+            pacer.delay(r#"{"ts":"2023-01-01T00:00:10Z"}"#, start),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            pacer.delay(
+                r#"{"ts":"2023-01-01T00:00:10Z"}"#,
+                start + Duration::from_secs(1)
+            ),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            pacer.delay(
+                r#"{"ts":"2022-12-31T23:59:59Z"}"#,
+                start + Duration::from_secs(6)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::predicate::Predicate;
+use crate::template::PathTemplate;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Files kept open for templated paths, before all of them are closed.
+const MAX_OPEN_FILES: usize = 64;
+
+/// A rule like `level>=error → errors.ndjson`, appending the records matching a condition to a file
+/// whose path may be a template like `errors/{service}/%Y-%m-%d.ndjson`.
+#[derive(Clone, Debug)]
+pub struct Route {
+    condition: Option<Predicate>,
+    path: PathTemplate,
+}
+
+impl Route {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (condition, path) = rule
+            .split_once('→')
+            .or_else(|| rule.split_once("->"))
+            .ok_or_else(|| format!("expected `CONDITION → FILE` instead of `{}`", rule))?;
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(format!("missing file in `{}`", rule));
+        }
+        Ok(Route {
+            condition: Some(Predicate::parse(condition.trim())?),
+            path: PathTemplate::parse(path)?,
+        })
+    }
+
+    /// Parses a path template receiving all records.
+    pub fn parse_all(path: &str) -> Result<Self, String> {
+        Ok(Route {
+            condition: None,
+            path: PathTemplate::parse(path)?,
+        })
+    }
+}
+
+/// The routes with the files of their rendered paths opened for appending, `None` after a failure.
+pub struct Router {
+    routes: Vec<Route>,
+    files: HashMap<PathBuf, Option<File>>,
+}
+
+impl Router {
+    /// Opens the files of routes with fixed paths, so their errors are reported right away.
+    pub fn open(routes: &[Route]) -> io::Result<Self> {
+        let mut files = HashMap::new();
+        for route in routes.iter().filter(|route| route.path.is_fixed()) {
+            let path = route.path.render(&Map::new());
+            let file = open(&path).map_err(|err| {
+                io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+            })?;
+            files.insert(path, Some(file));
+        }
+        Ok(Router {
+            routes: routes.to_vec(),
+            files,
+        })
+    }
+
+    /// Appends the record as a line to the files of the matching routes, giving up on a file
+    /// after opening or writing it failed.
+    pub fn route(&mut self, object: &Map<String, Value>, line: &[u8]) {
+        for route in &self.routes {
+            if !route
+                .condition
+                .as_ref()
+                .is_none_or(|condition| condition.matches(object))
+            {
+                continue;
+            }
+            let path = route.path.render(object);
+            if !self.files.contains_key(&path) && self.files.len() >= MAX_OPEN_FILES {
+                self.files.retain(|_, file| file.is_none());
+            }
+            let file = self.files.entry(path.clone()).or_insert_with(|| {
+                open(&path)
+                    .map_err(|err| eprintln!("ndjson: can't open {}: {}", path.display(), err))
+                    .ok()
+            });
+            let writer = match file {
+                Some(writer) => writer,
+                None => continue,
+            };
+            if let Err(err) = writer.write_all(line).and_then(|_| writer.write_all(b"\n")) {
+                eprintln!("ndjson: can't write to {}: {}", path.display(), err);
+                *file = None;
+            }
+        }
+    }
+}
+
+/// Opens a file for appending, creating it and its directories if missing.
+fn open(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_parse() {
+        let route = Route::parse("level>=error → errors.ndjson").unwrap();
+        assert_eq!(
+            route.path.render(&Map::new()),
+            PathBuf::from("errors.ndjson")
+        );
+        assert!(Route::parse("status>=500 -> 5xx.ndjson").is_ok());
+        assert!(Route::parse("level>=error").is_err());
+        assert!(Route::parse("level>=error → ").is_err());
+    }
+
+    #[test]
+    fn test_route() {
+        let path = std::env::temp_dir().join(format!("ndjson-route-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let rule = format!("level>=error → {}", path.display());
+        let mut router = Router::open(&[Route::parse(&rule).unwrap()]).unwrap();
+        for record in [
+            json!({"level": "info", "msg": "a"}),
+            json!({"level": "error", "msg": "b"}),
+        ] {
+            let line = record.to_string();
+            router.route(record.as_object().unwrap(), line.as_bytes());
+        }
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"level\":\"error\",\"msg\":\"b\"}\n"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_templated_route() {
+        let dir = std::env::temp_dir().join(format!("ndjson-routes-{}", std::process::id()));
+        let template = format!("{}/{{service}}/%Y.ndjson", dir.display());
+        let mut router = Router::open(&[Route::parse_all(&template).unwrap()]).unwrap();
+        for record in [
+            json!({"service": "web", "ts": "2023-01-01T00:00:00Z"}),
+            json!({"service": "api", "ts": "2023-01-01T00:00:00Z"}),
+            json!({"service": "web", "ts": "2024-01-01T00:00:00Z"}),
+        ] {
+            let line = record.to_string();
+            router.route(record.as_object().unwrap(), line.as_bytes());
+        }
+        for (path, lines) in [("web/2023", 1), ("api/2023", 1), ("web/2024", 1)] {
+            let path = dir.join(format!("{}.ndjson", path));
+            assert_eq!(fs::read_to_string(path).unwrap().lines().count(), lines);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use crate::duration;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Observations after which the windows of values which stopped appearing are forgotten.
+const PRUNE_INTERVAL: u64 = 1024;
+
+/// Limits the records per distinct value of a field, like `user_id=10/min`.
+#[derive(Clone, Debug)]
+pub struct SampleBy {
+    field: String,
+    limit: u64,
+    window: Duration,
+    /// Start of the current window and records passed in it per value.
+    windows: HashMap<String, (Instant, u64)>,
+    observed: u64,
+    dropped: u64,
+}
+
+impl SampleBy {
+    /// Parses a field with a number of records per window, like `user_id=10/min` or `tenant=100/30s`.
+    pub fn parse(sample: &str) -> Result<Self, String> {
+        let invalid = || format!("expected FIELD=N/WINDOW instead of `{}`", sample);
+        let (field, rate) = sample.rsplit_once('=').ok_or_else(invalid)?;
+        let (limit, window) = rate.split_once('/').ok_or_else(invalid)?;
+        let window = match window {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(3600),
+            window => duration::parse(window)?,
+        };
+        if field.is_empty() || window.is_zero() {
+            return Err(invalid());
+        }
+        Ok(SampleBy {
+            field: field.to_string(),
+            limit: limit.parse().map_err(|_| invalid())?,
+            window,
+            windows: HashMap::new(),
+            observed: 0,
+            dropped: 0,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Counts the record, returns whether its value exceeded the limit of the current window.
+    /// Records without the field are never dropped.
+    pub fn is_over_limit(&mut self, object: &Map<String, Value>, now: Instant) -> bool {
+        let value = match object.get(&self.field) {
+            Some(Value::String(string)) => string.clone(),
+            Some(value) => value.to_string(),
+            None => return false,
+        };
+        self.observed += 1;
+        if self.observed.is_multiple_of(PRUNE_INTERVAL) {
+            let window = self.window;
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < window);
+        }
+        let (start, count) = self.windows.entry(value).or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= self.limit {
+            self.dropped += 1;
+            return true;
+        }
+        *count += 1;
+        false
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let sample = SampleBy::parse("user_id=10/min").unwrap();
+        assert_eq!((sample.limit, sample.window), (10, Duration::from_secs(60)));
+        let sample = SampleBy::parse("a=b=5/30s").unwrap();
+        assert_eq!(sample.field, "a=b");
+        assert_eq!(sample.window, Duration::from_secs(30));
+        assert!(SampleBy::parse("user_id=10").is_err());
+        assert!(SampleBy::parse("user_id=ten/min").is_err());
+        assert!(SampleBy::parse("=10/min").is_err());
+    }
+
+    #[test]
+    fn test_is_over_limit() {
+        let mut sample = SampleBy::parse("user=2/min").unwrap();
+        let start = Instant::now();
+        let mut over = |record: Value, seconds| {
+            sample.is_over_limit(
+                record.as_object().unwrap(),
+                start + Duration::from_secs(seconds),
+            )
+        };
+        assert!(!over(json!({"user": "a"}), 0));
+        assert!(!over(json!({"user": "a"}), 1));
+        assert!(over(json!({"user": "a"}), 2));
+        assert!(!over(json!({"user": "b"}), 3));
+        assert!(!over(json!({}), 4));
+        assert!(!over(json!({"user": "a"}), 60));
+        assert_eq!(sample.dropped(), 1);
+    }
+}
@@ -0,0 +1,228 @@
+use std::io;
+
+/// Forbids creating sockets, opening files for writing, creating, renaming or deleting files and
+/// changing their permissions, owners, extended attributes or times, for the whole process
+/// including threads it already started. Writing to the open stdout and stderr
+/// and reading files stays possible, so untrusted input can at worst garble the output.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub fn enter() -> io::Result<()> {
+    let mut filter = filter();
+    let program = libc::sock_fprog {
+        len: filter.len() as u16,
+        filter: filter.as_mut_ptr(),
+    };
+    // SAFETY: the program outlives the calls, which only read it, and no-new-privs is required
+    // for installing a filter without CAP_SYS_ADMIN.
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let flags = libc::SECCOMP_FILTER_FLAG_TSYNC;
+        let program: *const libc::sock_fprog = &program;
+        if libc::syscall(
+            libc::SYS_seccomp,
+            libc::SECCOMP_SET_MODE_FILTER,
+            flags,
+            program,
+        ) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+)))]
+pub fn enter() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--sandbox is only supported on Linux on x86_64 and aarch64",
+    ))
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod arch {
+    pub const AUDIT_ARCH: u32 = 0xc000_003e;
+    /// Set in the numbers of x32 calls, which share the architecture but not the numbers.
+    pub const DENIED_BIT: Option<u32> = Some(0x4000_0000);
+    pub const DENIED: [libc::c_long; 42] = [
+        libc::SYS_socket,
+        libc::SYS_connect,
+        libc::SYS_bind,
+        libc::SYS_listen,
+        libc::SYS_accept,
+        libc::SYS_accept4,
+        libc::SYS_creat,
+        libc::SYS_unlink,
+        libc::SYS_unlinkat,
+        libc::SYS_rename,
+        libc::SYS_renameat,
+        libc::SYS_renameat2,
+        libc::SYS_mkdir,
+        libc::SYS_mkdirat,
+        libc::SYS_rmdir,
+        libc::SYS_link,
+        libc::SYS_linkat,
+        libc::SYS_symlink,
+        libc::SYS_symlinkat,
+        libc::SYS_mknod,
+        libc::SYS_mknodat,
+        libc::SYS_truncate,
+        libc::SYS_openat2,
+        libc::SYS_io_uring_setup,
+        libc::SYS_chmod,
+        libc::SYS_fchmod,
+        libc::SYS_fchmodat,
+        libc::SYS_fchmodat2,
+        libc::SYS_chown,
+        libc::SYS_fchown,
+        libc::SYS_fchownat,
+        libc::SYS_lchown,
+        libc::SYS_setxattr,
+        libc::SYS_lsetxattr,
+        libc::SYS_fsetxattr,
+        libc::SYS_removexattr,
+        libc::SYS_lremovexattr,
+        libc::SYS_fremovexattr,
+        libc::SYS_utime,
+        libc::SYS_utimes,
+        libc::SYS_futimesat,
+        libc::SYS_utimensat,
+    ];
+    /// Calls opening files, with the index of their flags argument.
+    pub const OPENS: [(libc::c_long, u32); 2] = [(libc::SYS_open, 1), (libc::SYS_openat, 2)];
+}
+
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+mod arch {
+    pub const AUDIT_ARCH: u32 = 0xc000_00b7;
+    /// Number of `fchmodat2`, which libc only defines for some architectures.
+    const SYS_FCHMODAT2: libc::c_long = 452;
+    pub const DENIED_BIT: Option<u32> = None;
+    pub const DENIED: [libc::c_long; 28] = [
+        libc::SYS_socket,
+        libc::SYS_connect,
+        libc::SYS_bind,
+        libc::SYS_listen,
+        libc::SYS_accept,
+        libc::SYS_accept4,
+        libc::SYS_unlinkat,
+        libc::SYS_renameat,
+        libc::SYS_renameat2,
+        libc::SYS_mkdirat,
+        libc::SYS_linkat,
+        libc::SYS_symlinkat,
+        libc::SYS_mknodat,
+        libc::SYS_truncate,
+        libc::SYS_openat2,
+        libc::SYS_io_uring_setup,
+        libc::SYS_fchmod,
+        libc::SYS_fchmodat,
+        SYS_FCHMODAT2,
+        libc::SYS_fchown,
+        libc::SYS_fchownat,
+        libc::SYS_setxattr,
+        libc::SYS_lsetxattr,
+        libc::SYS_fsetxattr,
+        libc::SYS_removexattr,
+        libc::SYS_lremovexattr,
+        libc::SYS_fremovexattr,
+        libc::SYS_utimensat,
+    ];
+    /// Calls opening files, with the index of their flags argument.
+    pub const OPENS: [(libc::c_long, u32); 1] = [(libc::SYS_openat, 2)];
+}
+
+/// Returns the seccomp program failing the denied calls, calls of the x32 ABI and opens for writing
+/// with `EPERM`, and killing the process if it runs as another architecture, whose call numbers
+/// differ.
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+fn filter() -> Vec<libc::sock_filter> {
+    use libc::{BPF_ABS, BPF_JEQ, BPF_JMP, BPF_JSET, BPF_K, BPF_LD, BPF_RET, BPF_W};
+    // Offsets in struct seccomp_data.
+    const NR: u32 = 0;
+    const ARCH: u32 = 4;
+    const ARGS: u32 = 16;
+    const WRITE_FLAGS: libc::c_int =
+        libc::O_WRONLY | libc::O_RDWR | libc::O_CREAT | libc::O_TRUNC | libc::O_APPEND;
+    let statement = |code: u32, k: u32| libc::sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    };
+    let jump = |code: u32, k: u32, jt: u8, jf: u8| libc::sock_filter {
+        code: (BPF_JMP | code | BPF_K) as u16,
+        jt,
+        jf,
+        k,
+    };
+    let deny = statement(
+        BPF_RET | BPF_K,
+        libc::SECCOMP_RET_ERRNO | libc::EPERM as u32,
+    );
+    let allow = statement(BPF_RET | BPF_K, libc::SECCOMP_RET_ALLOW);
+    let mut filter = vec![
+        statement(BPF_LD | BPF_W | BPF_ABS, ARCH),
+        jump(BPF_JEQ, arch::AUDIT_ARCH, 1, 0),
+        statement(BPF_RET | BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        statement(BPF_LD | BPF_W | BPF_ABS, NR),
+    ];
+    if let Some(bit) = arch::DENIED_BIT {
+        filter.push(jump(BPF_JSET, bit, 0, 1));
+        filter.push(deny);
+    }
+    for call in arch::DENIED {
+        filter.push(jump(BPF_JEQ, call as u32, 0, 1));
+        filter.push(deny);
+    }
+    for (call, flags) in arch::OPENS {
+        filter.push(jump(BPF_JEQ, call as u32, 0, 4));
+        // The low half of the argument, on these little-endian architectures.
+        filter.push(statement(BPF_LD | BPF_W | BPF_ABS, ARGS + 8 * flags));
+        filter.push(jump(BPF_JSET, WRITE_FLAGS as u32, 0, 1));
+        filter.push(deny);
+        filter.push(allow);
+    }
+    filter.push(allow);
+    filter
+}
+
+#[cfg(all(
+    test,
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let filter = filter();
+        let jumps_in_bounds = filter
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| u32::from(instruction.code) & 0x07 == libc::BPF_JMP)
+            .all(|(index, instruction)| {
+                index + 1 + (instruction.jt.max(instruction.jf) as usize) < filter.len()
+            });
+        assert!(jumps_in_bounds);
+        assert_eq!(
+            filter.len(),
+            4 + 2 * arch::DENIED_BIT.iter().count()
+                + 2 * arch::DENIED.len()
+                + 5 * arch::OPENS.len()
+                + 1
+        );
+        assert_eq!(filter.last().unwrap().k, libc::SECCOMP_RET_ALLOW);
+    }
+}
@@ -0,0 +1,125 @@
+use serde_json::Value;
+use termcolor::Color;
+
+const COLORS: [(&str, (u8, u8, u8)); 9] = [
+    ("black", (0, 0, 0)),
+    ("red", (220, 50, 47)),
+    ("green", (80, 200, 80)),
+    ("yellow", (230, 200, 40)),
+    ("orange", (240, 140, 30)),
+    ("blue", (60, 120, 230)),
+    ("magenta", (210, 60, 200)),
+    ("cyan", (40, 190, 200)),
+    ("white", (255, 255, 255)),
+];
+
+/// A color gradient for a numeric field, like `latency_ms:0=green,500=yellow,2000=red`.
+#[derive(Clone, Debug)]
+pub struct ColorScale {
+    field: String,
+    stops: Vec<(f64, (u8, u8, u8))>,
+}
+
+impl ColorScale {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (field, stops) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("missing `:` after the field in `{}`", spec))?;
+        let mut stops = stops
+            .split(',')
+            .map(|stop| {
+                let (value, color) = stop
+                    .split_once('=')
+                    .or_else(|| stop.split_once('→'))
+                    .ok_or_else(|| format!("expected VALUE=COLOR instead of `{}`", stop))?;
+                let value = value
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number `{}`", value.trim()))?;
+                Ok((value, parse_color(color.trim())?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if stops.is_empty() {
+            return Err(format!("missing colors in `{}`", spec));
+        }
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Ok(ColorScale {
+            field: field.trim().to_string(),
+            stops,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the color between the two closest stops, for numbers and numeric strings.
+    pub fn color(&self, value: &Value) -> Option<Color> {
+        let value = match value {
+            Value::Number(number) => number.as_f64()?,
+            Value::String(string) => string.parse().ok()?,
+            _ => return None,
+        };
+        let upper = self.stops.iter().position(|(stop, _)| value < *stop);
+        let (r, g, b) = match upper {
+            Some(0) => self.stops[0].1,
+            None => self.stops[self.stops.len() - 1].1,
+            Some(index) => {
+                let (low, from) = self.stops[index - 1];
+                let (high, to) = self.stops[index];
+                let ratio = (value - low) / (high - low);
+                let mix =
+                    |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * ratio) as u8;
+                (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+            }
+        };
+        Some(Color::Rgb(r, g, b))
+    }
+}
+
+fn parse_color(color: &str) -> Result<(u8, u8, u8), String> {
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok((r, g, b)),
+            _ => Err(format!("invalid color `{}`", color)),
+        };
+    }
+    COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(color))
+        .map(|(_, rgb)| *rgb)
+        .ok_or_else(|| format!("unknown color `{}`", color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let scale = ColorScale::parse("latency_ms: 0→green, 2000→#ff0000, 500→yellow").unwrap();
+        assert_eq!(scale.field(), "latency_ms");
+        assert_eq!(
+            scale.stops,
+            [
+                (0.0, (80, 200, 80)),
+                (500.0, (230, 200, 40)),
+                (2000.0, (255, 0, 0))
+            ]
+        );
+        assert!(ColorScale::parse("latency_ms").is_err());
+        assert!(ColorScale::parse("latency_ms:0=purple").is_err());
+        assert!(ColorScale::parse("latency_ms:fast=red").is_err());
+    }
+
+    #[test]
+    fn test_color() {
+        let scale = ColorScale::parse("latency_ms:0=#000000,100=#ffffff").unwrap();
+        assert_eq!(scale.color(&json!(-5)), Some(Color::Rgb(0, 0, 0)));
+        assert_eq!(scale.color(&json!(50)), Some(Color::Rgb(127, 127, 127)));
+        assert_eq!(scale.color(&json!("100")), Some(Color::Rgb(255, 255, 255)));
+        assert_eq!(scale.color(&json!(true)), None);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::pipeline::Processed;
+use crate::timestamp;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Audit trail of a session, written to a JSON file when it ends.
+pub struct SessionLog {
+    path: PathBuf,
+    started: DateTime<Utc>,
+    sources: Vec<String>,
+    lines: u64,
+    records: u64,
+    dropped: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl SessionLog {
+    pub fn new(path: PathBuf, sources: Vec<String>) -> Self {
+        SessionLog {
+            path,
+            started: Utc::now(),
+            sources,
+            lines: 0,
+            records: 0,
+            dropped: 0,
+            first: None,
+            last: None,
+        }
+    }
+
+    pub fn count(&mut self, processed: &Processed) {
+        self.lines += 1;
+        let object = match processed {
+            Processed::Unchanged(Some(Value::Object(object)))
+            | Processed::Changed(Value::Object(object)) => object,
+            Processed::Dropped => {
+                self.records += 1;
+                self.dropped += 1;
+                return;
+            }
+            _ => return,
+        };
+        self.records += 1;
+        if let Some(time) = timestamp::detect(object) {
+            self.first.get_or_insert(time);
+            self.last = Some(time);
+        }
+    }
+
+    pub fn write(&self, interrupted: bool) -> io::Result<()> {
+        let format = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Millis, true);
+        let session = json!({
+            "started": format(self.started),
+            "ended": format(Utc::now()),
+            "interrupted": interrupted,
+            "sources": self.sources,
+            "arguments": std::env::args().skip(1).collect::<Vec<_>>(),
+            "counts": {
+                "lines": self.lines,
+                "records": self.records,
+                "dropped": self.dropped,
+            },
+            "first_timestamp": self.first.map(format),
+            "last_timestamp": self.last.map(format),
+        });
+        let mut file = File::create(&self.path)?;
+        serde_json::to_writer_pretty(&mut file, &session)?;
+        writeln!(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count() {
+        let mut session = SessionLog::new(PathBuf::new(), vec!["stdin".to_string()]);
+        for processed in [
+            Processed::Unchanged(Some(json!({"ts": 1633091400}))),
+            Processed::Unchanged(None),
+            Processed::Dropped,
+            Processed::Changed(json!({"ts": 1633091460})),
+            Processed::Unchanged(Some(json!({"msg": "no time"}))),
+        ] {
+            session.count(&processed);
+        }
+        assert_eq!((session.lines, session.records, session.dropped), (5, 4, 1));
+        assert_eq!(session.first.unwrap().timestamp(), 1633091400);
+        assert_eq!(session.last.unwrap().timestamp(), 1633091460);
+    }
+}
@@ -0,0 +1,65 @@
+const UNITS: [(&str, u64); 9] = [
+    ("KIB", 1 << 10),
+    ("MIB", 1 << 20),
+    ("GIB", 1 << 30),
+    ("KB", 1 << 10),
+    ("MB", 1 << 20),
+    ("GB", 1 << 30),
+    ("K", 1 << 10),
+    ("M", 1 << 20),
+    ("G", 1 << 30),
+];
+
+/// Parses a number of bytes with an optional binary unit, like `512`, `10KB` or `1.5G`.
+pub fn parse(size: &str) -> Result<u64, String> {
+    let upper = size.to_uppercase();
+    let (number, factor) = UNITS
+        .iter()
+        .find_map(|(unit, factor)| Some((upper.strip_suffix(unit)?, *factor)))
+        .unwrap_or((upper.strip_suffix('B').unwrap_or(&upper), 1));
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| *number >= 0.0)
+        .map(|number| (number * factor as f64) as u64)
+        .ok_or_else(|| format!("invalid size `{}`", size))
+}
+
+/// Formats a number of bytes with one decimal in the largest fitting unit, like `2.3 KB`.
+pub fn format(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let power = (1..units.len())
+        .rev()
+        .find(|power| bytes >> (10 * power) > 0)
+        .unwrap_or(0);
+    match power {
+        0 => format!("{} B", bytes),
+        _ => format!(
+            "{:.1} {}",
+            bytes as f64 / (1u64 << (10 * power)) as f64,
+            units[power]
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("512"), Ok(512));
+        assert_eq!(parse("10KB"), Ok(10240));
+        assert_eq!(parse("1.5g"), Ok(3 << 29));
+        assert!(parse("-1").is_err());
+        assert!(parse("1T").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(512), "512 B");
+        assert_eq!(format(2355), "2.3 KB");
+        assert_eq!(format(3 << 29), "1.5 GB");
+    }
+}
@@ -0,0 +1,194 @@
+use crate::size;
+use std::fs::File;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+
+/// Part of the input to read, by zero-based line numbers or byte offsets with an exclusive end.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Slice {
+    Lines(u64, Option<u64>),
+    /// Lines starting in the byte range, so the first line is only included if it starts exactly at the start.
+    Bytes(u64, Option<u64>),
+}
+
+impl Slice {
+    /// Parses a range of line numbers counted from 1, like `1000..2000`, `1000..` or `..2000`.
+    pub fn parse_lines(range: &str) -> Result<Self, String> {
+        let (start, end) = parse_range(range, |number| {
+            number
+                .parse::<u64>()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| format!("invalid line number `{}`", number))
+        })?;
+        Ok(Slice::Lines(
+            start.map_or(0, |start| start - 1),
+            end.map(|end| end - 1),
+        ))
+    }
+
+    /// Parses a range of byte offsets with optional units, like `1GB..` or `512M..1G`.
+    pub fn parse_bytes(range: &str) -> Result<Self, String> {
+        let (start, end) = parse_range(range, size::parse)?;
+        Ok(Slice::Bytes(start.unwrap_or(0), end))
+    }
+
+    /// Returns the offset to seek to before reading, which is the byte before the start to find its line.
+    pub fn seek_offset(self) -> Option<u64> {
+        match self {
+            Slice::Bytes(start, _) if start > 0 => Some(start - 1),
+            _ => None,
+        }
+    }
+}
+
+fn parse_range<F>(range: &str, parse: F) -> Result<(Option<u64>, Option<u64>), String>
+where
+    F: Fn(&str) -> Result<u64, String>,
+{
+    let (start, end) = range
+        .split_once("..")
+        .ok_or_else(|| format!("expected START..END instead of `{}`", range))?;
+    let parse = |bound: &str| match bound.trim() {
+        "" => Ok(None),
+        bound => parse(bound).map(Some),
+    };
+    Ok((parse(start)?, parse(end)?))
+}
+
+/// Opens stdin again at the offset to read the slice of a redirected file without reading up to it.
+pub fn open_stdin(slice: Option<Slice>) -> Option<(File, u64)> {
+    let offset = slice?.seek_offset()?;
+    let mut file = stdin_file().ok()?;
+    if !file.metadata().ok()?.is_file() {
+        return None;
+    }
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    Some((file, offset))
+}
+
+#[cfg(unix)]
+fn stdin_file() -> io::Result<File> {
+    File::open("/dev/stdin")
+}
+
+#[cfg(windows)]
+fn stdin_file() -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    Ok(File::from(io::stdin().as_handle().try_clone_to_owned()?))
+}
+
+/// Reads the lines of a slice, skipping to its start by reading if the reader couldn't seek there.
+pub struct SlicedReader<R> {
+    reader: R,
+    slice: Option<Slice>,
+    /// Number of lines or offset of the bytes read so far.
+    position: u64,
+    skipped: bool,
+}
+
+impl<R: BufRead> SlicedReader<R> {
+    pub fn new(reader: R, slice: Option<Slice>, offset: u64) -> Self {
+        SlicedReader {
+            reader,
+            slice,
+            position: offset,
+            skipped: false,
+        }
+    }
+
+    /// Reads the next line including its terminator, returns 0 after the end of the slice.
+    pub fn read_line(&mut self, line: &mut Vec<u8>) -> io::Result<usize> {
+        if !self.skipped {
+            self.skipped = true;
+            self.skip()?;
+        }
+        let read = match self.slice {
+            Some(Slice::Lines(_, Some(end)) | Slice::Bytes(_, Some(end)))
+                if self.position >= end =>
+            {
+                0
+            }
+            _ => self.reader.read_until(b'\n', line)?,
+        };
+        self.position += match self.slice {
+            Some(Slice::Lines(..)) => (read > 0) as u64,
+            _ => read as u64,
+        };
+        Ok(read)
+    }
+
+    fn skip(&mut self) -> io::Result<()> {
+        match self.slice {
+            Some(Slice::Lines(start, _)) => {
+                while self.position < start && self.reader.skip_until(b'\n')? > 0 {
+                    self.position += 1;
+                }
+            }
+            Some(slice @ Slice::Bytes(..)) => {
+                if let Some(offset) = slice.seek_offset() {
+                    let rest = offset.saturating_sub(self.position);
+                    self.position += io::copy(&mut (&mut self.reader).take(rest), &mut io::sink())?;
+                    self.position += self.reader.skip_until(b'\n')? as u64;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(slice: &str, offset: u64) -> Vec<String> {
+        let input = "zero\none\ntwo\nthree\n";
+        let slice = match slice.strip_prefix("bytes ") {
+            Some(range) => Slice::parse_bytes(range),
+            None => Slice::parse_lines(slice),
+        };
+        let mut reader =
+            SlicedReader::new(&input.as_bytes()[offset as usize..], slice.ok(), offset);
+        let mut lines = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                return lines;
+            }
+            lines.push(String::from_utf8(line).unwrap().trim_end().to_string());
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Slice::parse_lines("1000..2000"),
+            Ok(Slice::Lines(999, Some(1999)))
+        );
+        assert_eq!(Slice::parse_lines("..10"), Ok(Slice::Lines(0, Some(9))));
+        assert!(Slice::parse_lines("0..10").is_err());
+        assert!(Slice::parse_lines("10").is_err());
+        assert_eq!(Slice::parse_bytes("1GB.."), Ok(Slice::Bytes(1 << 30, None)));
+        assert_eq!(
+            Slice::parse_bytes("1.5k..2MiB"),
+            Ok(Slice::Bytes(1536, Some(2 << 20)))
+        );
+        assert_eq!(
+            Slice::parse_bytes("10b..20"),
+            Ok(Slice::Bytes(10, Some(20)))
+        );
+        assert!(Slice::parse_bytes("1PB..").is_err());
+    }
+
+    #[test]
+    fn test_slices() {
+        assert_eq!(read("2..4", 0), ["one", "two"]);
+        assert_eq!(read("2..3", 0), ["one"]);
+        assert!(read("2..2", 0).is_empty());
+        assert_eq!(read("4..", 0), ["three"]);
+        assert_eq!(read("bytes 5..", 0), ["one", "two", "three"]);
+        assert_eq!(read("bytes 6..13", 0), ["two"]);
+        assert_eq!(read("bytes 6..13", 5), ["two"]);
+        assert_eq!(read("bytes ..1", 0), ["zero"]);
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::{Map, Value};
+
+/// Weight of the newest value in the moving averages.
+const ALPHA: f64 = 0.05;
+/// Values observed before outliers are reported.
+const WARMUP: u64 = 20;
+/// Distance from the mean in standard deviations to be an outlier.
+const THRESHOLD: f64 = 3.0;
+
+/// Tracks the exponentially weighted mean and variance of a numeric field.
+pub struct Spotlight {
+    field: String,
+    count: u64,
+    mean: f64,
+    variance: f64,
+}
+
+impl Spotlight {
+    pub fn new(field: &str) -> Self {
+        Spotlight {
+            field: field.to_string(),
+            count: 0,
+            mean: 0.0,
+            variance: 0.0,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Adds the value of the record, returns whether it is an outlier compared to the previous ones.
+    pub fn is_outlier(&mut self, object: &Map<String, Value>) -> bool {
+        let value = match object.get(&self.field).and_then(as_number) {
+            Some(value) => value,
+            None => return false,
+        };
+        let diff = value - self.mean;
+        let outlier = self.count >= WARMUP && diff.abs() > THRESHOLD * self.variance.sqrt();
+        if self.count == 0 {
+            self.mean = value;
+        } else {
+            self.mean += ALPHA * diff;
+            self.variance = (1.0 - ALPHA) * (self.variance + ALPHA * diff * diff);
+        }
+        self.count += 1;
+        outlier
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_outlier() {
+        let mut spotlight = Spotlight::new("ms");
+        let mut observe =
+            |value: Value| spotlight.is_outlier(json!({ "ms": value }).as_object().unwrap());
+        for index in 0..WARMUP {
+            assert!(!observe(json!(100 + index % 5)));
+        }
+        assert!(!observe(json!(103)));
+        assert!(observe(json!("2000")));
+        assert!(!observe(json!(null)));
+    }
+}
@@ -0,0 +1,191 @@
+use clap::Args;
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Exit code of curl with `--fail` for HTTP errors, after which the stream isn't retried.
+const HTTP_ERROR: i32 = 22;
+
+/// Follows a stream of Server-Sent Events with curl, formatting their data as records tagged with
+/// their event type, and reconnecting where it left off when the stream ends
+#[derive(Args, Debug)]
+pub struct SseOpt {
+    /// URL of the event stream
+    url: String,
+    /// Header sent with the requests, like `Authorization: Bearer TOKEN`
+    #[clap(
+        short = 'H',
+        long,
+        value_name = "HEADER",
+        multiple_occurrences(true),
+        number_of_values = 1
+    )]
+    header: Vec<String>,
+    /// Time to wait before reconnecting, unless the server sets one with `retry:`
+    #[clap(long, value_name = "DURATION", default_value = "3s", parse(try_from_str = crate::duration::parse))]
+    retry: Duration,
+}
+
+/// Collects the fields of the events of a stream, line by line.
+#[derive(Default)]
+struct EventParser {
+    data: Vec<String>,
+    event: String,
+    /// Id of the last event, sent as `Last-Event-ID` when reconnecting.
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl EventParser {
+    /// Reads a line, returning the type and data of the event a blank line ends, if it has data.
+    fn line(&mut self, line: &str) -> Option<(String, String)> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.event);
+            let data = std::mem::take(&mut self.data);
+            return match data.is_empty() {
+                true => None,
+                false => Some((event, data.join("\n"))),
+            };
+        }
+        let (field, value) = match line.split_once(':') {
+            Some(("", _)) => return None,
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "event" => self.event = value.to_string(),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+pub fn run(opt: &SseOpt) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let url = opt.url.clone();
+    let headers = opt.header.clone();
+    let retry = opt.retry;
+    thread::spawn(move || {
+        if let Err(err) = follow(&url, &headers, retry, &sender) {
+            let _ = sender.send(Err(err));
+        }
+    });
+    crate::print_tagged_lines("event", receiver.into_iter())
+}
+
+/// Sends the events of the stream, reconnecting after it ended unless the server answered with an
+/// HTTP error.
+fn follow(
+    url: &str,
+    headers: &[String],
+    retry: Duration,
+    sender: &Sender<io::Result<(String, String)>>,
+) -> io::Result<()> {
+    let mut parser = EventParser::default();
+    loop {
+        let mut command = Command::new("curl");
+        command.args(["--silent", "--show-error", "--no-buffer", "--fail"]);
+        command.args(["--header", "Accept: text/event-stream"]);
+        for header in headers {
+            command.args(["--header", header]);
+        }
+        if let Some(id) = &parser.last_id {
+            command.args(["--header", &format!("Last-Event-ID: {}", id)]);
+        }
+        let mut child = command
+            .arg(url)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => io::Error::new(err.kind(), "curl not found in PATH"),
+                _ => err,
+            })?;
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        for line in stdout.lines() {
+            let line = line?;
+            let event = parser.line(line.strip_suffix('\r').unwrap_or(&line));
+            if let Some((event, data)) = event {
+                let event = match event.as_str() {
+                    "message" => String::new(),
+                    _ => event,
+                };
+                for record in records(&data) {
+                    if sender.send(Ok((event.clone(), record))).is_err() {
+                        let _ = child.kill();
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        let status = child.wait()?;
+        if status.code() == Some(HTTP_ERROR) {
+            return Err(io::Error::other(format!("{} answered with an error", url)));
+        }
+        // Events which weren't ended by a blank line are incomplete and dropped.
+        parser.line("");
+        let delay = parser.retry.unwrap_or(retry);
+        eprintln!(
+            "ndjson: event stream ended, reconnecting in {}",
+            crate::duration::format(delay)
+        );
+        thread::sleep(delay);
+    }
+}
+
+/// Splits the data of an event into lines, unless it is JSON spread over several `data:` lines,
+/// which is written as one record.
+fn records(data: &str) -> Vec<String> {
+    match serde_json::from_str::<Value>(data) {
+        Ok(value) if data.contains('\n') => vec![value.to_string()],
+        _ => data.split('\n').map(str::to_string).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let mut parser = EventParser::default();
+        let lines = [
+            ": keep-alive",
+            "retry: 5000",
+            r#"data: {"level":"info","#,
+            r#"data:"msg":"a"}"#,
+            "id: 7",
+            "",
+            "event: deploy",
+            "data",
+            "",
+            "event: empty",
+            "",
+            "data: last",
+        ];
+        let events: Vec<_> = lines.iter().filter_map(|line| parser.line(line)).collect();
+        assert_eq!(
+            events,
+            [
+                (
+                    String::new(),
+                    "{\"level\":\"info\",\n\"msg\":\"a\"}".to_string()
+                ),
+                ("deploy".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(parser.last_id.as_deref(), Some("7"));
+        assert_eq!(parser.retry, Some(Duration::from_millis(5000)));
+        assert_eq!(records(&events[0].1), [r#"{"level":"info","msg":"a"}"#]);
+        assert_eq!(records("a\n{\"b\":1}"), ["a", r#"{"b":1}"#]);
+    }
+}
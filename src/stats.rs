@@ -0,0 +1,136 @@
+use crate::format::TokenKind;
+use crate::level::{Level, LevelFields};
+use crate::pipeline::Processed;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// How often the rate is measured.
+pub const INTERVAL: Duration = Duration::from_secs(1);
+/// How often the status line is redrawn at most while lines arrive.
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Counts the lines read for the status line of --stats.
+pub struct Stats {
+    levels: LevelFields,
+    lines: u64,
+    unparsed: u64,
+    by_level: BTreeMap<Level, u64>,
+    /// Start of the current interval and the lines read in it.
+    interval: Instant,
+    in_interval: u64,
+    /// Lines per second in the last interval.
+    rate: f64,
+    drawn: Option<Instant>,
+}
+
+impl Stats {
+    pub fn new(levels: LevelFields, now: Instant) -> Self {
+        Stats {
+            levels,
+            lines: 0,
+            unparsed: 0,
+            by_level: BTreeMap::new(),
+            interval: now,
+            in_interval: 0,
+            rate: 0.0,
+            drawn: None,
+        }
+    }
+
+    /// Counts a line as read, and the level of its record unless it is dropped. Empty lines aren't
+    /// counted as unparsed.
+    pub fn observe(&mut self, line: &[u8], processed: &Processed, now: Instant) {
+        self.tick(now);
+        self.lines += 1;
+        self.in_interval += 1;
+        let object = match processed {
+            Processed::Unchanged(None) if !line.trim_ascii().is_empty() => {
+                self.unparsed += 1;
+                return;
+            }
+            Processed::Unchanged(Some(Value::Object(object)))
+            | Processed::Changed(Value::Object(object)) => object,
+            _ => return,
+        };
+        let level = object
+            .iter()
+            .find_map(|(key, value)| self.levels.level(key, value));
+        if let Some(level) = level {
+            *self.by_level.entry(level).or_default() += 1;
+        }
+    }
+
+    /// Measures the rate once the current interval is over.
+    pub fn tick(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.interval);
+        if elapsed >= INTERVAL {
+            self.rate = self.in_interval as f64 / elapsed.as_secs_f64();
+            self.interval = now;
+            self.in_interval = 0;
+        }
+    }
+
+    /// Returns whether the status line is redrawn after a line, so fast input doesn't redraw it
+    /// for every line.
+    pub fn is_due(&mut self, now: Instant) -> bool {
+        match self.drawn {
+            Some(drawn) if now.saturating_duration_since(drawn) < REDRAW => false,
+            _ => {
+                self.drawn = Some(now);
+                true
+            }
+        }
+    }
+
+    /// Returns the parts of the status line with their kinds, like `1200 lines  35/s  2 unparsed
+    /// error 3  info 1195`, the most severe levels first.
+    pub fn status(&self) -> Vec<(TokenKind, String)> {
+        let mut parts = vec![
+            (TokenKind::Value, format!("{} lines", self.lines)),
+            (TokenKind::Value, format!("{:.0}/s", self.rate)),
+        ];
+        if self.unparsed > 0 {
+            parts.push((TokenKind::Dim, format!("{} unparsed", self.unparsed)));
+        }
+        for (level, count) in self.by_level.iter().rev() {
+            let text = format!("{} {}", level.name(), count);
+            parts.push((TokenKind::Level(*level), text));
+        }
+        parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let start = Instant::now();
+        let mut stats = Stats::new(LevelFields::default(), start);
+        for (line, offset) in [
+            (r#"{"level":"info"}"#, 0),
+            (r#"{"severity":"ERROR"}"#, 100),
+            ("not json", 200),
+            ("", 300),
+            (r#"{"level":"info","msg":"a"}"#, 400),
+        ] {
+            let processed = match serde_json::from_str(line) {
+                Ok(value) => Processed::Unchanged(Some(value)),
+                Err(_) => Processed::Unchanged(None),
+            };
+            stats.observe(
+                line.as_bytes(),
+                &processed,
+                start + Duration::from_millis(offset),
+            );
+        }
+        stats.tick(start + Duration::from_millis(2500));
+        let texts: Vec<String> = stats.status().into_iter().map(|(_, text)| text).collect();
+        assert_eq!(texts, ["5 lines", "2/s", "1 unparsed", "error 1", "info 2"]);
+        assert!(stats.is_due(start));
+        assert!(!stats.is_due(start + Duration::from_millis(50)));
+        assert!(stats.is_due(start + REDRAW));
+    }
+}
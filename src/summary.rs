@@ -0,0 +1,214 @@
+use crate::level::{self, Level};
+use crate::{duration, timestamp, OutputFormat};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+const MESSAGE_FIELDS: [&str; 4] = ["msg", "message", "error", "err"];
+const TOP_ERRORS: usize = 5;
+
+/// Statistics of the whole input, reported after it ended.
+pub struct Summary {
+    records: u64,
+    parse_failures: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+    levels: BTreeMap<Level, u64>,
+    errors: HashMap<String, u64>,
+    format: OutputFormat,
+    ascii: bool,
+}
+
+impl Summary {
+    pub fn new(format: OutputFormat, ascii: bool) -> Self {
+        Summary {
+            records: 0,
+            parse_failures: 0,
+            first: None,
+            last: None,
+            levels: BTreeMap::new(),
+            errors: HashMap::new(),
+            format,
+            ascii,
+        }
+    }
+
+    pub fn add_record(&mut self, object: &Map<String, Value>) {
+        self.records += 1;
+        if let Some(time) = timestamp::detect(object) {
+            self.first = Some(self.first.map_or(time, |first| first.min(time)));
+            self.last = Some(self.last.map_or(time, |last| last.max(time)));
+        }
+        let level = match level::detect(object) {
+            Some(level) => level,
+            None => return,
+        };
+        *self.levels.entry(level).or_default() += 1;
+        if level >= Level::Error {
+            let message = MESSAGE_FIELDS
+                .iter()
+                .find_map(|field| object.get(*field))
+                .map(|message| match message {
+                    Value::String(message) => message.clone(),
+                    message => message.to_string(),
+                });
+            if let Some(message) = message {
+                *self.errors.entry(message).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn add_unparsed(&mut self) {
+        self.parse_failures += 1;
+    }
+
+    pub fn report<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            return writeln!(writer, "{}", self.to_json());
+        }
+        let (rule, ellipsis) = match self.ascii {
+            true => ("--", "..."),
+            false => ("──", "…"),
+        };
+        writeln!(writer, "{} summary {}", rule, rule)?;
+        writeln!(writer, "records:        {}", self.records)?;
+        writeln!(writer, "parse failures: {}", self.parse_failures)?;
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            writeln!(
+                writer,
+                "time range:     {} {} {} ({})",
+                first.to_rfc3339_opts(SecondsFormat::Secs, true),
+                ellipsis,
+                last.to_rfc3339_opts(SecondsFormat::Secs, true),
+                duration::format((last - first).to_std().unwrap_or_default())
+            )?;
+        }
+        if !self.levels.is_empty() {
+            let levels: Vec<String> = self
+                .levels
+                .iter()
+                .rev()
+                .map(|(level, count)| format!("{} {}", level.name(), count))
+                .collect();
+            writeln!(writer, "levels:         {}", levels.join(", "))?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(writer, "top errors:")?;
+            for (message, count) in self.top_errors() {
+                writeln!(writer, "{:>7}  {}", count, message)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        let time_range = match (self.first, self.last) {
+            (Some(first), Some(last)) => json!({
+                "first": first.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                "last": last.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                "seconds": (last - first).num_milliseconds() as f64 / 1000.0,
+            }),
+            _ => Value::Null,
+        };
+        let levels: Map<String, Value> = self
+            .levels
+            .iter()
+            .rev()
+            .map(|(level, count)| (level.name().to_string(), json!(count)))
+            .collect();
+        let top_errors: Vec<Value> = self
+            .top_errors()
+            .into_iter()
+            .map(|(message, count)| json!({"message": message, "count": count}))
+            .collect();
+        json!({
+            "records": self.records,
+            "parse_failures": self.parse_failures,
+            "time_range": time_range,
+            "levels": levels,
+            "top_errors": top_errors,
+        })
+    }
+
+    fn top_errors(&self) -> Vec<(&String, &u64)> {
+        let mut errors: Vec<_> = self.errors.iter().collect();
+        errors.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        errors.truncate(TOP_ERRORS);
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn summary(format: OutputFormat) -> Summary {
+        let mut summary = Summary::new(format, false);
+        for record in [
+            json!({"time": "2021-10-01T12:00:00Z", "level": "info", "msg": "started"}),
+            json!({"time": "2021-10-01T12:01:30Z", "level": "error", "msg": "timeout"}),
+            json!({"time": "2021-10-01T12:00:30Z", "level": "error", "msg": "refused"}),
+            json!({"level": "error", "msg": "timeout"}),
+        ] {
+            summary.add_record(record.as_object().unwrap());
+        }
+        summary.add_unparsed();
+        summary
+    }
+
+    #[test]
+    fn test_report() {
+        let summary = summary(OutputFormat::Text);
+        let mut report = Vec::new();
+        summary.report(&mut report).unwrap();
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "── summary ──
+records:        4
+parse failures: 1
+time range:     2021-10-01T12:00:00Z … 2021-10-01T12:01:30Z (1m 30s)
+levels:         error 3, info 1
+top errors:
+      2  timeout
+      1  refused
+"
+        );
+    }
+
+    #[test]
+    fn test_ascii_report() {
+        let mut summary = summary(OutputFormat::Text);
+        summary.ascii = true;
+        let mut report = Vec::new();
+        summary.report(&mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        assert!(report.starts_with("-- summary --\n"));
+        assert!(report.contains("2021-10-01T12:00:00Z ... 2021-10-01T12:01:30Z"));
+        assert!(report.is_ascii());
+    }
+
+    #[test]
+    fn test_json_report() {
+        let mut report = Vec::new();
+        summary(OutputFormat::Json).report(&mut report).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&report).unwrap(),
+            json!({
+                "records": 4,
+                "parse_failures": 1,
+                "time_range": {
+                    "first": "2021-10-01T12:00:00Z",
+                    "last": "2021-10-01T12:01:30Z",
+                    "seconds": 90.0
+                },
+                "levels": {"error": 3, "info": 1},
+                "top_errors": [
+                    {"message": "timeout", "count": 2},
+                    {"message": "refused", "count": 1}
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use crate::timestamp;
+use serde_json::{Map, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Length of the record quoted by a rollup.
+const MAX_QUOTE: usize = 120;
+
+/// Suppresses records repeating one seen within a window, ignoring volatile fields like the time,
+/// and rolls up the repeats of each window once it ended.
+pub struct RepeatSuppressor {
+    window: Duration,
+    volatile: Vec<String>,
+    windows: HashMap<u64, Repeats>,
+    /// Starts of the windows, oldest first.
+    order: VecDeque<(Instant, u64)>,
+    suppressed: u64,
+}
+
+/// The record starting a window with the number of its repeats.
+struct Repeats {
+    record: String,
+    count: u64,
+}
+
+impl RepeatSuppressor {
+    /// Creates a suppressor ignoring the timestamp fields and the given ones.
+    pub fn new(window: Duration, volatile: Vec<String>) -> Self {
+        let volatile = timestamp::FIELDS
+            .iter()
+            .map(|field| field.to_string())
+            .chain(volatile)
+            .collect();
+        RepeatSuppressor {
+            window,
+            volatile,
+            windows: HashMap::new(),
+            order: VecDeque::new(),
+            suppressed: 0,
+        }
+    }
+
+    /// Returns whether the record repeats one seen within the window, otherwise it starts one.
+    pub fn is_repeat(&mut self, object: &Map<String, Value>, now: Instant) -> bool {
+        let stable: Map<String, Value> = object
+            .iter()
+            .filter(|(key, _)| !self.volatile.contains(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let record = Value::Object(stable).to_string();
+        let mut hasher = DefaultHasher::new();
+        record.hash(&mut hasher);
+        let hash = hasher.finish();
+        if let Some(repeats) = self.windows.get_mut(&hash) {
+            repeats.count += 1;
+            self.suppressed += 1;
+            return true;
+        }
+        self.windows.insert(hash, Repeats { record, count: 0 });
+        self.order.push_back((now, hash));
+        false
+    }
+
+    /// Ends the windows which are over, returning a message for each one with repeats.
+    pub fn expire(&mut self, now: Instant) -> Vec<String> {
+        let mut rollups = Vec::new();
+        while let Some((start, hash)) = self.order.front().copied() {
+            if now.duration_since(start) < self.window {
+                break;
+            }
+            self.order.pop_front();
+            match self.windows.remove(&hash) {
+                Some(repeats) if repeats.count > 0 => rollups.push(format!(
+                    "suppressed {} repeats of {}",
+                    repeats.count,
+                    quote(&repeats.record)
+                )),
+                _ => {}
+            }
+        }
+        rollups
+    }
+
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed
+    }
+}
+
+fn quote(record: &str) -> String {
+    match record.char_indices().nth(MAX_QUOTE) {
+        Some((end, _)) => format!("{}...", &record[..end]),
+        None => record.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_suppress() {
+        let mut suppressor =
+            RepeatSuppressor::new(Duration::from_secs(10), vec!["pid".to_string()]);
+        let start = Instant::now();
+        let record = |ts: u64, pid: u64| {
+            json!({"ts": ts, "pid": pid, "msg": "crash"})
+                .as_object()
+                .unwrap()
+                .clone()
+        };
+        assert!(!suppressor.is_repeat(&record(1, 10), start));
+        assert!(suppressor.is_repeat(&record(2, 11), start + Duration::from_secs(1)));
+        assert!(suppressor.is_repeat(&record(3, 12), start + Duration::from_secs(2)));
+        assert!(suppressor.expire(start + Duration::from_secs(9)).is_empty());
+        let later = start + Duration::from_secs(10);
+        assert_eq!(
+            suppressor.expire(later),
+            [r#"suppressed 2 repeats of {"msg":"crash"}"#]
+        );
+        assert!(!suppressor.is_repeat(&record(4, 13), later));
+        assert_eq!(suppressor.suppressed(), 2);
+    }
+}
@@ -0,0 +1,201 @@
+use serde_json::{Map, Value};
+
+const SEVERITIES: [&str; 8] = [
+    "emergency",
+    "alert",
+    "critical",
+    "error",
+    "warning",
+    "notice",
+    "info",
+    "debug",
+];
+
+const FACILITIES: [&str; 24] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv",
+    "ftp", "ntp", "audit", "alert", "clock", "local0", "local1", "local2", "local3", "local4",
+    "local5", "local6", "local7",
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parses a syslog line in the RFC 5424 format, like `<34>1 2003-10-11T22:14:15.003Z host app 12
+/// ID47 - message`, or the RFC 3164 one, like `<34>Oct 11 22:14:15 host app[12]: message`, whose
+/// priority is missing in files. The priority becomes `severity` and `facility` fields, and the
+/// fields of a message which is a JSON object are added to those of the envelope.
+pub fn parse(line: &[u8]) -> Option<Map<String, Value>> {
+    let line = std::str::from_utf8(line).ok()?.trim_end();
+    let mut object = Map::new();
+    let mut rest = line;
+    if let Some(after) = line.strip_prefix('<') {
+        let (priority, after) = after.split_once('>')?;
+        let priority: usize = match priority.len() {
+            1..=3 => priority.parse().ok()?,
+            _ => return None,
+        };
+        object.insert("severity".to_string(), SEVERITIES[priority % 8].into());
+        object.insert(
+            "facility".to_string(),
+            (*FACILITIES.get(priority / 8)?).into(),
+        );
+        rest = after;
+    }
+    let message = match rest.strip_prefix("1 ") {
+        Some(rest) => parse_5424(rest, &mut object)?,
+        None => parse_3164(rest, &mut object)?,
+    };
+    let message = message.trim_start_matches('\u{feff}');
+    match serde_json::from_str(message) {
+        Ok(Value::Object(fields)) => object.extend(fields),
+        _ => {
+            object.insert("msg".to_string(), message.into());
+        }
+    }
+    Some(object)
+}
+
+/// Reads the header and structured data of an RFC 5424 line after its version, returning the
+/// message. Nil values `-` are left out.
+fn parse_5424<'a>(rest: &'a str, object: &mut Map<String, Value>) -> Option<&'a str> {
+    let mut rest = rest;
+    for key in ["time", "host", "app", "pid", "msgid"] {
+        let (value, after) = rest.split_once(' ').unwrap_or((rest, ""));
+        if value.is_empty() {
+            return None;
+        }
+        if value != "-" {
+            object.insert(key.to_string(), value.into());
+        }
+        rest = after;
+    }
+    if let Some(after) = rest.strip_prefix('-') {
+        return Some(after.strip_prefix(' ').unwrap_or(after));
+    }
+    let mut data = Map::new();
+    while let Some(after) = rest.strip_prefix('[') {
+        let end = after.find([' ', ']'])?;
+        let mut params = Map::new();
+        rest = &after[end..];
+        while let Some(after) = rest.strip_prefix(' ') {
+            let (name, after) = after.split_once("=\"")?;
+            let mut value = String::new();
+            let mut chars = after.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (_, '\\') => value.push(chars.next()?.1),
+                    (index, '"') => break index,
+                    (_, c) => value.push(c),
+                }
+            };
+            params.insert(name.to_string(), value.into());
+            rest = &after[end + 1..];
+        }
+        rest = rest.strip_prefix(']')?;
+        data.insert(after[..end].to_string(), Value::Object(params));
+    }
+    if !data.is_empty() {
+        object.insert("structured_data".to_string(), Value::Object(data));
+    }
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+/// Reads the timestamp, host and tag of an RFC 3164 line, returning the message.
+fn parse_3164<'a>(rest: &'a str, object: &mut Map<String, Value>) -> Option<&'a str> {
+    let time = rest.get(..15)?;
+    let valid = time.is_ascii()
+        && MONTHS.contains(&&time[..3])
+        && time[3..].starts_with(' ')
+        && time[4..6].trim_start().parse::<u8>().is_ok()
+        && time.as_bytes()[9] == b':'
+        && time.as_bytes()[12] == b':';
+    if !valid || !rest[15..].starts_with(' ') {
+        return None;
+    }
+    object.insert("time".to_string(), time.into());
+    let (host, rest) = rest[16..].split_once(' ')?;
+    object.insert("host".to_string(), host.into());
+    let (tag, message) = match rest.split_once(": ") {
+        Some((tag, message)) if !tag.contains(' ') => (tag, message),
+        _ => return Some(rest),
+    };
+    match tag.strip_suffix(']').and_then(|tag| tag.split_once('[')) {
+        Some((app, pid)) => {
+            object.insert("app".to_string(), app.into());
+            object.insert("pid".to_string(), pid.into());
+        }
+        None => {
+            object.insert("app".to_string(), tag.into());
+        }
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parsed(line: &str) -> Option<Value> {
+        parse(line.as_bytes()).map(Value::Object)
+    }
+
+    #[test]
+    fn test_parse_5424() {
+        assert_eq!(
+            parsed(
+                r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="App\"lication"][other@1] An application event"#
+            ),
+            Some(json!({
+                "severity": "notice",
+                "facility": "local4",
+                "time": "2003-10-11T22:14:15.003Z",
+                "host": "mymachine.example.com",
+                "app": "evntslog",
+                "msgid": "ID47",
+                "structured_data": {
+                    "exampleSDID@32473": {"iut": "3", "eventSource": "App\"lication"},
+                    "other@1": {},
+                },
+                "msg": "An application event",
+            }))
+        );
+        assert_eq!(
+            parsed(r#"<11>1 2024-01-01T00:00:00Z web api 42 - - {"level":"error","msg":"boom"}"#),
+            Some(json!({
+                "severity": "error",
+                "facility": "user",
+                "time": "2024-01-01T00:00:00Z",
+                "host": "web",
+                "app": "api",
+                "pid": "42",
+                "level": "error",
+                "msg": "boom",
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_3164() {
+        assert_eq!(
+            parsed("<34>Oct  1 22:14:15 mymachine su[230]: 'su root' failed on /dev/pts/8"),
+            Some(json!({
+                "severity": "critical",
+                "facility": "auth",
+                "time": "Oct  1 22:14:15",
+                "host": "mymachine",
+                "app": "su",
+                "pid": "230",
+                "msg": "'su root' failed on /dev/pts/8",
+            }))
+        );
+        assert_eq!(
+            parsed(r#"Oct 11 22:14:15 web api: {"status":500}"#),
+            Some(json!({"time": "Oct 11 22:14:15", "host": "web", "app": "api", "status": 500}))
+        );
+        assert_eq!(parsed("<999>1 - - - - -"), None);
+        assert_eq!(parsed("<34>not syslog"), None);
+        assert_eq!(parsed("plain text line"), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::timestamp;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+/// A file path with field placeholders and strftime specifiers, like
+/// `logs/{service}/%Y-%m-%d.ndjson`, rendered per record.
+#[derive(Clone, Debug)]
+pub struct PathTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    /// Text which may contain strftime specifiers.
+    Text(String),
+    Field(String),
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed `{{` in `{}`", template))?;
+            parts.push(Part::Text(rest[..open].to_string()));
+            parts.push(Part::Field(rest[open + 1..open + close].to_string()));
+            rest = &rest[open + close + 1..];
+        }
+        parts.push(Part::Text(rest.to_string()));
+        for part in &parts {
+            if let Part::Text(text) = part {
+                if StrftimeItems::new(text).any(|item| item == Item::Error) {
+                    return Err(format!("invalid strftime specifier in `{}`", template));
+                }
+            }
+        }
+        parts.retain(|part| *part != Part::Text(String::new()));
+        Ok(PathTemplate { parts })
+    }
+
+    /// Whether the path is the same for all records.
+    pub fn is_fixed(&self) -> bool {
+        self.parts
+            .iter()
+            .all(|part| matches!(part, Part::Text(text) if !text.contains('%')))
+    }
+
+    /// Renders the path for a record at the time of its timestamp, or the current time without
+    /// one. Field values can't leave the directory, missing fields are written as `-`.
+    pub fn render(&self, object: &Map<String, Value>) -> PathBuf {
+        let mut time = None;
+        let mut path = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) if text.contains('%') => {
+                    let time: &DateTime<Utc> = time
+                        .get_or_insert_with(|| timestamp::detect(object).unwrap_or_else(Utc::now));
+                    path.push_str(&time.format(text).to_string());
+                }
+                Part::Text(text) => path.push_str(text),
+                Part::Field(field) => {
+                    let value = match object.get(field) {
+                        Some(Value::String(string)) => string.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(value) => value.to_string(),
+                    };
+                    path.push_str(&sanitize(&value));
+                }
+            }
+        }
+        PathBuf::from(path)
+    }
+}
+
+/// Replaces path separators and names with a special meaning in a field value.
+fn sanitize(value: &str) -> String {
+    match value {
+        "" => "-".to_string(),
+        "." | ".." => value.replace('.', "_"),
+        value => value.replace(['/', '\\', '\0'], "_"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, record: Value) -> String {
+        PathTemplate::parse(template)
+            .unwrap()
+            .render(record.as_object().unwrap())
+            .display()
+            .to_string()
+    }
+
+    #[test]
+    fn test_render() {
+        let record = json!({"service": "web", "ts": "2023-01-02T03:04:05Z"});
+        assert_eq!(
+            render("logs/{service}/%Y-%m-%d.ndjson", record),
+            "logs/web/2023-01-02.ndjson"
+        );
+        assert_eq!(
+            render(
+                "{service}-{code}.log",
+                json!({"service": "../etc", "code": 5})
+            ),
+            ".._etc-5.log"
+        );
+        assert_eq!(
+            render("{service}/{a}.log", json!({"service": ".."})),
+            "__/-.log"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(PathTemplate::parse("errors.ndjson").unwrap().is_fixed());
+        assert!(!PathTemplate::parse("{level}.ndjson").unwrap().is_fixed());
+        assert!(!PathTemplate::parse("%Y.ndjson").unwrap().is_fixed());
+        assert!(PathTemplate::parse("{level.ndjson").is_err());
+        assert!(PathTemplate::parse("%Q.ndjson").is_err());
+    }
+}
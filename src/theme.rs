@@ -0,0 +1,185 @@
+use crate::level::Level;
+use std::collections::HashMap;
+use termcolor::{Color, ColorSpec};
+
+/// Names of the token kinds which can be colored by the theme.
+pub const KINDS: [&str; 12] = [
+    "key",
+    "value",
+    "true",
+    "false",
+    "null",
+    "dim",
+    "string",
+    "secret",
+    "spotlight",
+    "alert",
+    "error",
+    "highlight",
+];
+pub const LEVELS: [Level; 6] = [
+    Level::Trace,
+    Level::Debug,
+    Level::Info,
+    Level::Warn,
+    Level::Error,
+    Level::Fatal,
+];
+
+/// Names of the basic colors, which are intense unless prefixed with `dark-`.
+pub const COLORS: [(&str, Color); 8] = [
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("white", Color::White),
+];
+
+/// A foreground color with an optional background.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Style {
+    foreground: Color,
+    background: Option<Color>,
+    intense: bool,
+}
+
+impl Style {
+    /// Parses colors like `blue`, `dark-blue`, `208` of the 256-color palette or `#ffaa00`,
+    /// optionally with a background like `white on red`.
+    pub fn parse(style: &str) -> Result<Self, String> {
+        let (foreground, background) = match style.split_once(" on ") {
+            Some((foreground, background)) => (foreground, Some(background)),
+            None => (style, None),
+        };
+        let (foreground, intense) = parse_color(foreground.trim())?;
+        Ok(Style {
+            foreground,
+            background: background
+                .map(|background| parse_color(background.trim()))
+                .transpose()?
+                .map(|(color, _)| color),
+            intense,
+        })
+    }
+
+    pub fn spec(&self) -> ColorSpec {
+        let mut spec = ColorSpec::new();
+        spec.set_fg(Some(self.foreground))
+            .set_bg(self.background)
+            .set_intense(self.intense);
+        spec
+    }
+}
+
+/// Returns a color with whether it is the intense variant, which basic color names are unless
+/// prefixed with `dark-`.
+fn parse_color(color: &str) -> Result<(Color, bool), String> {
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |index: usize| {
+            hex.get(index..index + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(red), Some(green), Some(blue)) => Ok((Color::Rgb(red, green, blue), false)),
+            _ => Err(format!("invalid color `{}`", color)),
+        };
+    }
+    if let Ok(index) = color.parse::<u8>() {
+        return Ok((Color::Ansi256(index), false));
+    }
+    let (name, intense) = match color.strip_prefix("dark-") {
+        Some(name) => (name, false),
+        None => (color, true),
+    };
+    match COLORS.iter().find(|(known, _)| *known == name) {
+        Some((_, color)) => Ok((*color, intense)),
+        None => Err(format!("invalid color `{}`", color)),
+    }
+}
+
+/// Colors replacing the default ones for token kinds and levels.
+#[derive(Clone, Default, Debug)]
+pub struct Theme {
+    kinds: HashMap<String, Style>,
+    levels: HashMap<Level, Style>,
+}
+
+impl Theme {
+    /// Sets the color of a token kind like `key` or `string`.
+    pub fn set(&mut self, kind: &str, style: &str) -> Result<(), String> {
+        if !KINDS.contains(&kind) {
+            return Err(format!(
+                "unknown token kind `{}`, expected one of {}",
+                kind,
+                KINDS.join(", ")
+            ));
+        }
+        self.kinds.insert(kind.to_string(), Style::parse(style)?);
+        Ok(())
+    }
+
+    /// Sets the color of the values of a level like `warn`.
+    pub fn set_level(&mut self, level: &str, style: &str) -> Result<(), String> {
+        let level = LEVELS
+            .iter()
+            .find(|known| known.name() == level)
+            .ok_or_else(|| format!("unknown level `{}`", level))?;
+        self.levels.insert(*level, Style::parse(style)?);
+        Ok(())
+    }
+
+    pub fn kind(&self, kind: &str) -> Option<&Style> {
+        self.kinds.get(kind)
+    }
+
+    pub fn level(&self, level: Level) -> Option<&Style> {
+        self.levels.get(&level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(
+            Style::parse("blue").unwrap().spec(),
+            ColorSpec::new()
+                .set_fg(Some(Color::Blue))
+                .set_intense(true)
+                .clone()
+        );
+        assert_eq!(
+            Style::parse("dark-white on 52").unwrap().spec(),
+            ColorSpec::new()
+                .set_fg(Some(Color::White))
+                .set_bg(Some(Color::Ansi256(52)))
+                .clone()
+        );
+        assert_eq!(
+            Style::parse("#ffaa00").unwrap().spec(),
+            ColorSpec::new()
+                .set_fg(Some(Color::Rgb(255, 170, 0)))
+                .clone()
+        );
+        assert!(Style::parse("#ffaa").is_err());
+        assert!(Style::parse("purple").is_err());
+        assert!(Style::parse("256").is_err());
+    }
+
+    #[test]
+    fn test_theme() {
+        let mut theme = Theme::default();
+        assert!(theme.set("key", "blue").is_ok());
+        assert!(theme.set("keys", "blue").is_err());
+        assert!(theme.set_level("warn", "208").is_ok());
+        assert!(theme.set_level("warning", "208").is_err());
+        assert!(theme.kind("key").is_some());
+        assert!(theme.level(Level::Warn).is_some());
+        assert!(theme.level(Level::Error).is_none());
+    }
+}
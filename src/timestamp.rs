@@ -0,0 +1,151 @@
+use crate::duration;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde_json::{Map, Value};
+
+pub const FIELDS: [&str; 4] = ["time", "ts", "timestamp", "@timestamp"];
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+/// How the values of timestamp fields are written, in local time or relative to now like `3s ago`.
+#[derive(Clone, Debug)]
+pub struct TimeDisplay {
+    fields: Vec<String>,
+    format: String,
+    relative: bool,
+}
+
+impl TimeDisplay {
+    /// Creates a display for the given fields, the known timestamp fields if there are none.
+    pub fn new(fields: Vec<String>, format: String, relative: bool) -> Self {
+        let fields = match fields.is_empty() {
+            true => FIELDS.iter().map(|field| field.to_string()).collect(),
+            false => fields,
+        };
+        TimeDisplay {
+            fields,
+            format,
+            relative,
+        }
+    }
+
+    /// Returns the readable time of a timestamp field, `None` for other fields and values which
+    /// aren't a timestamp.
+    pub fn render(&self, key: &str, value: &Value, now: DateTime<Utc>) -> Option<String> {
+        if !self.fields.iter().any(|field| field == key) {
+            return None;
+        }
+        let time = parse(value)?;
+        if !self.relative {
+            return Some(time.with_timezone(&Local).format(&self.format).to_string());
+        }
+        let elapsed = now.signed_duration_since(time);
+        Some(match elapsed.to_std() {
+            Ok(elapsed) => format!("{} ago", duration::format(elapsed)),
+            Err(_) => format!("in {}", duration::format((-elapsed).to_std().ok()?)),
+        })
+    }
+}
+
+/// Checks the strftime specifiers of a time format like `%H:%M:%S`.
+pub fn parse_format(format: &str) -> Result<String, String> {
+    match StrftimeItems::new(format).any(|item| item == Item::Error) {
+        true => Err(format!("invalid strftime specifier in `{}`", format)),
+        false => Ok(format.to_string()),
+    }
+}
+
+/// Returns the time of the first known timestamp field.
+pub fn detect(object: &Map<String, Value>) -> Option<DateTime<Utc>> {
+    FIELDS
+        .iter()
+        .find_map(|field| object.get(*field))
+        .and_then(parse)
+}
+
+/// Parses ISO-8601 strings, assuming UTC without an offset, and epoch seconds, millis, micros or nanos.
+pub fn parse(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::Number(number) => from_epoch(number.as_f64()?),
+        Value::String(string) => {
+            if let Ok(time) = DateTime::parse_from_rfc3339(string) {
+                return Some(time.with_timezone(&Utc));
+            }
+            for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+                if let Ok(time) = NaiveDateTime::parse_from_str(string, format) {
+                    return Some(Utc.from_utc_datetime(&time));
+                }
+            }
+            if let Ok(time) = DateTime::parse_from_str(string, "%Y-%m-%d %H:%M:%S%.f %z") {
+                return Some(time.with_timezone(&Utc));
+            }
+            from_epoch(string.parse().ok()?)
+        }
+        _ => None,
+    }
+}
+
+fn from_epoch(number: f64) -> Option<DateTime<Utc>> {
+    let nanos = match number.abs() {
+        n if n < 1e11 => number * 1e9,
+        n if n < 1e14 => number * 1e6,
+        n if n < 1e17 => number * 1e3,
+        _ => number,
+    };
+    Some(Utc.timestamp_nanos(nanos as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let expected = Utc.with_ymd_and_hms(2021, 10, 1, 12, 30, 0).unwrap();
+        for value in [
+            json!("2021-10-01T12:30:00Z"),
+            json!("2021-10-01T14:30:00+02:00"),
+            json!("2021-10-01T12:30:00"),
+            json!("2021-10-01 12:30:00.000"),
+            json!(1633091400),
+            json!(1633091400000u64),
+            json!(1633091400000000u64),
+            json!(1633091400000000000u64),
+            json!("1633091400"),
+        ] {
+            assert_eq!(parse(&value), Some(expected), "{}", value);
+        }
+        assert_eq!(parse(&json!("yesterday")), None);
+    }
+
+    #[test]
+    fn test_time_display() {
+        let now = Utc.with_ymd_and_hms(2021, 10, 1, 12, 30, 0).unwrap();
+        let relative = TimeDisplay::new(Vec::new(), DEFAULT_FORMAT.to_string(), true);
+        assert_eq!(
+            relative.render("ts", &json!("2021-10-01T12:29:57Z"), now),
+            Some("3s ago".to_string())
+        );
+        assert_eq!(
+            relative.render("time", &json!(1633091400000u64 + 3_720_000), now),
+            Some("in 1h 2m".to_string())
+        );
+        assert_eq!(relative.render("msg", &json!(1633091400), now), None);
+        assert_eq!(relative.render("ts", &json!("soon"), now), None);
+        let epoch = TimeDisplay::new(vec!["at".to_string()], "%s".to_string(), false);
+        assert_eq!(
+            epoch.render("at", &json!("2021-10-01T12:30:00Z"), now),
+            Some("1633091400".to_string())
+        );
+        assert_eq!(epoch.render("ts", &json!(1633091400), now), None);
+        assert!(parse_format("%H:%M").is_ok());
+        assert!(parse_format("%Q").is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        let record = json!({"msg": "hi", "@timestamp": "2021-10-01T12:30:00Z"});
+        assert!(detect(record.as_object().unwrap()).is_some());
+        assert!(detect(json!({"msg": "hi"}).as_object().unwrap()).is_none());
+    }
+}
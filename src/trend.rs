@@ -0,0 +1,116 @@
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+
+/// Values shown unless a count is given like `queue_depth:50`.
+const DEFAULT_LENGTH: usize = 20;
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const ASCII_BARS: [char; 8] = ['_', '.', '-', '~', '=', '+', '#', '@'];
+
+/// Keeps the last values of a numeric field to draw them as a sparkline.
+#[derive(Clone, Debug)]
+pub struct Trend {
+    field: String,
+    length: usize,
+    values: VecDeque<f64>,
+}
+
+impl Trend {
+    /// Parses a field with an optional number of values, like `queue_depth` or `queue_depth:50`.
+    pub fn parse(trend: &str) -> Result<Self, String> {
+        let (field, length) = match trend.rsplit_once(':') {
+            Some((field, length)) => {
+                let length = length
+                    .parse()
+                    .ok()
+                    .filter(|length| *length > 1)
+                    .ok_or_else(|| format!("invalid number of values `{}`", length))?;
+                (field, length)
+            }
+            None => (trend, DEFAULT_LENGTH),
+        };
+        Ok(Trend {
+            field: field.to_string(),
+            length,
+            values: VecDeque::new(),
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Adds the value of the record, returns the sparkline up to it if the record has one.
+    pub fn observe(&mut self, object: &Map<String, Value>) -> Option<String> {
+        let value = object.get(&self.field).and_then(as_number)?;
+        if self.values.len() == self.length {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        Some(self.sparkline())
+    }
+
+    /// Draws the values scaled between the smallest and the largest one.
+    fn sparkline(&self) -> String {
+        let min = self.values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self
+            .values
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        self.values
+            .iter()
+            .map(|value| match max - min {
+                range if range > 0.0 => {
+                    BARS[((value - min) / range * (BARS.len() - 1) as f64).round() as usize]
+                }
+                _ => BARS[BARS.len() / 2],
+            })
+            .collect()
+    }
+}
+
+/// Replaces the bars of a sparkline with ASCII characters of a similar height.
+pub fn to_ascii(sparkline: &str) -> String {
+    sparkline
+        .chars()
+        .map(|c| match BARS.iter().position(|bar| *bar == c) {
+            Some(index) => ASCII_BARS[index],
+            None => c,
+        })
+        .collect()
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.parse().ok().filter(|value: &f64| value.is_finite()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Trend::parse("queue_depth").unwrap().length, DEFAULT_LENGTH);
+        assert_eq!(Trend::parse("a:b:5").unwrap().field, "a:b");
+        assert!(Trend::parse("queue_depth:1").is_err());
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut trend = Trend::parse("depth:4").unwrap();
+        let mut observe =
+            |value: Value| trend.observe(json!({ "depth": value }).as_object().unwrap());
+        assert_eq!(observe(json!(3)).as_deref(), Some("▅"));
+        assert_eq!(observe(json!(null)), None);
+        assert_eq!(observe(json!(0)).as_deref(), Some("█▁"));
+        assert_eq!(observe(json!("7")).as_deref(), Some("▄▁█"));
+        assert_eq!(observe(json!(7)).as_deref(), Some("▄▁██"));
+        assert_eq!(observe(json!(14)).as_deref(), Some("▁▅▅█"));
+        assert_eq!(to_ascii("▁▅▅█"), "_==@");
+    }
+}
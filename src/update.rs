@@ -0,0 +1,227 @@
+use clap::Args;
+use semver::Version;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::Command;
+
+const RELEASES: &str = "https://api.github.com/repos/rojul/ndjson/releases/latest";
+
+/// Name of the release asset listing the SHA-256 checksums of the binaries.
+const CHECKSUMS: &str = "SHA256SUMS";
+
+/// Replaces the binary by the one of the latest GitHub release for this platform, after verifying
+/// its checksum
+#[derive(Args, Debug)]
+pub struct UpdateOpt {
+    /// Only reports whether a newer release exists
+    #[clap(long)]
+    check: bool,
+}
+
+pub fn run(opt: &UpdateOpt) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let release: Value = serde_json::from_slice(&download(RELEASES)?)?;
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| invalid("release without a tag".to_string()))?;
+    let current = env!("CARGO_PKG_VERSION");
+    match compare(tag, current)? {
+        Ordering::Less => {
+            println!(
+                "ndjson {} is newer than the latest release {}",
+                current, tag
+            );
+            return Ok(());
+        }
+        Ordering::Equal => {
+            println!("ndjson {} is up to date", current);
+            return Ok(());
+        }
+        Ordering::Greater => {}
+    }
+    if opt.check {
+        println!("ndjson {} is available, {} is installed", tag, current);
+        return Ok(());
+    }
+    let name = asset_name(env::consts::ARCH, env::consts::OS);
+    let binary_url = asset_url(&release, &name)
+        .ok_or_else(|| invalid(format!("release {} has no binary {}", tag, name)))?;
+    let checksums_url = asset_url(&release, CHECKSUMS)
+        .ok_or_else(|| invalid(format!("release {} has no {}", tag, CHECKSUMS)))?;
+    let checksums = String::from_utf8_lossy(&download(checksums_url)?).into_owned();
+    let expected = checksum(&checksums, &name)
+        .ok_or_else(|| invalid(format!("{} lacks a checksum of {}", CHECKSUMS, name)))?;
+    let binary = download(binary_url)?;
+    let actual = hex(&Sha256::digest(&binary));
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(invalid(format!(
+            "checksum mismatch for {}: expected {}, got {}",
+            name, expected, actual
+        )));
+    }
+    let exe = env::current_exe()?;
+    replace(&exe, &binary)?;
+    println!("ndjson updated from {} to {}", current, tag);
+    Ok(())
+}
+
+/// Compares the semantic version of a release tag like `v0.3.0` to the installed one.
+fn compare(tag: &str, current: &str) -> io::Result<Ordering> {
+    let parse = |version: &str| {
+        Version::parse(version).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid version {}: {}", version, err),
+            )
+        })
+    };
+    Ok(parse(tag.trim_start_matches('v'))?.cmp(&parse(current)?))
+}
+
+/// Downloads a URL with curl, which is available on nearly all servers and handles proxies.
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["-fsSL", "-H", "Accept: application/vnd.github+json", url])
+        .output()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                err.kind(),
+                "ndjson self-update requires curl to be installed",
+            ),
+            _ => err,
+        })?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "can't download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Writes the new binary next to the running one and renames it over it, so the running process
+/// and concurrent invocations never see a partially written file. Windows doesn't allow replacing
+/// a running executable, but renaming it, so there it is first moved aside to `ndjson.old`, which
+/// the next update removes.
+fn replace(exe: &Path, binary: &[u8]) -> io::Result<()> {
+    let new = exe.with_extension("new");
+    fs::write(&new, binary)?;
+    fs::set_permissions(&new, fs::metadata(exe)?.permissions())?;
+    let mut written = Vec::new();
+    File::open(&new)?.read_to_end(&mut written)?;
+    if written != binary {
+        fs::remove_file(&new)?;
+        return Err(io::Error::other(format!("can't write {}", new.display())));
+    }
+    let failed = |err: io::Error| {
+        let _ = fs::remove_file(&new);
+        io::Error::new(
+            err.kind(),
+            format!("can't replace {}: {}", exe.display(), err),
+        )
+    };
+    if cfg!(windows) {
+        let old = exe.with_extension("old");
+        match fs::remove_file(&old) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(failed(err)),
+            _ => {}
+        }
+        fs::rename(exe, &old).map_err(failed)?;
+        return fs::rename(&new, exe).map_err(|err| {
+            let _ = fs::rename(&old, exe);
+            failed(err)
+        });
+    }
+    fs::rename(&new, exe).map_err(failed)
+}
+
+/// Returns the name of the release binary for a platform, like `ndjson-x86_64-unknown-linux-musl`.
+fn asset_name(arch: &str, os: &str) -> String {
+    let target = match os {
+        "linux" => "unknown-linux-musl",
+        "macos" => "apple-darwin",
+        "windows" => "pc-windows-msvc.exe",
+        os => os,
+    };
+    format!("ndjson-{}-{}", arch, target)
+}
+
+fn asset_url<'a>(release: &'a Value, name: &str) -> Option<&'a str> {
+    release["assets"]
+        .as_array()?
+        .iter()
+        .find(|asset| asset["name"] == name)?["browser_download_url"]
+        .as_str()
+}
+
+/// Returns the checksum of a file from the output of `sha256sum`, lines like `<hex>  <name>`.
+fn checksum<'a>(checksums: &'a str, name: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (sum, file) = line.split_once(char::is_whitespace)?;
+        (file.trim_start().trim_start_matches('*') == name).then_some(sum)
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(
+            hex(&Sha256::digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("v0.3.0", "0.2.0").unwrap(), Ordering::Greater);
+        assert_eq!(compare("0.2.0", "0.2.0").unwrap(), Ordering::Equal);
+        assert_eq!(compare("v0.2.0", "0.10.0").unwrap(), Ordering::Less);
+        assert_eq!(compare("v0.3.0-rc.1", "0.3.0").unwrap(), Ordering::Less);
+        assert!(compare("latest", "0.2.0").is_err());
+    }
+
+    #[test]
+    fn test_assets() {
+        let name = asset_name("x86_64", "linux");
+        assert_eq!(name, "ndjson-x86_64-unknown-linux-musl");
+        let release = json!({"tag_name": "v0.3.0", "assets": [
+            {"name": "SHA256SUMS", "browser_download_url": "https://example.com/sums"},
+            {"name": name, "browser_download_url": "https://example.com/bin"},
+        ]});
+        assert_eq!(asset_url(&release, &name), Some("https://example.com/bin"));
+        assert_eq!(asset_url(&release, "other"), None);
+        let sums =
+            "abc123  ndjson-aarch64-apple-darwin\ndef456 *ndjson-x86_64-unknown-linux-musl\n";
+        assert_eq!(checksum(sums, &name), Some("def456"));
+        assert_eq!(checksum(sums, "ndjson-armv7"), None);
+    }
+
+    #[test]
+    fn test_replace() {
+        let dir = env::temp_dir().join(format!("ndjson-update-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("ndjson.exe");
+        fs::write(&exe, b"old").unwrap();
+        for binary in [&b"new"[..], b"newer"] {
+            replace(&exe, binary).unwrap();
+            assert_eq!(fs::read(&exe).unwrap(), binary);
+        }
+        assert!(!exe.with_extension("new").exists());
+        assert_eq!(exe.with_extension("old").exists(), cfg!(windows));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
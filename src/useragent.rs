@@ -0,0 +1,228 @@
+use serde_json::{Map, Value};
+
+const BOTS: [&str; 4] = ["bot", "spider", "crawler", "slurp"];
+const CLIENTS: [&str; 6] = [
+    "curl/",
+    "Wget/",
+    "python-requests/",
+    "Go-http-client/",
+    "okhttp/",
+    "PostmanRuntime/",
+];
+
+/// Derives browser, OS and device fields from a user-agent field.
+pub struct UserAgentParser {
+    field: String,
+}
+
+impl UserAgentParser {
+    pub fn new(field: &str) -> Self {
+        UserAgentParser {
+            field: field.to_string(),
+        }
+    }
+
+    pub fn annotate(&self, object: &mut Map<String, Value>) -> bool {
+        let user_agent = match object.get(&self.field).and_then(Value::as_str) {
+            Some(user_agent) => parse(user_agent),
+            None => return false,
+        };
+        let mut fields = Vec::new();
+        if let Some(browser) = user_agent.browser {
+            fields.push((format!("{}_browser", self.field), Value::String(browser)));
+        }
+        if let Some(os) = user_agent.os {
+            fields.push((format!("{}_os", self.field), Value::String(os)));
+        }
+        fields.push((
+            format!("{}_device", self.field),
+            Value::String(user_agent.device.to_string()),
+        ));
+        crate::pipeline::insert_after(object, &self.field, fields);
+        true
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct UserAgent {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device: &'static str,
+}
+
+pub fn parse(user_agent: &str) -> UserAgent {
+    let lowercase = user_agent.to_lowercase();
+    if let Some(client) = CLIENTS
+        .iter()
+        .find(|client| user_agent.starts_with(*client))
+    {
+        return UserAgent {
+            browser: Some(named_version(user_agent, client)),
+            os: None,
+            device: "Client",
+        };
+    }
+    if BOTS.iter().any(|bot| lowercase.contains(bot)) {
+        return UserAgent {
+            browser: user_agent
+                .split([' ', ';', '('])
+                .find(|token| BOTS.iter().any(|bot| token.to_lowercase().contains(bot)))
+                .map(|token| token.trim_end_matches(')').to_string()),
+            os: None,
+            device: "Bot",
+        };
+    }
+    UserAgent {
+        browser: browser(user_agent),
+        os: os(user_agent),
+        device: device(user_agent),
+    }
+}
+
+fn browser(user_agent: &str) -> Option<String> {
+    let browsers = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("SamsungBrowser/", "Samsung Internet"),
+        ("Firefox/", "Firefox"),
+        ("FxiOS/", "Firefox"),
+        ("CriOS/", "Chrome"),
+        ("Chromium/", "Chromium"),
+        ("Chrome/", "Chrome"),
+        ("MSIE ", "Internet Explorer"),
+    ];
+    for (token, name) in browsers {
+        if let Some(version) = version_after(user_agent, token) {
+            return Some(format!("{} {}", name, major(version)));
+        }
+    }
+    if user_agent.contains("Trident/") {
+        let version = version_after(user_agent, "rv:").unwrap_or("11");
+        return Some(format!("Internet Explorer {}", major(version)));
+    }
+    if user_agent.contains("Safari/") {
+        return Some(match version_after(user_agent, "Version/") {
+            Some(version) => format!("Safari {}", major(version)),
+            None => "Safari".to_string(),
+        });
+    }
+    None
+}
+
+fn os(user_agent: &str) -> Option<String> {
+    if let Some(version) = version_after(user_agent, "Windows NT ") {
+        let name = match version {
+            "10.0" => "10",
+            "6.3" => "8.1",
+            "6.2" => "8",
+            "6.1" => "7",
+            "6.0" => "Vista",
+            "5.1" | "5.2" => "XP",
+            version => version,
+        };
+        return Some(format!("Windows {}", name));
+    }
+    for token in ["iPhone OS ", "CPU OS "] {
+        if let Some(version) = version_after(user_agent, token) {
+            return Some(format!("iOS {}", version.replace('_', ".")));
+        }
+    }
+    if let Some(version) = version_after(user_agent, "Android ") {
+        return Some(format!("Android {}", version));
+    }
+    if let Some(version) = version_after(user_agent, "Mac OS X ") {
+        return Some(format!("macOS {}", version.replace('_', ".")));
+    }
+    if user_agent.contains("CrOS") {
+        return Some("ChromeOS".to_string());
+    }
+    if user_agent.contains("Linux") {
+        return Some("Linux".to_string());
+    }
+    None
+}
+
+fn device(user_agent: &str) -> &'static str {
+    let android_tablet = user_agent.contains("Android") && !user_agent.contains("Mobile");
+    if user_agent.contains("iPad") || user_agent.contains("Tablet") || android_tablet {
+        "Tablet"
+    } else if user_agent.contains("Mobile") || user_agent.contains("iPhone") {
+        "Mobile"
+    } else {
+        "Desktop"
+    }
+}
+
+fn named_version(user_agent: &str, token: &str) -> String {
+    match version_after(user_agent, token) {
+        Some(version) => format!("{} {}", token.trim_end_matches('/'), version),
+        None => token.trim_end_matches('/').to_string(),
+    }
+}
+
+/// Returns the version number directly following the token.
+fn version_after<'a>(user_agent: &'a str, token: &str) -> Option<&'a str> {
+    let start = user_agent.find(token)? + token.len();
+    let rest = &user_agent[start..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+        .unwrap_or(rest.len());
+    Some(&rest[..end]).filter(|version| !version.is_empty())
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_agent(browser: Option<&str>, os: Option<&str>, device: &'static str) -> UserAgent {
+        UserAgent {
+            browser: browser.map(str::to_string),
+            os: os.map(str::to_string),
+            device,
+        }
+    }
+
+    #[test]
+    fn test_browsers() {
+        for (input, output) in [
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                user_agent(Some("Chrome 120"), Some("Windows 10"), "Desktop"),
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.2210.91",
+                user_agent(Some("Edge 120"), Some("Windows 10"), "Desktop"),
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1.2 Mobile/15E148 Safari/604.1",
+                user_agent(Some("Safari 17"), Some("iOS 17.1.2"), "Mobile"),
+            ),
+            (
+                "Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+                user_agent(Some("Firefox 121"), Some("Linux"), "Desktop"),
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                user_agent(Some("Chrome 120"), Some("Android 14"), "Tablet"),
+            ),
+        ] {
+            assert_eq!(parse(input), output);
+        }
+    }
+
+    #[test]
+    fn test_clients() {
+        assert_eq!(
+            parse("curl/8.4.0"),
+            user_agent(Some("curl 8.4.0"), None, "Client")
+        );
+        assert_eq!(
+            parse("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"),
+            user_agent(Some("Googlebot/2.1"), None, "Bot")
+        );
+    }
+}
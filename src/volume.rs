@@ -0,0 +1,199 @@
+use crate::{duration, timestamp};
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use clap::Args;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const MONTH_SECONDS: f64 = 30.0 * 24.0 * 60.0 * 60.0;
+
+/// Reports the bytes and records per group and time window, to find the noisiest loggers
+#[derive(Args, Debug)]
+pub struct VolumeOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+    /// Field to group the records by, like `service`
+    #[clap(long, value_name = "FIELD")]
+    by: Option<String>,
+    /// Splits the counts into time windows, like `1h`
+    #[clap(long, value_name = "DURATION", parse(try_from_str = duration::parse))]
+    window: Option<Duration>,
+    /// Adds the bytes per 30 days projected from the window or the time range of the input
+    #[clap(long)]
+    monthly: bool,
+}
+
+#[derive(Default)]
+struct Counts {
+    records: u64,
+    bytes: u64,
+}
+
+/// Counts lines and bytes by window start in epoch milliseconds and group value.
+struct Volume {
+    by: Option<String>,
+    window: Option<i64>,
+    counts: BTreeMap<(Option<i64>, Option<String>), Counts>,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+pub fn run(opt: &VolumeOpt) -> io::Result<()> {
+    let mut volume = Volume {
+        by: opt.by.clone(),
+        window: opt.window.map(|window| window.as_millis().max(1) as i64),
+        counts: BTreeMap::new(),
+        first: None,
+        last: None,
+    };
+    if opt.files.is_empty() {
+        volume.add_lines(io::stdin().lock())?;
+    }
+    for path in &opt.files {
+        volume.add_lines(BufReader::new(File::open(path)?))?;
+    }
+    crate::print_lines(volume.report(opt.monthly).into_iter().map(Ok))
+}
+
+impl Volume {
+    fn add_lines<R: BufRead>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? != 0 {
+            self.add_line(&line);
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Counts a line including its terminator, lines which aren't records have no group or window.
+    fn add_line(&mut self, line: &[u8]) {
+        let object = match serde_json::from_slice(line) {
+            Ok(Value::Object(object)) => object,
+            _ => Map::new(),
+        };
+        let time = timestamp::detect(&object);
+        if let Some(time) = time {
+            self.first = Some(self.first.map_or(time, |first| first.min(time)));
+            self.last = Some(self.last.map_or(time, |last| last.max(time)));
+        }
+        let window = match (self.window, time) {
+            (Some(window), Some(time)) => Some(time.timestamp_millis().div_euclid(window) * window),
+            _ => None,
+        };
+        let group = self
+            .by
+            .as_ref()
+            .and_then(|by| object.get(by))
+            .map(|value| match value {
+                Value::String(string) => string.clone(),
+                value => value.to_string(),
+            });
+        let counts = self.counts.entry((window, group)).or_default();
+        counts.records += 1;
+        counts.bytes += line.len() as u64;
+    }
+
+    /// Returns a line per window and group, the largest groups of each window first.
+    fn report(&self, monthly: bool) -> Vec<String> {
+        let span = match (self.window, self.first, self.last) {
+            (Some(window), _, _) => Some(window as f64 / 1000.0),
+            (None, Some(first), Some(last)) if last > first => {
+                Some((last - first).num_milliseconds() as f64 / 1000.0)
+            }
+            _ => None,
+        };
+        let mut rows: Vec<_> = self.counts.iter().collect();
+        rows.sort_by(|((window, _), a), ((other, _), b)| {
+            window.cmp(other).then(b.bytes.cmp(&a.bytes))
+        });
+        rows.into_iter()
+            .map(|((window, group), counts)| {
+                let mut row = Map::new();
+                if let Some(window) = window {
+                    let start = Utc.timestamp_millis_opt(*window).unwrap();
+                    let start = start.to_rfc3339_opts(SecondsFormat::Secs, true);
+                    row.insert("window".to_string(), Value::from(start));
+                }
+                if let Some(by) = &self.by {
+                    row.insert(by.clone(), group.clone().map_or(Value::Null, Value::from));
+                }
+                row.insert("records".to_string(), Value::from(counts.records));
+                row.insert("bytes".to_string(), Value::from(counts.bytes));
+                if monthly {
+                    let projected = span.map(|span| {
+                        Value::from((counts.bytes as f64 * MONTH_SECONDS / span) as u64)
+                    });
+                    row.insert(
+                        "monthly_bytes".to_string(),
+                        projected.unwrap_or(Value::Null),
+                    );
+                }
+                Value::Object(row).to_string()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(by: Option<&str>, window: Option<i64>, lines: &[&str]) -> Volume {
+        let mut volume = Volume {
+            by: by.map(str::to_string),
+            window,
+            counts: BTreeMap::new(),
+            first: None,
+            last: None,
+        };
+        for line in lines {
+            volume.add_line(format!("{}\n", line).as_bytes());
+        }
+        volume
+    }
+
+    #[test]
+    fn test_report() {
+        let volume = volume(
+            Some("service"),
+            None,
+            &[
+                r#"{"ts":"2023-01-01T00:00:00Z","service":"web"}"#,
+                r#"{"ts":"2023-01-01T01:00:00Z","service":"api","msg":"long"}"#,
+                r#"{"ts":"2023-01-02T00:00:00Z","service":"api"}"#,
+                "text",
+            ],
+        );
+        assert_eq!(
+            volume.report(true),
+            [
+                r#"{"service":"api","records":2,"bytes":105,"monthly_bytes":3150}"#,
+                r#"{"service":"web","records":1,"bytes":46,"monthly_bytes":1380}"#,
+                r#"{"service":null,"records":1,"bytes":5,"monthly_bytes":150}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_windows() {
+        let volume = volume(
+            None,
+            Some(3_600_000),
+            &[
+                r#"{"ts":"2023-01-01T00:10:00Z"}"#,
+                r#"{"ts":"2023-01-01T00:50:00Z"}"#,
+                r#"{"ts":"2023-01-01T01:00:00Z"}"#,
+            ],
+        );
+        assert_eq!(
+            volume.report(false),
+            [
+                r#"{"window":"2023-01-01T00:00:00Z","records":2,"bytes":60}"#,
+                r#"{"window":"2023-01-01T01:00:00Z","records":1,"bytes":30}"#,
+            ]
+        );
+    }
+}
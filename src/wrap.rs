@@ -0,0 +1,179 @@
+use clap::Args;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// Writes the records as one JSON array, the inverse of `unwrap`
+#[derive(Args, Debug)]
+pub struct WrapOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+}
+
+/// Writes the elements of JSON arrays as one record per line, the inverse of `wrap`
+#[derive(Args, Debug)]
+pub struct UnwrapOpt {
+    /// Files to read instead of stdin
+    files: Vec<PathBuf>,
+}
+
+pub fn run_wrap(opt: &WrapOpt) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut wrapper = Wrapper::new(stdout.lock());
+    if opt.files.is_empty() {
+        wrapper.add_lines(io::stdin().lock())?;
+    }
+    for path in &opt.files {
+        wrapper.add_lines(BufReader::new(File::open(path)?))?;
+    }
+    wrapper.finish()
+}
+
+pub fn run_unwrap(opt: &UnwrapOpt) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    if opt.files.is_empty() {
+        unwrap(io::stdin().lock(), &mut stdout)?;
+    }
+    for path in &opt.files {
+        unwrap(BufReader::new(File::open(path)?), &mut stdout)?;
+    }
+    Ok(())
+}
+
+/// Writes lines as the elements of an array as they are read, one per line.
+struct Wrapper<W: Write> {
+    out: W,
+    elements: u64,
+    skipped: u64,
+}
+
+impl<W: Write> Wrapper<W> {
+    fn new(out: W) -> Self {
+        Wrapper {
+            out,
+            elements: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Adds the JSON lines, skipping blank lines and counting the other ones which aren't JSON.
+    fn add_lines<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if serde_json::from_str::<Value>(line).is_err() {
+                self.skipped += 1;
+                continue;
+            }
+            let separator = match self.elements {
+                0 => "[\n",
+                _ => ",\n",
+            };
+            write!(self.out, "{}{}", separator, line)?;
+            self.elements += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        match self.elements {
+            0 => writeln!(self.out, "[]")?,
+            _ => writeln!(self.out, "\n]")?,
+        }
+        if self.skipped > 0 {
+            eprintln!("ndjson: skipped {} lines which aren't JSON", self.skipped);
+        }
+        self.out.flush()
+    }
+}
+
+/// Writes the elements of one or more top-level arrays as compact lines, reading one element at a
+/// time so arrays larger than memory can be converted.
+fn unwrap<R: BufRead, W: Write>(reader: R, out: &mut W) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut bytes = reader.bytes();
+    let mut element = Vec::new();
+    loop {
+        match bytes.next().transpose()? {
+            None => return Ok(()),
+            Some(byte) if byte.is_ascii_whitespace() => continue,
+            Some(b'[') => {}
+            Some(_) => return Err(invalid("expected a JSON array")),
+        }
+        let (mut depth, mut string, mut escaped) = (0usize, false, false);
+        loop {
+            let byte = bytes
+                .next()
+                .transpose()?
+                .ok_or_else(|| invalid("unterminated JSON array"))?;
+            if string {
+                string = escaped || byte != b'"';
+                escaped = !escaped && byte == b'\\';
+            } else if depth == 0 && (byte == b',' || byte == b']') {
+                if !element.iter().all(u8::is_ascii_whitespace) {
+                    let value: Value = serde_json::from_slice(&element)?;
+                    writeln!(out, "{}", value)?;
+                }
+                element.clear();
+                if byte == b']' {
+                    break;
+                }
+                continue;
+            } else {
+                match byte {
+                    b'"' => string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            element.push(byte);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        let mut out = Vec::new();
+        let mut empty = Wrapper::new(&mut out);
+        empty.add_lines("".as_bytes()).unwrap();
+        empty.finish().unwrap();
+        assert_eq!(out, b"[]\n");
+        let mut out = Vec::new();
+        let mut wrapper = Wrapper::new(&mut out);
+        wrapper
+            .add_lines("{\"a\":1}\n\nplain\n{\"a\":2}\n".as_bytes())
+            .unwrap();
+        assert_eq!(wrapper.skipped, 1);
+        wrapper.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n{\"a\":1},\n{\"a\":2}\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let unwrapped = |input: &str| {
+            let mut out = Vec::new();
+            unwrap(input.as_bytes(), &mut out).map(|_| String::from_utf8(out).unwrap())
+        };
+        assert_eq!(
+            unwrapped("[\n  {\"a\": [1, 2], \"b\": \"x,]\\\"\"},\n  {\"a\": {}}\n]\n[3]").unwrap(),
+            "{\"a\":[1,2],\"b\":\"x,]\\\"\"}\n{\"a\":{}}\n3\n"
+        );
+        assert_eq!(unwrapped(" [] ").unwrap(), "");
+        assert!(unwrapped("{\"a\": 1}").is_err());
+        assert!(unwrapped("[{\"a\": 1},").is_err());
+        assert!(unwrapped("[{\"a\": }]").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use serde_json::{Map, Value};
+
+/// Nesting of elements parsed at most, so a deeply nested document can't overflow the stack.
+/// Deeper documents are left unparsed.
+const MAX_DEPTH: usize = 64;
+
+/// Converts an XML document into JSON like `{"root": {"@id": "1", "item": ["a", "b"]}}`, with
+/// attributes under `@` keys, repeated elements as arrays and the text of elements with
+/// attributes or children under `#text`. Elements without any content are `null`.
+pub fn parse(text: &str) -> Option<Value> {
+    let mut parser = Parser {
+        rest: text,
+        depth: MAX_DEPTH,
+    };
+    parser.skip_misc()?;
+    let (name, value) = parser.element()?;
+    parser.skip_misc()?;
+    if !parser.rest.is_empty() {
+        return None;
+    }
+    let mut root = Map::new();
+    root.insert(name, value);
+    Some(Value::Object(root))
+}
+
+/// Replaces the XML string at a dotted path like `request.body` by its JSON structure, returning
+/// whether it was replaced.
+pub fn parse_field(object: &mut Map<String, Value>, path: &str) -> bool {
+    let mut keys = path.split('.');
+    let mut value = match keys.next().and_then(|key| object.get_mut(key)) {
+        Some(value) => value,
+        None => return false,
+    };
+    for key in keys {
+        value = match value.get_mut(key) {
+            Some(value) => value,
+            None => return false,
+        };
+    }
+    match value.as_str().and_then(parse) {
+        Some(parsed) => {
+            *value = parsed;
+            true
+        }
+        None => false,
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    /// Nesting of elements left below the current one.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace, the XML declaration, processing instructions, comments and the doctype.
+    fn skip_misc(&mut self) -> Option<()> {
+        loop {
+            self.rest = self.rest.trim_start();
+            let terminator = if self.rest.starts_with("<?") {
+                "?>"
+            } else if self.rest.starts_with("<!--") {
+                "-->"
+            } else if self.rest.starts_with("<!") && !self.rest.starts_with("<![CDATA[") {
+                ">"
+            } else {
+                return Some(());
+            };
+            self.skip_past(terminator)?;
+        }
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Option<&str> {
+        let end = self.rest.find(terminator)?;
+        let skipped = &self.rest[..end];
+        self.rest = &self.rest[end + terminator.len()..];
+        Some(skipped)
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || "/>=".contains(c))
+            .unwrap_or(self.rest.len());
+        let (name, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    /// Parses an element starting at `<` into its name and value.
+    fn element(&mut self) -> Option<(String, Value)> {
+        self.depth = self.depth.checked_sub(1)?;
+        self.rest = self.rest.strip_prefix('<')?;
+        let name = self.name()?;
+        let mut object = Map::new();
+        loop {
+            self.rest = self.rest.trim_start();
+            if let Some(rest) = self.rest.strip_prefix("/>") {
+                self.rest = rest;
+                self.depth += 1;
+                return Some((name, content(object, String::new())));
+            }
+            if let Some(rest) = self.rest.strip_prefix('>') {
+                self.rest = rest;
+                break;
+            }
+            let attribute = self.name()?;
+            self.rest = self.rest.trim_start().strip_prefix('=')?.trim_start();
+            let quote = self
+                .rest
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')?;
+            self.rest = &self.rest[1..];
+            let value = unescape(self.skip_past(&quote.to_string())?)?;
+            object.insert(format!("@{}", attribute), Value::String(value));
+        }
+        let mut text = String::new();
+        loop {
+            if let Some(rest) = self.rest.strip_prefix("</") {
+                self.rest = rest;
+                if self.name()? != name {
+                    return None;
+                }
+                self.rest = self.rest.trim_start().strip_prefix('>')?;
+                self.depth += 1;
+                return Some((name, content(object, text.trim().to_string())));
+            } else if let Some(rest) = self.rest.strip_prefix("<![CDATA[") {
+                self.rest = rest;
+                text.push_str(self.skip_past("]]>")?);
+            } else if self.rest.starts_with("<!--") || self.rest.starts_with("<?") {
+                self.skip_misc()?;
+            } else if self.rest.starts_with('<') {
+                let (child, value) = self.element()?;
+                match object.get_mut(&child) {
+                    Some(Value::Array(values)) => values.push(value),
+                    Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                    None => {
+                        object.insert(child, value);
+                    }
+                }
+            } else {
+                let end = self.rest.find('<')?;
+                text.push_str(&unescape(&self.rest[..end])?);
+                self.rest = &self.rest[end..];
+            }
+        }
+    }
+}
+
+/// Returns the value of an element from its attributes and children, and its text.
+fn content(mut object: Map<String, Value>, text: String) -> Value {
+    match (object.is_empty(), text.is_empty()) {
+        (true, true) => Value::Null,
+        (true, false) => Value::String(text),
+        (false, true) => Value::Object(object),
+        (false, false) => {
+            object.insert("#text".to_string(), Value::String(text));
+            Value::Object(object)
+        }
+    }
+}
+
+/// Replaces the predefined entities and character references, failing on unknown ones.
+fn unescape(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';')? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            entity => {
+                let code = match entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => entity.strip_prefix('#')?.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        };
+        unescaped.push(c);
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let xml = r#"<?xml version="1.0"?>
+            <soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/">
+              <!-- request -->
+              <soap:Body>
+                <Order id='7' status="new">
+                  <Item>a &amp; b</Item><Item>&#x41;<![CDATA[<c>]]></Item>
+                  <Note lang="en">fragile</Note>
+                  <Empty/>
+                </Order>
+              </soap:Body>
+            </soap:Envelope>"#;
+        assert_eq!(
+            parse(xml),
+            Some(json!({"soap:Envelope": {
+                "@xmlns:soap": "http://schemas.xmlsoap.org/soap/envelope/",
+                "soap:Body": {"Order": {
+                    "@id": "7",
+                    "@status": "new",
+                    "Item": ["a & b", "A<c>"],
+                    "Note": {"@lang": "en", "#text": "fragile"},
+                    "Empty": null,
+                }},
+            }}))
+        );
+        assert_eq!(parse("<a>1</b>"), None);
+        assert_eq!(parse("<a>1</a><b/>"), None);
+        assert_eq!(parse("<a>&bogus;</a>"), None);
+        assert_eq!(parse("not xml"), None);
+        let nested = |depth| "<a>".repeat(depth) + &"</a>".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_some());
+        assert_eq!(parse(&nested(MAX_DEPTH + 1)), None);
+        assert_eq!(parse(&"<a>".repeat(1 << 18)), None);
+    }
+
+    #[test]
+    fn test_parse_field() {
+        let mut record = json!({"req": {"body": "<a><b>1</b></a>"}, "msg": "<x"});
+        let object = record.as_object_mut().unwrap();
+        assert!(parse_field(object, "req.body"));
+        assert!(!parse_field(object, "msg"));
+        assert!(!parse_field(object, "missing.path"));
+        assert_eq!(
+            record,
+            json!({"req": {"body": {"a": {"b": "1"}}}, "msg": "<x"})
+        );
+    }
+}